@@ -47,4 +47,45 @@ export async function registerCanister(
     console.error('Error registering canister:', error);
     return { success: false, error: error instanceof Error ? error.message : 'Unknown error' };
   }
-} 
\ No newline at end of file
+} 
+/**
+ * Register several canisters, e.g. a token and its miners, in one request
+ * @param principal The principal ID of the user who created the canisters
+ * @param canisters The canister IDs and types to register
+ * @param allOrNothing Register none of the canisters if any of them is invalid
+ * @returns A promise that resolves to the API response with a result per canister
+ */
+export async function registerCanisters(
+  principal: string,
+  canisters: { canisterId: string; canisterType: 'token_backend' | 'miner' | 'ledger' }[],
+  allOrNothing = true
+): Promise<any> {
+  try {
+    const response = await fetch(`${CANISTER_API_URL}/canisters/batch`, {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/json',
+      },
+      body: JSON.stringify({
+        all_or_nothing: allOrNothing,
+        canisters: canisters.map(({ canisterId, canisterType }) => ({
+          principal,
+          canister_id: canisterId,
+          canister_type: canisterType === 'token_backend' ? 'token' : canisterType,
+          module_hash: null
+        }))
+      })
+    });
+
+    const data = await response.json();
+    if (!response.ok) {
+      console.error(`Error registering canisters: ${data.message}`);
+      return { success: false, error: data.message, data };
+    }
+
+    return { success: true, data };
+  } catch (error) {
+    console.error('Error registering canisters:', error);
+    return { success: false, error: error instanceof Error ? error.message : 'Unknown error' };
+  }
+}
@@ -51,7 +51,7 @@ fi
 echo -e "\n${YELLOW}=== Testing System Endpoints ===${NC}"
 call_api "GET" "/system/status" "" "Get system status"
 call_api "POST" "/system/refresh" "" "Trigger system refresh"
-call_api "GET" "/system/interfaces" "" "Generate interface files"
+call_api "GET" "/interfaces" "" "List interfaces"
 
 # Test Canister endpoints
 echo -e "\n${YELLOW}=== Testing Canister Endpoints ===${NC}"
@@ -86,6 +86,7 @@ echo -e "\n${YELLOW}=== Testing Miner Endpoints ===${NC}"
 call_api "GET" "/miners" "" "Get all miners"
 call_api "GET" "/miners/test-canister-1" "" "Get specific miner"
 call_api "GET" "/miners/test-canister-1/stats" "" "Get miner stats"
+call_api "GET" "/miners/test-canister-1/projection" "" "Get miner reward projection"
 call_api "GET" "/miners/by-token/test-canister-1" "" "Get miners by token"
 call_api "GET" "/miners/stats" "" "Get all mining stats"
 call_api "DELETE" "/miners/test-canister-1" "" "Delete miner"
@@ -0,0 +1,201 @@
+#!/bin/bash
+
+# PostgreSQL backend test for the ICP Canister Registry
+# Starts a throwaway PostgreSQL cluster (unless DATABASE_URL is set), runs the server
+# against it twice and checks that registry and admin data are shared through the database.
+#
+# Requires: initdb and pg_ctl (or DATABASE_URL), curl, jq and a built server binary.
+# initdb refuses to run as root, run this as a regular user or pass DATABASE_URL.
+
+set -e
+
+# Color codes for output
+GREEN='\033[0;32m'
+YELLOW='\033[1;33m'
+BLUE='\033[0;34m'
+RED='\033[0;31m'
+NC='\033[0m' # No Color
+
+SCRIPT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" && pwd )"
+ROOT_DIR="$( cd "${SCRIPT_DIR}/../.." && pwd )"
+SERVER_BIN="${SERVER_BIN:-${ROOT_DIR}/target/debug/https-outcall}"
+BASE_URL="http://localhost:8080"
+PG_PORT="${PG_PORT:-55432}"
+ADMIN_PASSWORD="postgres-test-password"
+
+TOKEN_ID="ryjl3-tyaaa-aaaaa-aaaba-cai"
+MINER_ID="rrkah-fqaaa-aaaaa-aaaaq-cai"
+LEDGER_ID="mxzaz-hqaaa-aaaar-qaada-cai"
+PRINCIPAL="2vxsx-fae"
+
+WORK_DIR=$(mktemp -d)
+SERVER_PID=""
+STARTED_CLUSTER=false
+FAILURES=0
+
+cleanup() {
+    if [ -n "$SERVER_PID" ]; then
+        kill "$SERVER_PID" 2>/dev/null || true
+        wait "$SERVER_PID" 2>/dev/null || true
+    fi
+    if [ "$STARTED_CLUSTER" = true ]; then
+        pg_ctl -D "$WORK_DIR/pgdata" -m fast stop > /dev/null 2>&1 || true
+    fi
+    rm -rf "$WORK_DIR"
+}
+trap cleanup EXIT
+
+# Function to print colored section headers
+section() {
+    echo -e "\n${BLUE}== $1 ==${NC}"
+}
+
+# Function to record a check result
+check() {
+    local description=$1
+    local actual=$2
+    local expected=$3
+    if [ "$actual" = "$expected" ]; then
+        echo -e "${GREEN}✓ $description${NC}"
+    else
+        echo -e "${RED}✗ $description (expected '$expected', got '$actual')${NC}"
+        FAILURES=$((FAILURES + 1))
+    fi
+}
+
+start_server() {
+    local log_file=$1
+    (cd "$WORK_DIR" && DATABASE_URL="$DATABASE_URL" ADMIN_PASSWORD="$ADMIN_PASSWORD" \
+        BACKUP_INTERVAL_SECS=0 exec "$SERVER_BIN" > "$log_file" 2>&1) &
+    SERVER_PID=$!
+    for _ in $(seq 1 50); do
+        if curl -s "$BASE_URL/health/live" > /dev/null; then
+            return 0
+        fi
+        sleep 0.2
+    done
+    echo -e "${RED}Server did not start, log:${NC}"
+    cat "$log_file"
+    exit 1
+}
+
+stop_server() {
+    kill "$SERVER_PID"
+    wait "$SERVER_PID" 2>/dev/null || true
+    SERVER_PID=""
+}
+
+login() {
+    curl -s -X POST "$BASE_URL/admin/login" \
+        -H "Content-Type: application/json" \
+        -d "{\"username\": \"admin\", \"password\": \"$ADMIN_PASSWORD\"}" | jq -r '.data.access_token'
+}
+
+if [ ! -x "$SERVER_BIN" ]; then
+    echo -e "${YELLOW}Building server...${NC}"
+    (cd "$ROOT_DIR" && cargo build)
+fi
+
+if curl -s "$BASE_URL/health/live" > /dev/null; then
+    echo -e "${RED}Something is already listening on $BASE_URL, stop it first${NC}"
+    exit 1
+fi
+
+# Start a throwaway cluster unless a database was given
+if [ -z "$DATABASE_URL" ]; then
+    section "Starting PostgreSQL on port $PG_PORT"
+    initdb -D "$WORK_DIR/pgdata" -U postgres --auth=trust > /dev/null
+    pg_ctl -D "$WORK_DIR/pgdata" -o "-p $PG_PORT -k $WORK_DIR" -l "$WORK_DIR/postgres.log" -w start > /dev/null
+    STARTED_CLUSTER=true
+    DATABASE_URL="postgres://postgres@localhost:$PG_PORT/postgres"
+fi
+
+section "First instance"
+start_server "$WORK_DIR/server1.log"
+
+check "Readiness reports the PostgreSQL backend" \
+    "$(curl -s "$BASE_URL/health/ready" | jq -r '.database.detail')" "connected (postgres)"
+check "Bootstrap admin created" \
+    "$(grep -c "Bootstrap admin 'admin' created" "$WORK_DIR/server1.log")" "1"
+
+check "Register a token canister" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X POST "$BASE_URL/canisters" \
+        -H "Content-Type: application/json" \
+        -d "{\"principal\": \"$PRINCIPAL\", \"canister_id\": \"$TOKEN_ID\", \"canister_type\": \"token\", \"module_hash\": null}")" "201"
+check "Duplicate registration rejected" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X POST "$BASE_URL/canisters" \
+        -H "Content-Type: application/json" \
+        -d "{\"principal\": \"$PRINCIPAL\", \"canister_id\": \"$TOKEN_ID\", \"canister_type\": \"token\", \"module_hash\": null}")" "400"
+check "Batch registration" \
+    "$(curl -s -X POST "$BASE_URL/canisters/batch" \
+        -H "Content-Type: application/json" \
+        -d "{\"canisters\": [{\"principal\": \"$PRINCIPAL\", \"canister_id\": \"$MINER_ID\", \"canister_type\": \"miner\", \"module_hash\": null}]}" \
+        | jq -r '.data[0].status')" "registered"
+check "Canisters listed" "$(curl -s "$BASE_URL/canisters" | jq '.data | length')" "2"
+check "Principal portfolio" \
+    "$(curl -s "$BASE_URL/principals/$PRINCIPAL" | jq '.data.canister_count')" "2"
+check "Status counts canisters" "$(curl -s "$BASE_URL/system/status" | jq '.data.canisters_count')" "2"
+
+TOKEN=$(login)
+check "Admin login" "$([ "$TOKEN" != "null" ] && echo ok)" "ok"
+AUTH="Authorization: Bearer $TOKEN"
+
+check "Soft delete" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X DELETE -H "$AUTH" "$BASE_URL/admin/canisters/$MINER_ID")" "200"
+check "Trash lists the deleted canister" \
+    "$(curl -s -H "$AUTH" "$BASE_URL/admin/canisters/trash" | jq -r '.data[0].canister_id')" "$MINER_ID"
+check "Restore" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X POST -H "$AUTH" "$BASE_URL/admin/canisters/$MINER_ID/restore")" "200"
+
+EXPORT=$(curl -s -H "$AUTH" "$BASE_URL/admin/export")
+check "Export contains the canisters" "$(echo "$EXPORT" | jq '.canisters | length')" "2"
+check "Import dry run" \
+    "$(echo "$EXPORT" | curl -s -X POST -H "$AUTH" -H "Content-Type: application/json" \
+        --data-binary @- "$BASE_URL/admin/import?dry_run=true" | jq -r '.success')" "true"
+check "Register a ledger canister" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X POST "$BASE_URL/canisters" \
+        -H "Content-Type: application/json" \
+        -d "{\"principal\": \"$PRINCIPAL\", \"canister_id\": \"$LEDGER_ID\", \"canister_type\": \"ledger\", \"module_hash\": null}")" "201"
+check "Import ledger info" \
+    "$(curl -s -X POST -H "$AUTH" -H "Content-Type: application/json" "$BASE_URL/admin/import" \
+        -d "{\"version\": 1, \"exported_at\": 0, \"ledger_info\": [{\"canister_id\": \"$LEDGER_ID\", \"name\": \"ckBTC\", \"symbol\": \"ckBTC\", \"decimals\": 8, \"fee\": \"10\", \"total_supply\": \"340282366920938463463374607431768211455\", \"logo\": null, \"supported_standards\": \"ICRC-1,ICRC-2\", \"metadata\": \"{}\", \"last_updated\": 0}]}" \
+        | jq -r '.data.applied')" "true"
+check "Ledger info stored" \
+    "$(curl -s "$BASE_URL/ledgers/$LEDGER_ID" | jq -r '.data.total_supply')" "340282366920938463463374607431768211455"
+check "Status counts ledgers" "$(curl -s "$BASE_URL/system/status" | jq '.data.ledgers_count')" "1"
+check "Account history of an unindexed ledger" \
+    "$(curl -s "$BASE_URL/ledgers/$LEDGER_ID/accounts/$PRINCIPAL/transactions" | jq '.data.total')" "0"
+check "Backups are not available" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X POST -H "$AUTH" "$BASE_URL/admin/backups")" "501"
+check "Pool metrics exported" \
+    "$(curl -s "$BASE_URL/metrics" | grep -c '^db_pool_max_size')" "1"
+
+stop_server
+
+section "Second instance on the same database"
+start_server "$WORK_DIR/server2.log"
+
+check "No second bootstrap admin" \
+    "$(grep -c "Bootstrap admin" "$WORK_DIR/server2.log" || true)" "0"
+check "Canisters still present" "$(curl -s "$BASE_URL/canisters" | jq '.data | length')" "3"
+
+TOKEN=$(login)
+AUTH="Authorization: Bearer $TOKEN"
+check "Audit log kept" \
+    "$(curl -s -H "$AUTH" "$BASE_URL/admin/audit-log?action=canister.restore" | jq '.data.total')" "1"
+check "Permanent delete" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X DELETE -H "$AUTH" "$BASE_URL/admin/canisters/$MINER_ID?permanent=true")" "200"
+check "Permanent delete of a ledger" \
+    "$(curl -s -o /dev/null -w '%{http_code}' -X DELETE -H "$AUTH" "$BASE_URL/admin/canisters/$LEDGER_ID?permanent=true")" "200"
+check "Statistics after delete" "$(curl -s "$BASE_URL/system/status" | jq '.data.canisters_count')" "1"
+check "Ledger info deleted with its canister" "$(curl -s "$BASE_URL/system/status" | jq '.data.ledgers_count')" "0"
+
+stop_server
+
+section "Results"
+if [ $FAILURES -eq 0 ]; then
+    echo -e "${GREEN}All PostgreSQL backend checks passed${NC}"
+else
+    echo -e "${RED}$FAILURES checks failed${NC}"
+    exit 1
+fi
@@ -1,55 +1,169 @@
-use actix_web::{web, HttpRequest, HttpResponse};
-use log::{error, info};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use actix_web::http::header::HeaderMap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::Utc;
+use log::{error, warn};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::OnceLock;
 
-use crate::db::DbPool;
-use crate::db::models::admin::Admin;
+use crate::db::models::admin::{Admin, Permission};
 use crate::api::handlers::ApiResponse;
+use crate::db::repository::Repository;
 
-/// Authenticate an admin user by API key
-/// Returns Ok(admin) if authentication succeeds, or Err(HttpResponse) if it fails
-pub async fn authenticate_admin(
-    req: &HttpRequest,
-    db_pool: &web::Data<DbPool>,
-) -> Result<Admin, HttpResponse> {
-    // Check for API key in headers
-    let api_key = match req.headers().get("X-API-KEY") {
-        Some(key) => match key.to_str() {
-            Ok(key_str) => key_str,
-            Err(_) => {
-                return Err(HttpResponse::Unauthorized()
-                    .json(ApiResponse::<()>::error("Invalid API key format")));
-            }
-        },
-        _ => {
-            return Err(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("API key required")));
+/// Token type of short-lived access tokens sent as `Authorization: Bearer`
+pub const ACCESS_TOKEN_TYPE: &str = "access";
+/// Token type of refresh tokens exchanged at `/admin/refresh`
+pub const REFRESH_TOKEN_TYPE: &str = "refresh";
+
+// Key used to sign admin session tokens
+static SIGNING_KEY: OnceLock<hmac::Key> = OnceLock::new();
+
+/// Claims carried by a signed admin session token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenClaims {
+    /// Session ID
+    pub sid: String,
+    /// Admin ID
+    pub sub: String,
+    /// Token type, access or refresh
+    pub typ: String,
+    /// Refresh token ID, only set on refresh tokens
+    pub jti: Option<String>,
+    /// Expiry as a unix timestamp
+    pub exp: i64,
+}
+
+/// Why a request could not be authenticated
+#[derive(Debug)]
+pub enum AuthError {
+    Unauthorized(String),
+    Forbidden(String),
+    Internal(String),
+}
+
+impl AuthError {
+    pub fn into_response(self) -> HttpResponse {
+        match self {
+            AuthError::Unauthorized(message) => HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::error(&message)),
+            AuthError::Forbidden(message) => HttpResponse::Forbidden()
+                .json(ApiResponse::<()>::error(&message)),
+            AuthError::Internal(message) => HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(&message)),
         }
-    };
+    }
+}
 
-    // Get database connection
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return Err(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Database error")));
+// Get the token signing key, from ADMIN_TOKEN_SECRET or generated for this process
+fn signing_key() -> &'static hmac::Key {
+    SIGNING_KEY.get_or_init(|| match env::var("ADMIN_TOKEN_SECRET") {
+        Ok(secret) if !secret.is_empty() => hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+        _ => {
+            warn!("ADMIN_TOKEN_SECRET not set, using a random key; admin sessions will not survive restarts or be shared between instances");
+            let mut secret = [0u8; 32];
+            SystemRandom::new()
+                .fill(&mut secret)
+                .expect("Failed to generate token signing key");
+            hmac::Key::new(hmac::HMAC_SHA256, &secret)
         }
+    })
+}
+
+/// Sign a set of claims into a bearer token
+pub fn issue_token(claims: &TokenClaims) -> String {
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap_or_default());
+    let signature = hmac::sign(signing_key(), payload.as_bytes());
+    format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+}
+
+/// Verify a token's signature, type and expiry, returning its claims
+pub fn verify_token(token: &str, expected_type: &str) -> Option<TokenClaims> {
+    let (payload, signature) = token.split_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    hmac::verify(signing_key(), payload.as_bytes(), &signature).ok()?;
+
+    let claims: TokenClaims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+    if claims.typ != expected_type || claims.exp <= Utc::now().timestamp() {
+        return None;
+    }
+
+    Some(claims)
+}
+
+/// Resolve the admin making a request from a bearer token or an `X-API-KEY` header
+pub async fn resolve_admin(headers: &HeaderMap, repo: &dyn Repository) -> Result<Admin, AuthError> {
+    // Prefer a session token when one is presented
+    if let Some(authorization) = headers.get("Authorization") {
+        let token = authorization
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| AuthError::Unauthorized("Invalid authorization header".to_string()))?;
+        return resolve_admin_from_token(token.trim(), repo).await;
+    }
+
+    // Check for API key in headers
+    let api_key = match headers.get("X-API-KEY") {
+        Some(key) => key
+            .to_str()
+            .map_err(|_| AuthError::Unauthorized("Invalid API key format".to_string()))?,
+        _ => return Err(AuthError::Unauthorized("API key or bearer token required".to_string())),
     };
 
     // Check if the API key is valid
-    match Admin::find_by_api_key(&conn, api_key) {
-        Ok(Some(admin)) => {
-            info!("Admin authenticated: {}", admin.username);
-            Ok(admin)
+    match repo.admins().find_by_api_key(api_key).await {
+        Ok(Some(admin)) => Ok(admin),
+        Ok(_) => Err(AuthError::Unauthorized("Invalid API key".to_string())),
+        Err(e) => {
+            error!("DB error while verifying API key: {}", e);
+            Err(AuthError::Internal("Database error".to_string()))
         }
-        Ok(_) => {
-            Err(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Invalid API key")))
+    }
+}
+
+// Resolve an admin from an access token, checking that its session is still open
+async fn resolve_admin_from_token(token: &str, repo: &dyn Repository) -> Result<Admin, AuthError> {
+    let claims = verify_token(token, ACCESS_TOKEN_TYPE)
+        .ok_or_else(|| AuthError::Unauthorized("Invalid or expired token".to_string()))?;
+
+    let session = match repo.sessions().find_by_id(&claims.sid).await {
+        Ok(Some(session)) if session.is_valid() && session.admin_id == claims.sub => session,
+        Ok(_) => return Err(AuthError::Unauthorized("Session expired or revoked".to_string())),
+        Err(e) => {
+            error!("DB error while verifying session: {}", e);
+            return Err(AuthError::Internal("Database error".to_string()));
         }
+    };
+
+    match repo.admins().find_by_id(&session.admin_id).await {
+        Ok(Some(admin)) if admin.is_active => Ok(admin),
+        Ok(_) => Err(AuthError::Unauthorized("Admin account is inactive".to_string())),
         Err(e) => {
-            error!("DB error while verifying API key: {}", e);
-            Err(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Database error")))
+            error!("DB error while loading admin: {}", e);
+            Err(AuthError::Internal("Database error".to_string()))
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Get the admin authenticated by the `AdminAuth` middleware
+pub fn current_admin(req: &HttpRequest) -> Result<Admin, AuthError> {
+    req.extensions()
+        .get::<Admin>()
+        .cloned()
+        .ok_or_else(|| AuthError::Unauthorized("Authentication required".to_string()))
+}
+
+/// Get the authenticated admin, checking that its role grants a permission
+pub fn require_permission(req: &HttpRequest, permission: Permission) -> Result<Admin, AuthError> {
+    let admin = current_admin(req)?;
+
+    if !admin.role.has_permission(permission) {
+        warn!("Admin {} with role {} denied {:?}", admin.username, admin.role, permission);
+        return Err(AuthError::Forbidden(format!("Role {} is not allowed to perform this action", admin.role)));
+    }
+
+    Ok(admin)
+}
@@ -0,0 +1,106 @@
+use actix_web::HttpRequest;
+use log::warn;
+use std::env;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+// Networks whose X-Forwarded-For entries are trusted, e.g. the load balancer
+static TRUSTED_PROXIES: OnceLock<Vec<IpNetwork>> = OnceLock::new();
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8`
+#[derive(Debug, Clone, Copy)]
+struct IpNetwork {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix_len) = match value.split_once('/') {
+            Some((addr, prefix_len)) => (addr.parse::<IpAddr>().ok()?, Some(prefix_len.parse::<u8>().ok()?)),
+            None => (value.parse::<IpAddr>().ok()?, None),
+        };
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        if prefix_len > max_len {
+            return None;
+        }
+        Some(Self { addr, prefix_len })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, normalize(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// Treat IPv4-mapped IPv6 addresses (we bind dual-stack) as IPv4
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+
+// Load the trusted proxy networks from TRUSTED_PROXIES
+fn trusted_proxies() -> &'static [IpNetwork] {
+    TRUSTED_PROXIES.get_or_init(|| {
+        env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let network = IpNetwork::parse(entry);
+                if network.is_none() {
+                    warn!("Ignoring invalid TRUSTED_PROXIES entry: {}", entry);
+                }
+                network
+            })
+            .collect()
+    })
+}
+
+fn is_trusted(ip: IpAddr) -> bool {
+    trusted_proxies().iter().any(|network| network.contains(ip))
+}
+
+/// The IP address of the client making a request
+///
+/// `X-Forwarded-For` is only honoured when the direct peer is a trusted proxy. The header
+/// is then read from the right, skipping trusted proxies, so clients cannot spoof their
+/// address by sending their own `X-Forwarded-For`.
+pub fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let peer = normalize(req.peer_addr()?.ip());
+    if !is_trusted(peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<IpAddr> = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|entry| entry.trim().parse::<IpAddr>().ok())
+        .map(normalize)
+        .collect();
+
+    Some(
+        forwarded
+            .iter()
+            .rev()
+            .copied()
+            .find(|ip| !is_trusted(*ip))
+            .or_else(|| forwarded.first().copied())
+            .unwrap_or(peer),
+    )
+}
@@ -1,35 +1,61 @@
 use actix_web::{web, HttpRequest, HttpResponse};
 use log::{info, error};
+use serde::{Deserialize, Serialize};
+use std::env;
 
-use crate::db::DbPool;
-use crate::api::auth::authenticate_admin;
-use crate::api::handlers::{canister, ApiResponse};
+use crate::api::auth::{current_admin, require_permission};
+use crate::api::handlers::{audit, canister, ApiResponse};
 use crate::db::models::verified_module_hash::VerifiedModuleHash;
-use crate::db::models::canister::Canister;
-use crate::db::models::token_info::TokenInfo;
-use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::admin::{Admin, AdminRole, Permission, MIN_PASSWORD_LENGTH};
+use crate::db::models::audit_log::AuditLogEntry;
+use crate::db::repository::Repository;
+
+// Default time the previous API key stays valid after a rotation (24 hours)
+const DEFAULT_KEY_GRACE_PERIOD_SECS: i64 = 86_400;
+
+#[derive(Deserialize)]
+pub struct DeleteCanisterQuery {
+    /// Delete the canister and its data instead of moving it to the trash
+    pub permanent: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateAdminRequest {
+    pub username: String,
+    pub password: String,
+    pub role: Option<AdminRole>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAdminRequest {
+    pub password: Option<String>,
+    pub is_active: Option<bool>,
+    pub role: Option<AdminRole>,
+}
+
+#[derive(Deserialize)]
+pub struct RotateApiKeyRequest {
+    pub grace_period_secs: Option<i64>,
+}
+
+/// An admin together with its freshly issued API key, returned only on creation and rotation
+#[derive(Serialize)]
+pub struct AdminCredentials {
+    pub admin: Admin,
+    pub api_key: String,
+}
 
 /// Add a verified module hash (admin only)
 pub async fn add_verified_module_hash(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     request: web::Json<canister::VerifiedModuleHashRequest>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageModuleHashes) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
-            };
-            
             // Extract request data
             let hash = &request.0.hash;
             let description = &request.0.description;
@@ -48,10 +74,17 @@ pub async fn add_verified_module_hash(
                 canister_type.clone(),
             );
             
+            // An existing entry for the hash is overwritten, keep it for the audit log
+            let before = repo.module_hashes().find_by_hash(hash).await.ok().flatten();
+            
             // Save the verified module hash
-            match module_hash.save(&conn) {
+            match repo.module_hashes().save(&module_hash).await {
                 Ok(_) => {
                     info!("Added verified module hash: {}", hash);
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("module_hash.add", "module_hash", hash)
+                        .by_admin(&admin)
+                        .with_before(&before)
+                        .with_after(&module_hash)).await;
                     HttpResponse::Created()
                         .json(ApiResponse::success(module_hash, "Verified module hash added successfully"))
                 }
@@ -62,38 +95,34 @@ pub async fn add_verified_module_hash(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
     }
 }
 
 /// Remove a verified module hash (admin only)
 pub async fn remove_verified_module_hash(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageModuleHashes) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
             let hash = path.into_inner();
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
-            };
+            // Keep the current state for the audit log
+            let before = repo.module_hashes().find_by_hash(&hash).await.ok().flatten();
             
             // Delete the verified module hash
-            match VerifiedModuleHash::delete(&conn, &hash) {
+            match repo.module_hashes().delete(&hash).await {
                 Ok(deleted) => {
                     if deleted {
                         info!("Removed verified module hash: {}", hash);
+                        audit::record(repo.get_ref(), &req, AuditLogEntry::new("module_hash.remove", "module_hash", &hash)
+                            .by_admin(&admin)
+                            .with_before(&before)).await;
                         HttpResponse::Ok()
                             .json(ApiResponse::<()>::success((), "Verified module hash removed successfully"))
                     } else {
@@ -108,41 +137,50 @@ pub async fn remove_verified_module_hash(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
     }
 }
 
-/// Delete a canister (admin only)
+/// Move a canister to the trash, or delete it and its token, miner and stats data
+/// permanently with `?permanent=true` (admin only)
 pub async fn delete_canister(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
+    query: web::Query<DeleteCanisterQuery>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageRegistry) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
             let canister_id = path.into_inner();
-            info!("Admin deleting canister: {}", canister_id);
+            let permanent = query.permanent.unwrap_or(false);
+            info!("Admin deleting canister: {} (permanent: {})", canister_id, permanent);
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
+            // Keep the current state for the audit log
+            let before = repo.canisters().find_any_by_canister_id(&canister_id).await.ok().flatten();
+            
+            let (result, action) = if permanent {
+                (repo.canisters().delete(&canister_id).await, "canister.purge")
+            } else {
+                (repo.canisters().soft_delete(&canister_id).await, "canister.delete")
             };
             
-            // Delete the canister
-            match Canister::delete(&conn, &canister_id) {
+            match result {
                 Ok(deleted) => {
                     if deleted {
-                        info!("Canister deleted: {}", canister_id);
+                        info!("Canister deleted: {} (permanent: {})", canister_id, permanent);
+                        audit::record(repo.get_ref(), &req, AuditLogEntry::new(action, "canister", &canister_id)
+                            .by_admin(&admin)
+                            .with_before(&before)).await;
+                        let message = if permanent {
+                            format!("Canister {} deleted permanently", canister_id)
+                        } else {
+                            format!("Canister {} moved to the trash", canister_id)
+                        };
                         HttpResponse::Ok()
-                            .json(ApiResponse::<()>::success((), &format!("Canister {} deleted successfully", canister_id)))
+                            .json(ApiResponse::<()>::success((), &message))
                     } else {
                         HttpResponse::NotFound()
                             .json(ApiResponse::<()>::error(&format!("Canister {} not found", canister_id)))
@@ -155,39 +193,100 @@ pub async fn delete_canister(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// List canisters in the trash (admin only)
+pub async fn get_deleted_canisters(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageRegistry) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            match repo.canisters().find_deleted().await {
+                Ok(canisters) => {
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(canisters, "Retrieved canisters in the trash"))
+                }
+                Err(e) => {
+                    error!("Failed to get deleted canisters: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get deleted canisters: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Restore a canister from the trash (admin only)
+pub async fn restore_canister(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageRegistry) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let canister_id = path.into_inner();
+            info!("Admin restoring canister: {}", canister_id);
+            
+            match repo.canisters().restore(&canister_id).await {
+                Ok(true) => {
+                    info!("Canister restored: {}", canister_id);
+                    let canister = repo.canisters().find_by_canister_id(&canister_id).await.ok().flatten();
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("canister.restore", "canister", &canister_id)
+                        .by_admin(&admin)
+                        .with_after(&canister)).await;
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(canister, &format!("Canister {} restored", canister_id)))
+                }
+                Ok(false) => {
+                    HttpResponse::NotFound()
+                        .json(ApiResponse::<()>::error(&format!("Canister {} not found in the trash", canister_id)))
+                }
+                Err(e) => {
+                    error!("Failed to restore canister: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to restore canister: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
     }
 }
 
 /// Delete a token (admin only)
 pub async fn delete_token(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageRegistry) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
             let canister_id = path.into_inner();
             info!("Admin deleting token: {}", canister_id);
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
-            };
+            // Keep the current state for the audit log
+            let before = repo.tokens().find_by_canister_id(&canister_id).await.ok().flatten();
             
             // Delete the token
-            match TokenInfo::delete(&conn, &canister_id) {
+            match repo.tokens().delete(&canister_id).await {
                 Ok(deleted) => {
                     if deleted {
                         info!("Token deleted: {}", canister_id);
+                        audit::record(repo.get_ref(), &req, AuditLogEntry::new("token.delete", "token", &canister_id)
+                            .by_admin(&admin)
+                            .with_before(&before)).await;
                         HttpResponse::Ok()
                             .json(ApiResponse::<()>::success((), &format!("Token {} deleted successfully", canister_id)))
                     } else {
@@ -202,39 +301,78 @@ pub async fn delete_token(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Delete a ledger (admin only)
+pub async fn delete_ledger(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageRegistry) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let canister_id = path.into_inner();
+            info!("Admin deleting ledger: {}", canister_id);
+            
+            // Keep the current state for the audit log
+            let before = repo.ledgers().find_by_canister_id(&canister_id).await.ok().flatten();
+            
+            // Delete the ledger
+            match repo.ledgers().delete(&canister_id).await {
+                Ok(deleted) => {
+                    if deleted {
+                        info!("Ledger deleted: {}", canister_id);
+                        audit::record(repo.get_ref(), &req, AuditLogEntry::new("ledger.delete", "ledger", &canister_id)
+                            .by_admin(&admin)
+                            .with_before(&before)).await;
+                        HttpResponse::Ok()
+                            .json(ApiResponse::<()>::success((), &format!("Ledger {} deleted successfully", canister_id)))
+                    } else {
+                        HttpResponse::NotFound()
+                            .json(ApiResponse::<()>::error(&format!("Ledger {} not found", canister_id)))
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to delete ledger: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to delete ledger: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
     }
 }
 
 /// Delete a miner (admin only)
 pub async fn delete_miner(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageRegistry) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
             let canister_id = path.into_inner();
             info!("Admin deleting miner: {}", canister_id);
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
-            };
+            // Keep the current state for the audit log
+            let before = repo.miners().find_by_canister_id(&canister_id).await.ok().flatten();
             
             // Delete the miner
-            match MinerInfo::delete(&conn, &canister_id) {
+            match repo.miners().delete(&canister_id).await {
                 Ok(deleted) => {
                     if deleted {
                         info!("Miner deleted: {}", canister_id);
+                        audit::record(repo.get_ref(), &req, AuditLogEntry::new("miner.delete", "miner", &canister_id)
+                            .by_admin(&admin)
+                            .with_before(&before)).await;
                         HttpResponse::Ok()
                             .json(ApiResponse::<()>::success((), &format!("Miner {} deleted successfully", canister_id)))
                     } else {
@@ -249,32 +387,22 @@ pub async fn delete_miner(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
     }
 }
 
 /// Get all module hashes (admin only)
 pub async fn get_all_module_hashes(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ViewRegistry) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
-            };
-            
             // Get all verified module hashes
-            match VerifiedModuleHash::find_all(&conn) {
+            match repo.module_hashes().find_all().await {
                 Ok(hashes) => {
                     HttpResponse::Ok()
                         .json(ApiResponse::success(hashes, "Retrieved all verified module hashes"))
@@ -286,37 +414,27 @@ pub async fn get_all_module_hashes(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
     }
 }
 
 /// Set a module hash for a canister (admin only)
 pub async fn set_module_hash(
     req: HttpRequest,
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
     request: web::Json<canister::ModuleHashRequest>,
 ) -> HttpResponse {
-    // Authenticate the admin
-    match authenticate_admin(&req, &db_pool).await {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageModuleHashes) {
         Ok(admin) => {
             info!("Admin authenticated: {}", admin.username);
             
             // Get the canister ID from the path
             let canister_id = path.into_inner();
             
-            // Get database connection
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                }
-            };
-            
             // Get the canister
-            let canister = match Canister::find_by_id(&conn, &canister_id) {
+            let canister = match repo.canisters().find_by_id(&canister_id).await {
                 Ok(Some(canister)) => canister,
                 Ok(_) => {
                     return HttpResponse::NotFound()
@@ -334,9 +452,13 @@ pub async fn set_module_hash(
             updated_canister.module_hash = Some(request.0.hash.clone());
             
             // Save the updated canister
-            match updated_canister.save(&conn) {
+            match repo.canisters().save(&updated_canister).await {
                 Ok(_) => {
                     info!("Updated module hash for canister {}: {}", canister_id, request.0.hash);
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("canister.set_module_hash", "canister", &canister_id)
+                        .by_admin(&admin)
+                        .with_before(&canister)
+                        .with_after(&updated_canister)).await;
                     HttpResponse::Ok()
                         .json(ApiResponse::success(updated_canister, &format!("Module hash updated for canister {}", canister_id)))
                 }
@@ -347,6 +469,369 @@ pub async fn set_module_hash(
                 }
             }
         }
-        Err(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// List all admin accounts (admin only)
+pub async fn list_admins(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageAdmins) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            match repo.admins().find_all().await {
+                Ok(admins) => {
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(admins, "Retrieved all admins"))
+                }
+                Err(e) => {
+                    error!("Failed to get admins: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get admins: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Get a single admin account (admin only)
+pub async fn get_admin(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageAdmins) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let admin_id = path.into_inner();
+            
+            match repo.admins().find_by_id(&admin_id).await {
+                Ok(Some(found)) => {
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(found, "Admin retrieved successfully"))
+                }
+                Ok(_) => {
+                    HttpResponse::NotFound()
+                        .json(ApiResponse::<()>::error(&format!("Admin {} not found", admin_id)))
+                }
+                Err(e) => {
+                    error!("Failed to get admin: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get admin: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
     }
-} 
\ No newline at end of file
+}
+
+/// Create a new admin account (admin only)
+pub async fn create_admin(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    request: web::Json<CreateAdminRequest>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageAdmins) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let username = request.0.username.trim().to_string();
+            if username.is_empty() {
+                return HttpResponse::BadRequest()
+                    .json(ApiResponse::<()>::error("Username must not be empty"));
+            }
+            if request.0.password.len() < MIN_PASSWORD_LENGTH {
+                return HttpResponse::BadRequest()
+                    .json(ApiResponse::<()>::error(&format!("Password must be at least {} characters", MIN_PASSWORD_LENGTH)));
+            }
+            
+            // Usernames are unique, and save() would otherwise overwrite the existing account
+            match repo.admins().find_by_username(&username).await {
+                Ok(Some(_)) => {
+                    return HttpResponse::BadRequest()
+                        .json(ApiResponse::<()>::error(&format!("Admin {} already exists", username)));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Failed to check if admin exists: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error("Database error"));
+                }
+            }
+            
+            let (new_admin, api_key) = match Admin::new(username, &request.0.password, request.0.role.unwrap_or(AdminRole::Viewer)) {
+                Ok(created) => created,
+                Err(e) => {
+                    error!("Failed to create admin: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error("Failed to create admin"));
+                }
+            };
+            
+            match repo.admins().save(&new_admin).await {
+                Ok(_) => {
+                    info!("Admin {} created admin {}", admin.username, new_admin.username);
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("admin.create", "admin", &new_admin.id)
+                        .by_admin(&admin)
+                        .with_after(&new_admin)).await;
+                    HttpResponse::Created()
+                        .json(ApiResponse::success(
+                            AdminCredentials { admin: new_admin, api_key },
+                            "Admin created successfully. Store the API key now, it will not be shown again",
+                        ))
+                }
+                Err(e) => {
+                    error!("Failed to save admin: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to save admin: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Update an admin's password, role or active flag (admin only)
+pub async fn update_admin(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+    request: web::Json<UpdateAdminRequest>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageAdmins) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let admin_id = path.into_inner();
+            
+            let mut target = match repo.admins().find_by_id(&admin_id).await {
+                Ok(Some(target)) => target,
+                Ok(_) => {
+                    return HttpResponse::NotFound()
+                        .json(ApiResponse::<()>::error(&format!("Admin {} not found", admin_id)));
+                }
+                Err(e) => {
+                    error!("Failed to get admin: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get admin: {}", e)));
+                }
+            };
+            let before = target.clone();
+            
+            if let Some(password) = &request.0.password {
+                if password.len() < MIN_PASSWORD_LENGTH {
+                    return HttpResponse::BadRequest()
+                        .json(ApiResponse::<()>::error(&format!("Password must be at least {} characters", MIN_PASSWORD_LENGTH)));
+                }
+                if let Err(e) = target.set_password(password) {
+                    error!("Failed to hash password: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error("Failed to update password"));
+                }
+            }
+            
+            // Deactivating or demoting a superadmin must leave another one in place
+            let loses_superadmin = target.is_active
+                && target.role == AdminRole::Superadmin
+                && (request.0.is_active == Some(false)
+                    || request.0.role.is_some_and(|role| role != AdminRole::Superadmin));
+            if loses_superadmin {
+                if let Some(response) = last_superadmin_error(repo.get_ref()).await {
+                    return response;
+                }
+            }
+            
+            if let Some(is_active) = request.0.is_active {
+                target.is_active = is_active;
+                target.last_updated = chrono::Utc::now().timestamp();
+            }
+            
+            if let Some(role) = request.0.role {
+                target.role = role;
+                target.last_updated = chrono::Utc::now().timestamp();
+            }
+            
+            match repo.admins().save(&target).await {
+                Ok(_) => {
+                    info!("Admin {} updated admin {}", admin.username, target.username);
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("admin.update", "admin", &target.id)
+                        .by_admin(&admin)
+                        .with_before(&before)
+                        .with_after(&target)).await;
+                    // A new password or a deactivation ends every open login session
+                    if request.0.password.is_some() || !target.is_active {
+                        if let Err(e) = repo.sessions().revoke_all_for_admin(&target.id).await {
+                            error!("Failed to revoke sessions of admin {}: {}", target.username, e);
+                        }
+                    }
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(target, "Admin updated successfully"))
+                }
+                Err(e) => {
+                    error!("Failed to update admin: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to update admin: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Deactivate an admin account (admin only)
+pub async fn deactivate_admin(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageAdmins) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let admin_id = path.into_inner();
+            
+            let mut target = match repo.admins().find_by_id(&admin_id).await {
+                Ok(Some(target)) => target,
+                Ok(_) => {
+                    return HttpResponse::NotFound()
+                        .json(ApiResponse::<()>::error(&format!("Admin {} not found", admin_id)));
+                }
+                Err(e) => {
+                    error!("Failed to get admin: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get admin: {}", e)));
+                }
+            };
+            let before = target.clone();
+            
+            if target.is_active && target.role == AdminRole::Superadmin {
+                if let Some(response) = last_superadmin_error(repo.get_ref()).await {
+                    return response;
+                }
+            }
+            
+            target.is_active = false;
+            target.last_updated = chrono::Utc::now().timestamp();
+            
+            match repo.admins().save(&target).await {
+                Ok(_) => {
+                    info!("Admin {} deactivated admin {}", admin.username, target.username);
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("admin.deactivate", "admin", &target.id)
+                        .by_admin(&admin)
+                        .with_before(&before)
+                        .with_after(&target)).await;
+                    if let Err(e) = repo.sessions().revoke_all_for_admin(&target.id).await {
+                        error!("Failed to revoke sessions of admin {}: {}", target.username, e);
+                    }
+                    HttpResponse::Ok()
+                        .json(ApiResponse::<()>::success((), &format!("Admin {} deactivated", target.username)))
+                }
+                Err(e) => {
+                    error!("Failed to deactivate admin: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to deactivate admin: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Rotate an admin's API key, keeping the old key valid for a grace period (admin only)
+pub async fn rotate_admin_api_key(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+    request: Option<web::Json<RotateApiKeyRequest>>,
+) -> HttpResponse {
+    // Admins may rotate their own key, other keys need admin management rights
+    match current_admin(&req) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+            
+            let admin_id = path.into_inner();
+            if admin.id != admin_id && !admin.role.has_permission(Permission::ManageAdmins) {
+                return HttpResponse::Forbidden()
+                    .json(ApiResponse::<()>::error("Not allowed to rotate the API key of another admin"));
+            }
+            
+            let grace_period_secs = request
+                .and_then(|r| r.0.grace_period_secs)
+                .unwrap_or_else(default_key_grace_period);
+            if grace_period_secs < 0 {
+                return HttpResponse::BadRequest()
+                    .json(ApiResponse::<()>::error("Grace period must not be negative"));
+            }
+            
+            let mut target = match repo.admins().find_by_id(&admin_id).await {
+                Ok(Some(target)) => target,
+                Ok(_) => {
+                    return HttpResponse::NotFound()
+                        .json(ApiResponse::<()>::error(&format!("Admin {} not found", admin_id)));
+                }
+                Err(e) => {
+                    error!("Failed to get admin: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get admin: {}", e)));
+                }
+            };
+            let before = target.clone();
+            
+            let api_key = target.rotate_api_key(grace_period_secs);
+            
+            match repo.admins().save(&target).await {
+                Ok(_) => {
+                    info!("Admin {} rotated the API key of admin {}", admin.username, target.username);
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("admin.rotate_key", "admin", &target.id)
+                        .by_admin(&admin)
+                        .with_before(&before)
+                        .with_after(&target)).await;
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(
+                            AdminCredentials { admin: target, api_key },
+                            "API key rotated. Store the new key now, it will not be shown again",
+                        ))
+                }
+                Err(e) => {
+                    error!("Failed to rotate API key: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to rotate API key: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Helper function to refuse deactivating or demoting the only remaining active superadmin
+/// Returns an error response if the change must not proceed
+async fn last_superadmin_error(repo: &dyn Repository) -> Option<HttpResponse> {
+    match repo.admins().count_active_superadmins().await {
+        Ok(count) if count <= 1 => Some(HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::error("Cannot deactivate or demote the last active superadmin"))),
+        Ok(_) => None,
+        Err(e) => {
+            error!("Failed to count active superadmins: {}", e);
+            Some(HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error("Database error")))
+        }
+    }
+}
+
+/// Helper function to read the default API key grace period
+fn default_key_grace_period() -> i64 {
+    env::var("ADMIN_KEY_GRACE_PERIOD_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_KEY_GRACE_PERIOD_SECS)
+}
@@ -0,0 +1,140 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::{info, error};
+use serde::{Deserialize, Serialize};
+
+use crate::api::auth::require_permission;
+use crate::api::client_ip::client_ip;
+use crate::api::handlers::ApiResponse;
+use crate::db::models::admin::Permission;
+use crate::db::models::audit_log::{AuditLogEntry, AuditLogFilter};
+use crate::db::repository::Repository;
+
+// Default and maximum page size for audit log queries
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+// Maximum number of entries in a single export
+const MAX_EXPORT_SIZE: i64 = 100_000;
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl AuditLogQuery {
+    // Split the query into its filter and paging parts
+    fn into_parts(self) -> (AuditLogFilter, Option<i64>, Option<i64>) {
+        let filter = AuditLogFilter {
+            actor_id: self.actor_id,
+            action: self.action,
+            target_type: self.target_type,
+            target_id: self.target_id,
+            since: self.since,
+            until: self.until,
+        };
+        (filter, self.limit, self.offset)
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLogEntry>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Store an audit log entry, logging instead of failing the request if it cannot be written
+pub async fn record(repo: &dyn Repository, req: &HttpRequest, entry: AuditLogEntry) {
+    let ip_address = client_ip(req).map(|ip| ip.to_string());
+    let entry = entry.with_ip(ip_address.as_deref());
+    if let Err(e) = repo.audit_log().save(&entry).await {
+        error!("Failed to write audit log entry for {} on {} {}: {}", entry.action, entry.target_type, entry.target_id, e);
+    }
+}
+
+/// Query the audit log (admin only)
+pub async fn get_audit_log(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    query: web::Query<AuditLogQuery>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ViewAuditLog) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+
+            let (filter, limit, offset) = query.into_inner().into_parts();
+            let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+            let offset = offset.unwrap_or(0).max(0);
+
+            let result = match repo.audit_log().count(&filter).await {
+                Ok(total) => repo.audit_log().find(&filter, limit, offset).await.map(|entries| (entries, total)),
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok((entries, total)) => {
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(
+                            AuditLogPage { entries, total, limit, offset },
+                            "Retrieved audit log",
+                        ))
+                }
+                Err(e) => {
+                    error!("Failed to get audit log: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get audit log: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Export the audit log as JSON Lines (admin only)
+pub async fn export_audit_log(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    query: web::Query<AuditLogQuery>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ViewAuditLog) {
+        Ok(admin) => {
+            info!("Admin {} exporting audit log", admin.username);
+
+            let (filter, limit, offset) = query.into_inner().into_parts();
+            let limit = limit.unwrap_or(MAX_EXPORT_SIZE).clamp(1, MAX_EXPORT_SIZE);
+            let offset = offset.unwrap_or(0).max(0);
+
+            match repo.audit_log().find(&filter, limit, offset).await {
+                Ok(entries) => {
+                    let mut body = String::new();
+                    for entry in &entries {
+                        if let Ok(line) = serde_json::to_string(entry) {
+                            body.push_str(&line);
+                            body.push('\n');
+                        }
+                    }
+
+                    HttpResponse::Ok()
+                        .content_type("application/x-ndjson")
+                        .insert_header(("Content-Disposition", "attachment; filename=\"audit-log.jsonl\""))
+                        .body(body)
+                }
+                Err(e) => {
+                    error!("Failed to export audit log: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to export audit log: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,133 @@
+use actix_files::NamedFile;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::{info, error};
+
+use crate::api::auth::require_permission;
+use crate::api::handlers::{audit, ApiResponse};
+use crate::db::backup::{self, BackupConfig};
+use crate::db::models::admin::Permission;
+use crate::db::models::audit_log::AuditLogEntry;
+use crate::db::repository::Repository;
+use crate::jobs::tasks;
+
+/// Write a database snapshot now (superadmin only)
+pub async fn create_backup(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageBackups) {
+        Ok(admin) => {
+            info!("Admin {} triggered a database backup", admin.username);
+
+            // Snapshots copy the SQLite file, other backends have their own tools
+            let db_path = match repo.file_path() {
+                Some(path) => path.to_path_buf(),
+                None => return backups_unavailable(repo.backend()),
+            };
+
+            match tasks::backup::run(db_path).await {
+                Ok(backup) => {
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("backup.create", "backup", &backup.file_name)
+                        .by_admin(&admin)
+                        .with_after(&backup)).await;
+                    HttpResponse::Created()
+                        .json(ApiResponse::success(backup, "Backup created successfully"))
+                }
+                Err(e) => {
+                    error!("Failed to back up database: {:#}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to back up database: {:#}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// List database snapshots, newest first (superadmin only)
+pub async fn list_backups(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageBackups) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+
+            if repo.file_path().is_none() {
+                return backups_unavailable(repo.backend());
+            }
+
+            match backup::list_backups(&BackupConfig::get().dir) {
+                Ok(backups) => {
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(backups, "Retrieved backups"))
+                }
+                Err(e) => {
+                    error!("Failed to list backups: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to list backups: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Download the most recent database snapshot (superadmin only)
+pub async fn download_latest_backup(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageBackups) {
+        Ok(admin) => {
+            info!("Admin {} downloading latest backup", admin.username);
+
+            if repo.file_path().is_none() {
+                return backups_unavailable(repo.backend());
+            }
+
+            let latest = match backup::list_backups(&BackupConfig::get().dir) {
+                Ok(backups) => backups.into_iter().next(),
+                Err(e) => {
+                    error!("Failed to list backups: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to list backups: {}", e)));
+                }
+            };
+            let latest = match latest {
+                Some(latest) => latest,
+                None => {
+                    return HttpResponse::NotFound()
+                        .json(ApiResponse::<()>::error("No backups found"));
+                }
+            };
+
+            match NamedFile::open(&latest.path) {
+                Ok(file) => file
+                    .set_content_disposition(ContentDisposition {
+                        disposition: DispositionType::Attachment,
+                        parameters: vec![DispositionParam::Filename(latest.file_name)],
+                    })
+                    .into_response(&req),
+                Err(e) => {
+                    error!("Failed to open backup {:?}: {}", latest.path, e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error("Failed to open backup"))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Helper function to reject backup requests for backends without a local database file
+fn backups_unavailable(backend: &str) -> HttpResponse {
+    HttpResponse::NotImplemented().json(ApiResponse::<()>::error(&format!(
+        "Backups are only available with the SQLite backend, use the {} tools to back up this database",
+        backend
+    )))
+}
@@ -1,24 +1,52 @@
-use actix_web::{web, HttpResponse, Responder};
-use serde::Deserialize;
-use log::{info, error};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::Duration;
+use log::{info, warn, error};
 use serde_json;
 
-use crate::db::pool::DbPool;
 use crate::db::models::canister::{Canister, CanisterType};
-use crate::api::handlers::ApiResponse;
+use crate::db::models::canister_interface::CanisterInterface;
+use crate::api::handlers::{audit, ApiResponse};
+use crate::api::rate_limit::{self, CanisterRoute};
+use crate::db::models::audit_log::AuditLogEntry;
 use crate::db::models::verified_module_hash::VerifiedModuleHash;
-use crate::ic::agent::create_agent;
-use crate::ic::services::module_hash::get_module_hash;
+use crate::db::repository::Repository;
+use crate::ic::candid::interface::Interface;
+use crate::ic::client::IcClient;
 use crate::websocket;
 
 #[derive(Deserialize)]
 pub struct RegisterCanisterRequest {
     principal: String,
     canister_id: String,
-    canister_type: String,
+    /// Detected from the canister's `candid:service` metadata when omitted
+    canister_type: Option<String>,
     module_hash: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct BatchRegisterRequest {
+    canisters: Vec<RegisterCanisterRequest>,
+    /// Register nothing if any entry is invalid
+    #[serde(default)]
+    all_or_nothing: bool,
+}
+
+/// Outcome of one entry of a batch registration
+#[derive(Serialize)]
+pub struct BatchItemResult {
+    pub canister_id: String,
+    /// `registered`, `failed`, or `skipped` when an all-or-nothing batch was rejected
+    pub status: String,
+    pub error: Option<String>,
+    pub canister: Option<Canister>,
+}
+
+// Maximum number of canisters in one batch registration
+const MAX_BATCH_SIZE: usize = 50;
+
 #[derive(Deserialize)]
 pub struct UpdateCanisterRequest {
     principal: Option<String>,
@@ -33,20 +61,10 @@ pub struct ModuleHashRequest {
 }
 
 /// Get all canisters
-pub async fn get_all_canisters(db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn get_all_canisters(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get all canisters");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<Canister>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match Canister::find_all(&conn) {
+    match repo.canisters().find_all().await {
         Ok(canisters) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(canisters, "Canisters retrieved successfully")
@@ -62,20 +80,10 @@ pub async fn get_all_canisters(db_pool: web::Data<DbPool>) -> impl Responder {
 }
 
 /// Get canisters by type
-pub async fn get_canisters_by_type(db_pool: web::Data<DbPool>, path: web::Path<String>) -> impl Responder {
+pub async fn get_canisters_by_type(repo: web::Data<dyn Repository>, path: web::Path<String>) -> impl Responder {
     let canister_type = path.into_inner();
     info!("API: Get canisters by type: {}", canister_type);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<Canister>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
     // Convert string to CanisterType
     let canister_type_enum = match canister_type.to_lowercase().as_str() {
         "token" => CanisterType::Token,
@@ -89,7 +97,7 @@ pub async fn get_canisters_by_type(db_pool: web::Data<DbPool>, path: web::Path<S
         }
     };
     
-    match Canister::find_by_type(&conn, &canister_type_enum) {
+    match repo.canisters().find_by_type(&canister_type_enum).await {
         Ok(canisters) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(canisters, &format!("Canisters of type '{}' retrieved successfully", canister_type))
@@ -106,105 +114,30 @@ pub async fn get_canisters_by_type(db_pool: web::Data<DbPool>, path: web::Path<S
 
 /// Register a new canister
 pub async fn register_canister(
-    db_pool: web::Data<DbPool>,
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
     request: web::Json<RegisterCanisterRequest>,
 ) -> impl Responder {
     info!("API: Register canister: {}", request.canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Canister>::error(&format!("Database error: {}", e))
-            );
-        }
+    // Check the request and the canister's interface, or detect its type
+    let (canister, candid_service) = match validate_registration(repo.get_ref(), ic.get_ref(), &request).await {
+        Ok(validated) => validated,
+        Err(e) => return e.into_response(),
     };
     
-    // Check if canister already exists
-    match Canister::find_by_canister_id(&conn, &request.canister_id) {
-        Ok(Some(_)) => {
-            return HttpResponse::BadRequest().json(
-                ApiResponse::<Canister>::error(&format!("Canister with ID {} already exists", request.canister_id))
-            );
-        },
-        Ok(_) => {},
-        Err(e) => {
-            error!("Failed to check if canister exists: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Canister>::error(&format!("Failed to check if canister exists: {}", e))
-            );
-        }
-    }
-    
-    // Parse canister type
-    let canister_type = match request.canister_type.to_lowercase().as_str() {
-        "token" => CanisterType::Token,
-        "miner" => CanisterType::Miner,
-        "wallet" => CanisterType::Wallet,
-        "ledger" => CanisterType::Ledger,
-        _ => {
-            return HttpResponse::BadRequest().json(
-                ApiResponse::<Canister>::error(&format!("Invalid canister type: {}", request.canister_type))
-            );
-        }
-    };
-    
-    // If module_hash is provided, use it
-    // Otherwise, we'll fetch it in the background tasks
-    let module_hash = request.module_hash.clone();
-    
-    // Create new canister
-    let canister = Canister::new(
-        request.principal.clone(),
-        request.canister_id.clone(),
-        canister_type,
-        module_hash,
-    );
-    
     // Save canister
-    match canister.save(&conn) {
+    match repo.canisters().save(&canister).await {
         Ok(_) => {
-            // If module_hash not provided, start a background task to fetch it
-            if request.module_hash.is_none() {
-                let canister_id = request.canister_id.clone();
-                let canister_type_str = request.canister_type.clone();
-                let db_pool = db_pool.clone();
-                
-                // Spawn a task to fetch and verify the module hash
-                tokio::spawn(async move {
-                    if let Err(e) = update_and_verify_module_hash(db_pool, &canister_id, &canister_type_str).await {
-                        error!("Failed to update module hash for canister {}: {}", canister_id, e);
-                    }
-                });
-            } else {
-                // Verify the provided module hash
-                let module_hash = request.module_hash.as_ref().unwrap();
-                let canister_type_str = request.canister_type.clone();
-                
-                match VerifiedModuleHash::is_hash_verified(&conn, module_hash, &canister_type_str) {
-                    Ok(true) => {
-                        info!("Verified module hash for canister {}", request.canister_id);
-                    },
-                    Ok(false) => {
-                        info!("Unverified module hash for canister {}", request.canister_id);
-                    },
-                    Err(e) => {
-                        error!("Failed to check if module hash is verified: {}", e);
-                    }
-                }
-            }
+            audit::record(repo.get_ref(), &req, AuditLogEntry::new("canister.register", "canister", &request.canister_id)
+                .with_after(&canister)).await;
+            
+            save_interface(repo.get_ref(), &canister, candid_service).await;
+            schedule_module_hash_verification(&repo, &ic, &canister).await;
             
             // Send WebSocket notification about the new canister
-            websocket::broadcast_notification(
-                "canister_registered", 
-                serde_json::json!({
-                    "canister_id": request.canister_id,
-                    "principal": request.principal,
-                    "canister_type": request.canister_type,
-                    "timestamp": chrono::Utc::now().timestamp_millis()
-                })
-            );
+            notify_registered(&canister);
             
             HttpResponse::Created().json(
                 ApiResponse::success(canister, "Canister registered successfully")
@@ -212,6 +145,7 @@ pub async fn register_canister(
         },
         Err(e) => {
             error!("Failed to save canister: {}", e);
+            rate_limit::refund_canister(CanisterRoute::Registration, &canister.canister_id);
             HttpResponse::InternalServerError().json(
                 ApiResponse::<Canister>::error(&format!("Failed to save canister: {}", e))
             )
@@ -219,25 +153,114 @@ pub async fn register_canister(
     }
 }
 
+/// Register several canisters, e.g. a token and its miners, in one transaction
+pub async fn register_canisters_batch(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
+    request: web::Json<BatchRegisterRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    info!("API: Batch register {} canisters (all or nothing: {})", request.canisters.len(), request.all_or_nothing);
+    
+    if request.canisters.is_empty() || request.canisters.len() > MAX_BATCH_SIZE {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&format!("A batch must contain between 1 and {} canisters", MAX_BATCH_SIZE))
+        );
+    }
+    
+    // Validate every entry before writing anything
+    let mut seen = HashSet::new();
+    let mut validated = Vec::new();
+    for item in &request.canisters {
+        let result = if !seen.insert(item.canister_id.clone()) {
+            Err(RegistrationError::Invalid("Duplicate canister ID in batch".to_string()))
+        } else {
+            validate_registration(repo.get_ref(), ic.get_ref(), item).await
+        };
+        validated.push(result);
+    }
+    
+    let has_errors = validated.iter().any(|result| result.is_err());
+    let rejected = request.all_or_nothing && has_errors;
+    
+    // Save the valid entries, all in one transaction
+    let saved = if rejected {
+        Ok(())
+    } else {
+        let canisters: Vec<Canister> = validated.iter().flatten().map(|(canister, _)| canister.clone()).collect();
+        repo.canisters().save_all(&canisters).await
+    };
+    // Entries that were not saved do not count against the per-canister limit
+    if rejected || saved.is_err() {
+        for (canister, _) in validated.iter().flatten() {
+            rate_limit::refund_canister(CanisterRoute::Registration, &canister.canister_id);
+        }
+    }
+    if let Err(e) = saved {
+        error!("Failed to save canister batch: {}", e);
+        return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to save canisters: {}", e))
+        );
+    }
+    
+    let mut registered = 0;
+    let mut results: Vec<BatchItemResult> = Vec::with_capacity(validated.len());
+    for (item, result) in request.canisters.iter().zip(validated) {
+        let item_result = match result {
+            Ok((canister, candid_service)) if !rejected => {
+                registered += 1;
+                audit::record(repo.get_ref(), &req, AuditLogEntry::new("canister.register", "canister", &canister.canister_id)
+                    .with_after(&canister)).await;
+                save_interface(repo.get_ref(), &canister, candid_service).await;
+                schedule_module_hash_verification(&repo, &ic, &canister).await;
+                notify_registered(&canister);
+                BatchItemResult {
+                    canister_id: item.canister_id.clone(),
+                    status: "registered".to_string(),
+                    error: None,
+                    canister: Some(canister),
+                }
+            },
+            Ok(_) => BatchItemResult {
+                canister_id: item.canister_id.clone(),
+                status: "skipped".to_string(),
+                error: Some("Batch rejected because other entries are invalid".to_string()),
+                canister: None,
+            },
+            Err(e) => BatchItemResult {
+                canister_id: item.canister_id.clone(),
+                status: "failed".to_string(),
+                error: Some(e.to_string()),
+                canister: None,
+            },
+        };
+        results.push(item_result);
+    }
+    
+    let message = format!("Registered {} of {} canisters", registered, results.len());
+    if registered == 0 {
+        HttpResponse::UnprocessableEntity().json(ApiResponse {
+            success: false,
+            message,
+            data: Some(results),
+        })
+    } else if has_errors {
+        HttpResponse::Ok().json(ApiResponse::success(results, &message))
+    } else {
+        HttpResponse::Created().json(ApiResponse::success(results, &message))
+    }
+}
+
 /// Get a specific canister
 pub async fn get_canister(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Get canister: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Canister>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match Canister::find_by_canister_id(&conn, &canister_id) {
+    match repo.canisters().find_by_canister_id(&canister_id).await {
         Ok(Some(canister)) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(canister, "Canister retrieved successfully")
@@ -257,27 +280,46 @@ pub async fn get_canister(
     }
 }
 
+/// Get the Candid service definition read from a canister when it was registered
+pub async fn get_canister_interface(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let canister_id = path.into_inner();
+    info!("API: Get canister interface: {}", canister_id);
+    
+    match repo.interfaces().find_by_canister_id(&canister_id).await {
+        Ok(Some(interface)) => {
+            HttpResponse::Ok().json(
+                ApiResponse::success(interface, "Canister interface retrieved successfully")
+            )
+        },
+        Ok(_) => {
+            HttpResponse::NotFound().json(
+                ApiResponse::<CanisterInterface>::error(&format!("No interface stored for canister {}", canister_id))
+            )
+        },
+        Err(e) => {
+            error!("Failed to get canister interface: {}", e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<CanisterInterface>::error(&format!("Failed to get canister interface: {}", e))
+            )
+        }
+    }
+}
+
 /// Update a canister
 pub async fn update_canister(
-    db_pool: web::Data<DbPool>,
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
     request: web::Json<UpdateCanisterRequest>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Update canister: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Canister>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
     // Get existing canister
-    let mut canister = match Canister::find_by_canister_id(&conn, &canister_id) {
+    let mut canister = match repo.canisters().find_by_canister_id(&canister_id).await {
         Ok(Some(canister)) => canister,
         Ok(_) => {
             return HttpResponse::NotFound().json(
@@ -292,6 +334,8 @@ pub async fn update_canister(
         }
     };
     
+    let before = canister.clone();
+    
     // Update fields if provided
     if let Some(principal) = &request.principal {
         canister.principal = principal.clone();
@@ -314,8 +358,11 @@ pub async fn update_canister(
     canister.module_hash = request.module_hash.clone();
     
     // Save updated canister
-    match canister.save(&conn) {
+    match repo.canisters().save(&canister).await {
         Ok(_) => {
+            audit::record(repo.get_ref(), &req, AuditLogEntry::new("canister.update", "canister", &canister_id)
+                .with_before(&before)
+                .with_after(&canister)).await;
             HttpResponse::Ok().json(
                 ApiResponse::success(canister, "Canister updated successfully")
             )
@@ -331,25 +378,17 @@ pub async fn update_canister(
 
 /// Delete a canister
 pub async fn delete_canister(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Delete canister: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Database error"));
-        }
-    };
-    
     // First check if the canister exists
-    match Canister::find_by_canister_id(&conn, &canister_id) {
+    match repo.canisters().find_by_canister_id(&canister_id).await {
         Ok(Some(_)) => {
             // Delete the canister from the database
-            match Canister::delete(&conn, &canister_id) {
+            match repo.canisters().delete(&canister_id).await {
                 Ok(true) => {
                     info!("Canister deleted: {}", canister_id);
                     HttpResponse::Ok().json(ApiResponse::success(true, "Canister deleted"))
@@ -376,28 +415,20 @@ pub async fn delete_canister(
 }
 
 /// Get all module hashes - forwarding to verified module hashes
-pub async fn get_all_module_hashes(db_pool: web::Data<DbPool>) -> impl Responder {
-    get_all_verified_module_hashes(db_pool).await
+pub async fn get_all_module_hashes(repo: web::Data<dyn Repository>) -> impl Responder {
+    get_all_verified_module_hashes(repo).await
 }
 
 /// Set module hash for a canister
 pub async fn set_module_hash(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
     req: web::Json<ModuleHashRequest>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Set module hash for canister: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Database error"));
-        }
-    };
-    
-    match Canister::find_by_canister_id(&conn, &canister_id) {
+    match repo.canisters().find_by_canister_id(&canister_id).await {
         Ok(Some(mut canister)) => {
             // Update the module hash
             canister.module_hash = Some(req.hash.clone());
@@ -406,7 +437,7 @@ pub async fn set_module_hash(
             info!("Setting module hash with description: {}", req.description);
             
             // Save the updated canister
-            if let Err(e) = canister.save(&conn) {
+            if let Err(e) = repo.canisters().save(&canister).await {
                 error!("Failed to save canister: {}", e);
                 return HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Failed to save canister"));
             }
@@ -424,20 +455,10 @@ pub async fn set_module_hash(
 }
 
 /// Get all verified module hashes
-pub async fn get_all_verified_module_hashes(db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn get_all_verified_module_hashes(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get all verified module hashes");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<VerifiedModuleHash>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match VerifiedModuleHash::find_all(&conn) {
+    match repo.module_hashes().find_all().await {
         Ok(hashes) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(hashes, "Verified module hashes retrieved successfully")
@@ -461,21 +482,11 @@ pub struct VerifiedModuleHashRequest {
 
 /// Add a new verified module hash (admin only)
 pub async fn add_verified_module_hash(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     request: web::Json<VerifiedModuleHashRequest>,
 ) -> impl Responder {
     info!("API: Add verified module hash: {}", request.hash);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<VerifiedModuleHash>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
     // Validate hash format (hex string with 64 characters)
     if !is_valid_hex_hash(&request.hash) {
         return HttpResponse::BadRequest().json(
@@ -491,7 +502,7 @@ pub async fn add_verified_module_hash(
     );
     
     // Save verified module hash
-    match verified_hash.save(&conn) {
+    match repo.module_hashes().save(&verified_hash).await {
         Ok(_) => {
             HttpResponse::Created().json(
                 ApiResponse::success(verified_hash, "Verified module hash added successfully")
@@ -508,25 +519,17 @@ pub async fn add_verified_module_hash(
 
 /// Remove a verified module hash (admin only)
 pub async fn remove_verified_module_hash(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let hash = path.into_inner();
     info!("API: Remove verified module hash: {}", hash);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Database error"));
-        }
-    };
-    
     // First check if the verified module hash exists
-    match VerifiedModuleHash::find_by_hash(&conn, &hash) {
+    match repo.module_hashes().find_by_hash(&hash).await {
         Ok(Some(_)) => {
             // Delete the verified module hash from the database
-            match VerifiedModuleHash::delete(&conn, &hash) {
+            match repo.module_hashes().delete(&hash).await {
                 Ok(true) => {
                     info!("Verified module hash deleted: {}", hash);
                     HttpResponse::Ok().json(ApiResponse::success(true, "Verified module hash deleted"))
@@ -563,32 +566,27 @@ fn is_valid_hex_hash(hash: &str) -> bool {
 
 /// Helper function to update and verify a canister's module hash
 async fn update_and_verify_module_hash(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
     canister_id: &str,
     canister_type: &str,
 ) -> Result<(), anyhow::Error> {
     info!("Updating and verifying module hash for canister: {}", canister_id);
     
-    // Create IC agent
-    let agent = create_agent("https://ic0.app").await?;
-    
     // Get module hash from canister
-    let module_hash = get_module_hash(&agent, canister_id).await?;
-    
-    // Get DB connection
-    let conn = db_pool.get()?;
+    let module_hash = ic.get_module_hash(canister_id).await?;
     
     // Check if the hash is verified
-    let is_verified = VerifiedModuleHash::is_hash_verified(&conn, &module_hash, canister_type)?;
+    let is_verified = repo.module_hashes().is_hash_verified(&module_hash, canister_type).await?;
     
     // Update the canister's module hash
-    match Canister::find_by_canister_id(&conn, canister_id)? {
+    match repo.canisters().find_by_canister_id(canister_id).await? {
         Some(mut canister) => {
             // Update the module hash
             canister.module_hash = Some(module_hash.clone());
             
             // Save the updated canister
-            canister.save(&conn)?;
+            repo.canisters().save(&canister).await?;
             
             if is_verified {
                 info!("Verified module hash for canister {}: {}", canister_id, module_hash);
@@ -602,4 +600,217 @@ async fn update_and_verify_module_hash(
             Err(anyhow::anyhow!("Canister not found"))
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Why a registration request was rejected
+enum RegistrationError {
+    /// Malformed request, or the canister is already registered
+    Invalid(String),
+    /// The canister is in the trash
+    InTrash,
+    RateLimited(Duration),
+    /// The canister type does not match, or cannot be detected from, the canister's interface
+    Unresolved(String),
+    Internal(String),
+}
+
+impl fmt::Display for RegistrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistrationError::Invalid(message)
+            | RegistrationError::Unresolved(message)
+            | RegistrationError::Internal(message) => write!(f, "{}", message),
+            RegistrationError::InTrash => write!(f, "Canister is in the trash and must be restored by an admin"),
+            RegistrationError::RateLimited(_) => write!(f, "Rate limit exceeded for this canister"),
+        }
+    }
+}
+
+impl RegistrationError {
+    fn into_response(self) -> HttpResponse {
+        let body = ApiResponse::<Canister>::error(&self.to_string());
+        match self {
+            RegistrationError::Invalid(_) => HttpResponse::BadRequest().json(body),
+            RegistrationError::InTrash => HttpResponse::Conflict().json(body),
+            RegistrationError::RateLimited(retry_after) => rate_limit::too_many_requests(retry_after),
+            RegistrationError::Unresolved(_) => HttpResponse::UnprocessableEntity().json(body),
+            RegistrationError::Internal(_) => HttpResponse::InternalServerError().json(body),
+        }
+    }
+}
+
+/// Check a registration request and build the canister it would create
+///
+/// Takes a token from the canister's rate limit bucket; callers refund it if the canister is not saved.
+async fn validate_registration(
+    repo: &dyn Repository,
+    ic: &dyn IcClient,
+    request: &RegisterCanisterRequest,
+) -> Result<(Canister, Option<String>), RegistrationError> {
+    if request.canister_id.trim().is_empty() || request.principal.trim().is_empty() {
+        return Err(RegistrationError::Invalid("canister_id and principal are required".to_string()));
+    }
+    
+    let requested_type = match &request.canister_type {
+        Some(canister_type) => Some(
+            CanisterType::try_from(canister_type.clone())
+                .map_err(|_| RegistrationError::Invalid(format!("Invalid canister type: {}", canister_type)))?
+        ),
+        None => None,
+    };
+    
+    if let Some(module_hash) = &request.module_hash {
+        if !is_valid_hex_hash(module_hash) {
+            return Err(RegistrationError::Invalid("Invalid module hash format".to_string()));
+        }
+    }
+    
+    match repo.canisters().find_any_by_canister_id(&request.canister_id).await {
+        Ok(Some(existing)) if existing.deleted_at.is_some() => return Err(RegistrationError::InTrash),
+        Ok(Some(_)) => {
+            return Err(RegistrationError::Invalid(format!("Canister with ID {} already exists", request.canister_id)));
+        },
+        Ok(None) => {},
+        Err(e) => {
+            error!("Failed to check if canister exists: {}", e);
+            return Err(RegistrationError::Internal(format!("Failed to check if canister exists: {}", e)));
+        }
+    }
+    
+    // Limit registrations per canister ID, each one triggers IC calls
+    rate_limit::check_canister(CanisterRoute::Registration, &request.canister_id).map_err(RegistrationError::RateLimited)?;
+    
+    let (canister_type, candid_service) = match resolve_canister_type(ic, &request.canister_id, requested_type).await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            rate_limit::refund_canister(CanisterRoute::Registration, &request.canister_id);
+            return Err(RegistrationError::Unresolved(e));
+        }
+    };
+    
+    Ok((
+        Canister::new(
+            request.principal.clone(),
+            request.canister_id.clone(),
+            canister_type,
+            request.module_hash.clone(),
+        ),
+        candid_service,
+    ))
+}
+
+/// Check a requested canister type against the canister's `candid:service` metadata, or
+/// detect the type from it when none was requested
+///
+/// Returns the type together with the service definition, if it could be read. Canisters
+/// that keep their metadata private can only be registered with an explicit type.
+async fn resolve_canister_type(
+    ic: &dyn IcClient,
+    canister_id: &str,
+    requested: Option<CanisterType>,
+) -> Result<(CanisterType, Option<String>), String> {
+    let candid_service = match ic.get_candid_service(canister_id).await {
+        Ok(service) => service,
+        Err(e) => {
+            return match requested {
+                Some(canister_type) => {
+                    warn!("Registering canister {} as {} without checking its interface: {:#}", canister_id, canister_type.to_string(), e);
+                    Ok((canister_type, None))
+                },
+                None => Err(format!("Cannot detect the canister type, canister_type is required: {:#}", e)),
+            };
+        }
+    };
+    
+    let implemented = match Interface::implemented_by(&candid_service) {
+        Ok(implemented) => implemented,
+        Err(e) => {
+            return match requested {
+                Some(canister_type) => {
+                    warn!("Registering canister {} as {} with an unreadable interface: {:#}", canister_id, canister_type.to_string(), e);
+                    Ok((canister_type, Some(candid_service)))
+                },
+                None => Err(format!("Cannot detect the canister type, canister_type is required: {:#}", e)),
+            };
+        }
+    };
+    let detected: Vec<CanisterType> = implemented.iter().filter_map(Interface::canister_type).collect();
+    
+    match requested {
+        // Wallets have no interface of their own, but must not be one of the other types
+        Some(CanisterType::Wallet) => match detected.first() {
+            Some(detected) => Err(format!("Canister implements the {} interface, not a wallet", detected.to_string())),
+            None => Ok((CanisterType::Wallet, Some(candid_service))),
+        },
+        Some(canister_type) if detected.contains(&canister_type) => Ok((canister_type, Some(candid_service))),
+        Some(canister_type) => Err(format!("Canister does not implement the {} interface", canister_type.to_string())),
+        None => match detected.into_iter().next() {
+            Some(canister_type) => {
+                info!("Detected canister {} as {}", canister_id, canister_type.to_string());
+                Ok((canister_type, Some(candid_service)))
+            },
+            None => Err("Cannot detect the canister type from its interface, canister_type is required".to_string()),
+        },
+    }
+}
+
+/// Store the service definition read at registration, for later compatibility checks
+async fn save_interface(repo: &dyn Repository, canister: &Canister, candid_service: Option<String>) {
+    if let Some(candid_service) = candid_service {
+        let interface = CanisterInterface::new(canister.canister_id.clone(), candid_service);
+        if let Err(e) = repo.interfaces().save(&interface).await {
+            error!("Failed to save interface of canister {}: {}", canister.canister_id, e);
+        }
+    }
+}
+
+/// Fetch and verify the module hash of a newly registered canister in the background,
+/// or check the hash that was provided with the registration
+async fn schedule_module_hash_verification(
+    repo: &web::Data<dyn Repository>,
+    ic: &web::Data<dyn IcClient>,
+    canister: &Canister,
+) {
+    let canister_type_str = canister.canister_type.to_string();
+    
+    match &canister.module_hash {
+        None => {
+            let canister_id = canister.canister_id.clone();
+            let repo = repo.clone();
+            let ic = ic.clone();
+            
+            // Spawn a task to fetch and verify the module hash
+            tokio::spawn(async move {
+                if let Err(e) = update_and_verify_module_hash(repo, ic, &canister_id, &canister_type_str).await {
+                    error!("Failed to update module hash for canister {}: {}", canister_id, e);
+                }
+            });
+        },
+        Some(module_hash) => {
+            match repo.module_hashes().is_hash_verified(module_hash, &canister_type_str).await {
+                Ok(true) => {
+                    info!("Verified module hash for canister {}", canister.canister_id);
+                },
+                Ok(false) => {
+                    info!("Unverified module hash for canister {}", canister.canister_id);
+                },
+                Err(e) => {
+                    error!("Failed to check if module hash is verified: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Send a WebSocket notification about a newly registered canister
+fn notify_registered(canister: &Canister) {
+    websocket::broadcast_notification(
+        "canister_registered", 
+        serde_json::json!({
+            "canister_id": canister.canister_id,
+            "principal": canister.principal,
+            "canister_type": canister.canister_type.to_string(),
+            "timestamp": chrono::Utc::now().timestamp_millis()
+        })
+    );
+}
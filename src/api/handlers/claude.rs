@@ -7,9 +7,12 @@ use std::sync::{Arc, Mutex};
 use reqwest::Client;
 use log::{info, error};
 use std::env;
+use std::time::Instant;
 
 use crate::api::handlers::ApiResponse;
+use crate::api::rate_limit::{self, CanisterRoute};
 use crate::websocket;
+use crate::metrics;
 
 // Claude API configuration
 const CLAUDE_API_URL: &str = "https://api.anthropic.com/v1/messages";
@@ -98,12 +101,19 @@ pub async fn handle_claude_request(
         cache.get(&cache_key).map(|entry| entry.response_data.clone())
     };
     
+    metrics::record_dedup_lookup("claude", cached_response.is_some());
+    
     // If we have a cached response, return it
     if let Some(response) = cached_response {
         info!("Returning cached response for duplicate request: {}", cache_key);
         return HttpResponse::Ok().json(ApiResponse::success(response, "Cached response"));
     }
     
+    // Limit new requests per canister, cached duplicates are free
+    if let Err(retry_after) = rate_limit::check_canister(CanisterRoute::Claude, &canister_id) {
+        return rate_limit::too_many_requests(retry_after);
+    }
+    
     // First time seeing this request - process it
     info!("Processing new Claude API request from canister: {}", canister_id);
     
@@ -131,6 +141,7 @@ pub async fn handle_claude_request(
     });
     
     // Make the request to Claude API
+    let started = Instant::now();
     let response = match client.post(CLAUDE_API_URL)
         .header("x-api-key", claude_api_key)
         .header("anthropic-version", "2023-06-01")
@@ -140,6 +151,7 @@ pub async fn handle_claude_request(
         .await {
             Ok(res) => res,
             Err(e) => {
+                metrics::observe_claude_request(started, "error");
                 error!("Failed to send request to Claude API: {}", e);
                 return HttpResponse::InternalServerError().json(
                     ApiResponse::<()>::error(&format!("Failed to send request to Claude API: {}", e))
@@ -149,6 +161,7 @@ pub async fn handle_claude_request(
     
     // Check if the request was successful
     if !response.status().is_success() {
+        metrics::observe_claude_request(started, &response.status().as_u16().to_string());
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
         error!("Claude API returned error: {}", error_text);
         return HttpResponse::BadGateway().json(
@@ -160,6 +173,7 @@ pub async fn handle_claude_request(
     let claude_response: ClaudeResponse = match response.json().await {
         Ok(res) => res,
         Err(e) => {
+            metrics::observe_claude_request(started, "invalid_response");
             error!("Failed to parse Claude API response: {}", e);
             return HttpResponse::InternalServerError().json(
                 ApiResponse::<()>::error(&format!("Failed to parse Claude API response: {}", e))
@@ -167,6 +181,11 @@ pub async fn handle_claude_request(
         }
     };
     
+    metrics::observe_claude_request(started, "success");
+    if let Some(usage) = &claude_response.usage {
+        metrics::record_claude_usage(usage.input_tokens, usage.output_tokens);
+    }
+    
     // Cache the response (expires after 30 minutes)
     {
         let mut cache = CLAUDE_RESPONSE_CACHE.lock().unwrap();
@@ -0,0 +1,138 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::{info, error};
+use serde::Deserialize;
+
+use crate::api::auth::require_permission;
+use crate::api::handlers::{audit, ApiResponse};
+use crate::db::export::{ExportFormat, ImportMode, ImportReport, RegistryExport};
+use crate::db::models::admin::Permission;
+use crate::db::models::audit_log::AuditLogEntry;
+use crate::db::repository::Repository;
+
+/// Largest accepted import body (64 MiB)
+pub const MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+    /// Table to export; required for CSV, JSON exports all tables when omitted
+    pub table: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+    /// Table contained in a CSV import
+    pub table: Option<String>,
+    /// `upsert` (default) or `skip_existing`
+    pub mode: Option<String>,
+    pub dry_run: Option<bool>,
+}
+
+/// Export the registry as JSON or one table as CSV (admin only)
+pub async fn export_registry(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    query: web::Query<ExportQuery>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ViewRegistry) {
+        Ok(admin) => {
+            info!("Admin {} exporting registry", admin.username);
+
+            let format = match ExportFormat::parse(query.format.as_deref(), query.table.as_deref()) {
+                Ok(format) => format,
+                Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e.to_string())),
+            };
+
+            let export = match RegistryExport::load(repo.get_ref(), &format.tables()).await {
+                Ok(export) => export,
+                Err(e) => {
+                    error!("Failed to export registry: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to export registry: {}", e)));
+                }
+            };
+
+            match format {
+                ExportFormat::Csv(table) => match export.to_csv(table) {
+                    Ok(body) => HttpResponse::Ok()
+                        .content_type("text/csv")
+                        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.csv\"", table)))
+                        .body(body),
+                    Err(e) => {
+                        error!("Failed to write CSV export: {}", e);
+                        HttpResponse::InternalServerError()
+                            .json(ApiResponse::<()>::error(&format!("Failed to export registry: {}", e)))
+                    }
+                },
+                ExportFormat::Json(_) => HttpResponse::Ok()
+                    .insert_header(("Content-Disposition", "attachment; filename=\"registry.json\""))
+                    .json(export),
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Import a JSON export or a CSV table, optionally as a dry run (superadmin only)
+pub async fn import_registry(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ImportRegistry) {
+        Ok(admin) => {
+            let dry_run = query.dry_run.unwrap_or(false);
+            info!("Admin {} importing registry (dry run: {})", admin.username, dry_run);
+
+            let format = match ExportFormat::parse(query.format.as_deref(), query.table.as_deref()) {
+                Ok(format) => format,
+                Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e.to_string())),
+            };
+            let mode = match query.mode.clone().map(ImportMode::try_from).transpose() {
+                Ok(mode) => mode.unwrap_or_default(),
+                Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e.to_string())),
+            };
+
+            let export = match RegistryExport::parse(format, &body) {
+                Ok(export) => export,
+                Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e.to_string())),
+            };
+
+            match export.import(repo.get_ref(), mode, dry_run).await {
+                Ok(report) => {
+                    if report.applied {
+                        info!("Registry import applied by {}", admin.username);
+                        audit::record(repo.get_ref(), &req, AuditLogEntry::new("registry.import", "registry", "registry")
+                            .by_admin(&admin)
+                            .with_after(&report)).await;
+                    }
+
+                    let errors = report.error_count();
+                    if errors > 0 {
+                        HttpResponse::UnprocessableEntity().json(ApiResponse::<ImportReport> {
+                            success: false,
+                            message: format!("{} invalid rows, nothing was imported", errors),
+                            data: Some(report),
+                        })
+                    } else if dry_run {
+                        HttpResponse::Ok().json(ApiResponse::success(report, "Dry run completed, nothing was imported"))
+                    } else {
+                        HttpResponse::Ok().json(ApiResponse::success(report, "Registry imported successfully"))
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to import registry: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to import registry: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
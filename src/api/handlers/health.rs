@@ -0,0 +1,189 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use crate::db::repository::Repository;
+use crate::ic::agent::{check_ic_status, server_identity};
+use crate::jobs;
+use crate::websocket::{SessionCount, WebSocketServer};
+
+// Process start time, used to report uptime
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+
+// How long a successful IC status check is trusted before calling the IC again
+const IC_STATUS_CACHE_SECS: i64 = 30;
+// Upper bound for the IC status call so a slow boundary node cannot stall the probe
+const IC_STATUS_TIMEOUT_SECS: u64 = 5;
+// Default maximum age of the last successful job run before data counts as stale
+const DEFAULT_MAX_JOB_AGE_SECS: i64 = 300;
+
+#[derive(Clone)]
+struct IcStatusEntry {
+    result: Result<String, String>,
+    checked_at: DateTime<Utc>,
+}
+
+lazy_static::lazy_static! {
+    static ref IC_STATUS_CACHE: Mutex<Option<IcStatusEntry>> = Mutex::new(None);
+}
+
+#[derive(Serialize)]
+pub struct CheckResult {
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize)]
+pub struct JobCheck {
+    pub ok: bool,
+    pub last_success: Option<i64>,
+    pub age_secs: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct WebSocketCheck {
+    pub running: bool,
+    pub sessions: usize,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessReport {
+    pub status: String,
+    pub uptime: u64,
+    pub database: CheckResult,
+    pub identity: CheckResult,
+    pub ic: CheckResult,
+    pub jobs: HashMap<String, JobCheck>,
+    pub websocket: WebSocketCheck,
+}
+
+/// Record the process start time
+pub fn mark_started() {
+    let _ = STARTED_AT.set(Instant::now());
+}
+
+/// Seconds since the process started
+pub fn uptime_secs() -> u64 {
+    STARTED_AT.get().map(|started| started.elapsed().as_secs()).unwrap_or(0)
+}
+
+/// Liveness probe: the process is up and serving requests
+pub async fn liveness() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "ok",
+        "uptime": uptime_secs(),
+    }))
+}
+
+/// Readiness probe: the instance can serve fresh data
+pub async fn readiness(
+    repo: web::Data<dyn Repository>,
+    ws_server: web::Data<actix::Addr<WebSocketServer>>,
+) -> impl Responder {
+    info!("API: Readiness check");
+
+    let database = check_database(repo.get_ref()).await;
+    let identity = check_identity();
+    let ic = check_ic().await;
+    let jobs = check_jobs();
+    let websocket = check_websocket(&ws_server).await;
+
+    // The WebSocket server is reported but does not gate readiness
+    let ready = database.ok && identity.ok && ic.ok && jobs.values().all(|job| job.ok);
+
+    let report = ReadinessReport {
+        status: if ready { "ready" } else { "not_ready" }.to_string(),
+        uptime: uptime_secs(),
+        database,
+        identity,
+        ic,
+        jobs,
+        websocket,
+    };
+
+    if ready {
+        HttpResponse::Ok().json(report)
+    } else {
+        warn!("Readiness check failed");
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+// Check that a connection can be checked out of the pool and used
+async fn check_database(repo: &dyn Repository) -> CheckResult {
+    match repo.registry().ping().await {
+        Ok(_) => CheckResult { ok: true, detail: format!("connected ({})", repo.backend()) },
+        Err(e) => CheckResult { ok: false, detail: format!("{:#}", e) },
+    }
+}
+
+// Check that the server identity has been loaded
+fn check_identity() -> CheckResult {
+    match server_identity() {
+        Some(identity) => CheckResult { ok: true, detail: format!("{} {}", identity.key_type, identity.principal) },
+        None => CheckResult { ok: false, detail: "identity not loaded".to_string() },
+    }
+}
+
+// Check IC reachability, reusing a recent result when available
+async fn check_ic() -> CheckResult {
+    let cached = IC_STATUS_CACHE.lock().unwrap().clone();
+
+    let entry = match cached {
+        Some(entry) if Utc::now() - entry.checked_at < Duration::seconds(IC_STATUS_CACHE_SECS) => entry,
+        _ => {
+            let result = match tokio::time::timeout(
+                std::time::Duration::from_secs(IC_STATUS_TIMEOUT_SECS),
+                check_ic_status("https://ic0.app"),
+            ).await {
+                Ok(Ok(status)) => Ok(status),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err("IC status call timed out".to_string()),
+            };
+            let entry = IcStatusEntry { result, checked_at: Utc::now() };
+            *IC_STATUS_CACHE.lock().unwrap() = Some(entry.clone());
+            entry
+        }
+    };
+
+    match entry.result {
+        Ok(status) => CheckResult { ok: true, detail: status },
+        Err(e) => CheckResult { ok: false, detail: e },
+    }
+}
+
+// Check that every background job has succeeded recently
+fn check_jobs() -> HashMap<String, JobCheck> {
+    let max_age = env::var("READINESS_MAX_JOB_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_MAX_JOB_AGE_SECS);
+    let now = Utc::now().timestamp();
+
+    jobs::JOB_NAMES
+        .iter()
+        .map(|job| {
+            let last_success = jobs::last_success(job);
+            let age_secs = last_success.map(|ts| now - ts);
+            let check = JobCheck {
+                ok: age_secs.is_some_and(|age| age <= max_age),
+                last_success,
+                age_secs,
+            };
+            (job.to_string(), check)
+        })
+        .collect()
+}
+
+// Ask the WebSocket server actor for its session count
+async fn check_websocket(ws_server: &actix::Addr<WebSocketServer>) -> WebSocketCheck {
+    match ws_server.send(SessionCount).await {
+        Ok(sessions) => WebSocketCheck { running: true, sessions },
+        Err(_) => WebSocketCheck { running: false, sessions: 0 },
+    }
+}
@@ -0,0 +1,80 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use log::{info, warn, error};
+use serde::Serialize;
+
+use crate::api::auth::require_permission;
+use crate::api::handlers::{audit, ApiResponse};
+use crate::db::models::admin::Permission;
+use crate::db::models::audit_log::AuditLogEntry;
+use crate::db::repository::Repository;
+use crate::ic::agent::{self, IdentityInfo, RetiredIdentity};
+use crate::ic::client::IcClient;
+
+/// The identity the server signs IC calls with, and the ones it replaced
+#[derive(Serialize)]
+pub struct ServerIdentity {
+    /// `None` when no identity file could be loaded and calls are anonymous
+    pub current: Option<IdentityInfo>,
+    pub retired: Vec<RetiredIdentity>,
+}
+
+// Collect the current and retired identities
+fn server_identity() -> ServerIdentity {
+    let retired = agent::retired_identities().unwrap_or_else(|e| {
+        warn!("Failed to list retired identities: {:#}", e);
+        Vec::new()
+    });
+    ServerIdentity { current: agent::server_identity(), retired }
+}
+
+/// Get the server principal and the principals of retired identities (admin only)
+pub async fn get_identity(req: HttpRequest) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ViewRegistry) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+
+            HttpResponse::Ok()
+                .json(ApiResponse::success(server_identity(), "Retrieved server identity"))
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Replace the server identity with a new key, keeping the previous one (superadmin only)
+pub async fn rotate_identity(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ManageIdentity) {
+        Ok(admin) => {
+            info!("Admin {} rotating the server identity", admin.username);
+
+            // Key generation and file I/O block, and are slow for encrypted identities, keep them off the async workers
+            let rotated = web::block(agent::rotate_identity).await
+                .context("Identity rotation panicked")
+                .and_then(|result| result);
+            match rotated {
+                Ok((previous, current)) => {
+                    // Calls made from now on sign with the new key
+                    ic.reset_agent().await;
+                    audit::record(repo.get_ref(), &req, AuditLogEntry::new("identity.rotate", "identity", &current.principal)
+                        .by_admin(&admin)
+                        .with_before(&previous)
+                        .with_after(&current)).await;
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(server_identity(), "Server identity rotated successfully"))
+                }
+                Err(e) => {
+                    error!("Failed to rotate server identity: {:#}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to rotate server identity: {:#}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,57 @@
+use actix_web::{web, HttpResponse, Responder};
+use log::{info, error};
+use serde::Serialize;
+
+use crate::api::handlers::ApiResponse;
+use crate::ic::candid::interface::{Binding, Interface};
+
+#[derive(Serialize)]
+pub struct InterfaceFiles {
+    pub name: String,
+    /// File names to request from `/interfaces/{file_name}`
+    pub files: Vec<String>,
+}
+
+/// List the served interfaces and their files
+pub async fn list_interfaces() -> impl Responder {
+    info!("API: List interfaces");
+
+    let interfaces: Vec<InterfaceFiles> = Interface::ALL
+        .iter()
+        .map(|interface| InterfaceFiles {
+            name: interface.name().to_string(),
+            files: Binding::ALL
+                .iter()
+                .map(|binding| format!("{}{}", interface.name(), binding.extension()))
+                .collect(),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(ApiResponse::success(interfaces, "Interfaces retrieved successfully"))
+}
+
+/// Serve a Candid interface or a binding generated from it, e.g. `ledger.did` or `ledger.did.d.ts`
+pub async fn get_interface_file(path: web::Path<String>) -> impl Responder {
+    let file_name = path.into_inner();
+    info!("API: Get interface file: {}", file_name);
+
+    // Only known names are served, nothing is read from the file system
+    let (interface, binding) = match Interface::from_file_name(&file_name) {
+        Some(found) => found,
+        None => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<()>::error(&format!("Interface file {} not found", file_name))
+            );
+        }
+    };
+
+    match interface.render(binding) {
+        Ok(body) => HttpResponse::Ok().content_type(binding.content_type()).body(body),
+        Err(e) => {
+            error!("Failed to render interface file {}: {:#}", file_name, e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to render interface file {}: {}", file_name, e))
+            )
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use actix_web::{web, HttpResponse, Responder};
+use log::{info, error};
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::ledger_transaction::{LedgerIndexState, LedgerTransaction};
+use crate::api::handlers::ApiResponse;
+use crate::db::repository::Repository;
+
+// Default and maximum page size for transaction history queries
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Deserialize)]
+pub struct AccountTransactionsQuery {
+    /// Hex-encoded 32-byte subaccount, the default subaccount when omitted
+    pub subaccount: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AccountTransactionsPage {
+    pub transactions: Vec<LedgerTransaction>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Normalize a subaccount to the form stored by the indexer, the default subaccount being `None`
+pub fn parse_subaccount(subaccount: Option<&str>) -> Result<Option<String>, String> {
+    let subaccount = match subaccount.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(subaccount) => subaccount.to_lowercase(),
+        None => return Ok(None),
+    };
+    if subaccount.len() != 64 || !subaccount.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Subaccount must be 64 hex characters".to_string());
+    }
+    if subaccount.chars().all(|c| c == '0') {
+        return Ok(None);
+    }
+    Ok(Some(subaccount))
+}
+
+/// Get all ledgers
+pub async fn get_all_ledgers(repo: web::Data<dyn Repository>) -> impl Responder {
+    info!("API: Get all ledgers");
+    
+    match repo.ledgers().find_all().await {
+        Ok(ledgers) => {
+            HttpResponse::Ok().json(
+                ApiResponse::success(ledgers, "Ledgers retrieved successfully")
+            )
+        },
+        Err(e) => {
+            error!("Failed to get ledgers: {}", e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<Vec<LedgerInfo>>::error(&format!("Failed to get ledgers: {}", e))
+            )
+        }
+    }
+}
+
+/// Get a specific ledger
+pub async fn get_ledger(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let canister_id = path.into_inner();
+    info!("API: Get ledger: {}", canister_id);
+    
+    match repo.ledgers().find_by_canister_id(&canister_id).await {
+        Ok(Some(ledger)) => {
+            HttpResponse::Ok().json(
+                ApiResponse::success(ledger, "Ledger retrieved successfully")
+            )
+        },
+        Ok(_) => {
+            HttpResponse::NotFound().json(
+                ApiResponse::<LedgerInfo>::error(&format!("Ledger with canister ID {} not found", canister_id))
+            )
+        },
+        Err(e) => {
+            error!("Failed to get ledger: {}", e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<LedgerInfo>::error(&format!("Failed to get ledger: {}", e))
+            )
+        }
+    }
+}
+
+/// Get the indexing progress of a ledger
+pub async fn get_ledger_index_state(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let canister_id = path.into_inner();
+    info!("API: Get ledger index state: {}", canister_id);
+
+    match repo.ledger_transactions().index_state(&canister_id).await {
+        Ok(Some(state)) => {
+            HttpResponse::Ok().json(
+                ApiResponse::success(state, "Ledger index state retrieved successfully")
+            )
+        },
+        Ok(None) => {
+            HttpResponse::NotFound().json(
+                ApiResponse::<LedgerIndexState>::error(&format!("Ledger {} has not been indexed yet", canister_id))
+            )
+        },
+        Err(e) => {
+            error!("Failed to get ledger index state: {}", e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<LedgerIndexState>::error(&format!("Failed to get ledger index state: {}", e))
+            )
+        }
+    }
+}
+
+/// Get the indexed transactions of an account on a ledger, newest first
+pub async fn get_account_transactions(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<(String, String)>,
+    query: web::Query<AccountTransactionsQuery>,
+) -> impl Responder {
+    let (canister_id, owner) = path.into_inner();
+    info!("API: Get transactions of {} on ledger {}", owner, canister_id);
+
+    let query = query.into_inner();
+    let subaccount = match parse_subaccount(query.subaccount.as_deref()) {
+        Ok(subaccount) => subaccount,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ApiResponse::<AccountTransactionsPage>::error(&e));
+        }
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match repo.ledgers().find_by_canister_id(&canister_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<AccountTransactionsPage>::error(&format!("Ledger with canister ID {} not found", canister_id))
+            );
+        }
+        Err(e) => {
+            error!("Failed to get ledger: {}", e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<AccountTransactionsPage>::error(&format!("Failed to get ledger: {}", e))
+            );
+        }
+    }
+
+    let transactions = repo.ledger_transactions();
+    let result = match transactions.count_by_account(&canister_id, &owner, subaccount.as_deref()).await {
+        Ok(total) => transactions
+            .find_by_account(&canister_id, &owner, subaccount.as_deref(), limit, offset).await
+            .map(|transactions| (transactions, total)),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((transactions, total)) => {
+            HttpResponse::Ok().json(
+                ApiResponse::success(
+                    AccountTransactionsPage { transactions, total, limit, offset },
+                    "Account transactions retrieved successfully",
+                )
+            )
+        },
+        Err(e) => {
+            error!("Failed to get account transactions: {}", e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<AccountTransactionsPage>::error(&format!("Failed to get account transactions: {}", e))
+            )
+        }
+    }
+}
@@ -2,10 +2,10 @@ use actix_web::{web, HttpResponse, Responder};
 use log::{info, error};
 use serde::Serialize;
 
-use crate::db::DbPool;
 use crate::db::models::miner_info::MinerInfo;
 use crate::db::models::mining_stats::MiningStats;
 use crate::api::handlers::ApiResponse;
+use crate::db::repository::Repository;
 
 #[derive(Serialize)]
 pub struct MinerWithStats {
@@ -13,21 +13,36 @@ pub struct MinerWithStats {
     pub mining_stats: Option<MiningStats>,
 }
 
+#[derive(Serialize)]
+pub struct RewardEstimate {
+    /// Estimated reward in the token's smallest unit
+    pub amount: u64,
+    /// Estimated reward formatted with the token's decimals
+    pub formatted: String,
+}
+
+#[derive(Serialize)]
+pub struct MiningProjection {
+    pub canister_id: String,
+    pub token_canister_id: String,
+    pub ticker: String,
+    pub decimals: u8,
+    pub miner_hash_rate: f64,
+    pub network_hash_rate: f64,
+    pub network_share: f64,
+    pub average_block_time: f64,
+    pub current_block_reward: u64,
+    pub remaining_supply: u64,
+    pub per_hour: RewardEstimate,
+    pub per_day: RewardEstimate,
+    pub per_week: RewardEstimate,
+}
+
 /// Get all miners
-pub async fn get_all_miners(db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn get_all_miners(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get all miners");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<MinerInfo>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match MinerInfo::find_all(&conn) {
+    match repo.miners().find_all().await {
         Ok(miners) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(miners, "Miners retrieved successfully")
@@ -44,23 +59,13 @@ pub async fn get_all_miners(db_pool: web::Data<DbPool>) -> impl Responder {
 
 /// Get a specific miner
 pub async fn get_miner(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Get miner: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<MinerInfo>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match MinerInfo::find_by_canister_id(&conn, &canister_id) {
+    match repo.miners().find_by_canister_id(&canister_id).await {
         Ok(Some(miner)) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(miner, "Miner retrieved successfully")
@@ -82,23 +87,13 @@ pub async fn get_miner(
 
 /// Get mining stats for a specific miner
 pub async fn get_miner_stats(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Get mining stats for miner: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<MiningStats>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match MiningStats::find_by_canister_id(&conn, &canister_id) {
+    match repo.mining_stats().find_by_canister_id(&canister_id).await {
         Ok(Some(stats)) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(stats, "Mining stats retrieved successfully")
@@ -120,23 +115,13 @@ pub async fn get_miner_stats(
 
 /// Get miners by token
 pub async fn get_miners_by_token(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let token_canister_id = path.into_inner();
     info!("API: Get miners by token: {}", token_canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<MinerInfo>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match MinerInfo::find_by_token(&conn, &token_canister_id) {
+    match repo.miners().find_by_token(&token_canister_id).await {
         Ok(miners) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(miners, "Miners retrieved successfully")
@@ -153,30 +138,22 @@ pub async fn get_miners_by_token(
 
 /// Delete a miner
 pub async fn delete_miner(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Delete miner: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Database error"));
-        }
-    };
-    
     // First check if the miner exists
-    match MinerInfo::find_by_canister_id(&conn, &canister_id) {
+    match repo.miners().find_by_canister_id(&canister_id).await {
         Ok(Some(_)) => {
             // Delete the miner from the database
-            match MinerInfo::delete(&conn, &canister_id) {
+            match repo.miners().delete(&canister_id).await {
                 Ok(true) => {
                     info!("Miner deleted: {}", canister_id);
                     
                     // Also delete any associated mining stats
-                    if let Err(e) = MiningStats::delete(&conn, &canister_id) {
+                    if let Err(e) = repo.mining_stats().delete(&canister_id).await {
                         error!("Failed to delete associated mining stats: {}", e);
                         // Continue anyway, as this is not critical
                     }
@@ -205,20 +182,10 @@ pub async fn delete_miner(
 }
 
 /// Get all mining stats
-pub async fn get_all_mining_stats(db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn get_all_mining_stats(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get all mining stats");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<MiningStats>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match MiningStats::find_all(&conn) {
+    match repo.mining_stats().find_all().await {
         Ok(stats) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(stats, "Mining stats retrieved successfully")
@@ -231,4 +198,160 @@ pub async fn get_all_mining_stats(db_pool: web::Data<DbPool>) -> impl Responder
             )
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Get projected mining rewards for a specific miner
+pub async fn get_miner_projection(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let canister_id = path.into_inner();
+    info!("API: Get mining projection for miner: {}", canister_id);
+    
+    let miner = match repo.miners().find_by_canister_id(&canister_id).await {
+        Ok(Some(miner)) => miner,
+        Ok(_) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<MiningProjection>::error(&format!("Miner with canister ID {} not found", canister_id))
+            );
+        },
+        Err(e) => {
+            error!("Failed to get miner: {}", e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<MiningProjection>::error(&format!("Failed to get miner: {}", e))
+            );
+        }
+    };
+    
+    let token_canister_id = match &miner.current_token {
+        Some(token) => token.clone(),
+        None => {
+            return HttpResponse::BadRequest().json(
+                ApiResponse::<MiningProjection>::error(&format!("Miner {} is not connected to a token", canister_id))
+            );
+        }
+    };
+    
+    let stats = match repo.mining_stats().find_by_canister_id(&canister_id).await {
+        Ok(Some(stats)) => stats,
+        Ok(_) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<MiningProjection>::error(&format!("Mining stats for canister ID {} not found", canister_id))
+            );
+        },
+        Err(e) => {
+            error!("Failed to get mining stats: {}", e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<MiningProjection>::error(&format!("Failed to get mining stats: {}", e))
+            );
+        }
+    };
+    
+    let token = match repo.tokens().find_by_canister_id(&token_canister_id).await {
+        Ok(Some(token)) => token,
+        Ok(_) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<MiningProjection>::error(&format!("Token with canister ID {} not found", token_canister_id))
+            );
+        },
+        Err(e) => {
+            error!("Failed to get token: {}", e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<MiningProjection>::error(&format!("Failed to get token: {}", e))
+            );
+        }
+    };
+    
+    let average_block_time = match token.average_block_time {
+        Some(block_time) if block_time > 0.0 => block_time,
+        _ => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<MiningProjection>::error(&format!("Average block time for token {} is not available yet", token_canister_id))
+            );
+        }
+    };
+    
+    let mut network_hash_rate = match repo.mining_stats().total_hash_rate_for_token(&token_canister_id).await {
+        Ok(rate) => rate,
+        Err(e) => {
+            error!("Failed to get network hash rate: {}", e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<MiningProjection>::error(&format!("Failed to get network hash rate: {}", e))
+            );
+        }
+    };
+    
+    // An idle miner is not part of the network total, so project as if it joined
+    if !miner.is_mining {
+        network_hash_rate += stats.last_hash_rate;
+    }
+    
+    let network_share = if network_hash_rate > 0.0 {
+        (stats.last_hash_rate / network_hash_rate).min(1.0)
+    } else {
+        0.0
+    };
+    
+    let remaining_supply = remaining_supply(token.total_supply, &token.mining_progress_percentage);
+    
+    let estimate = |period_secs: f64| {
+        let blocks = period_secs / average_block_time;
+        // The network cannot emit more than what is left to mine
+        let network_emission = (blocks * token.current_block_reward as f64).min(remaining_supply as f64);
+        let amount = (network_emission * network_share).floor() as u64;
+        RewardEstimate {
+            amount,
+            formatted: format_token_amount(amount, token.decimals),
+        }
+    };
+    
+    let projection = MiningProjection {
+        canister_id: canister_id.clone(),
+        token_canister_id: token_canister_id.clone(),
+        ticker: token.ticker.clone(),
+        decimals: token.decimals,
+        miner_hash_rate: stats.last_hash_rate,
+        network_hash_rate,
+        network_share,
+        average_block_time,
+        current_block_reward: token.current_block_reward,
+        remaining_supply,
+        per_hour: estimate(3_600.0),
+        per_day: estimate(86_400.0),
+        per_week: estimate(604_800.0),
+    };
+    
+    HttpResponse::Ok().json(
+        ApiResponse::success(projection, "Mining projection calculated successfully")
+    )
+}
+
+/// Helper function to derive the unmined supply from the reported mining progress
+fn remaining_supply(total_supply: u64, mining_progress_percentage: &str) -> u64 {
+    let progress = mining_progress_percentage
+        .trim()
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .unwrap_or(0.0)
+        .clamp(0.0, 100.0);
+    
+    (total_supply as f64 * (100.0 - progress) / 100.0).floor() as u64
+}
+
+/// Helper function to format a raw token amount using the token's decimals
+pub fn format_token_amount(amount: u64, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    
+    let digits = format!("{:0>width$}", amount, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
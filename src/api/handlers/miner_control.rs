@@ -0,0 +1,191 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use candid::Principal;
+use log::{info, warn, error};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api::auth::require_permission;
+use crate::api::handlers::query::load_interface;
+use crate::api::handlers::{audit, ApiResponse};
+use crate::db::models::admin::Permission;
+use crate::db::models::audit_log::AuditLogEntry;
+use crate::db::models::canister::CanisterType;
+use crate::db::models::miner_command::{MinerCommand, MinerCommandKind};
+use crate::db::repository::Repository;
+use crate::ic::candid::json::CandidMethod;
+use crate::ic::client::IcClient;
+
+// Default and maximum number of commands returned per page
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// A control command for a miner, tagged by `command`
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum MinerCommandRequest {
+    StartMining,
+    StopMining,
+    SetToken { token: String },
+    SetSpeed { speed_percentage: u8 },
+}
+
+impl MinerCommandRequest {
+    // The command and its update call arguments as JSON
+    fn into_parts(self) -> Result<(MinerCommandKind, Vec<Value>), String> {
+        match self {
+            MinerCommandRequest::StartMining => Ok((MinerCommandKind::StartMining, vec![])),
+            MinerCommandRequest::StopMining => Ok((MinerCommandKind::StopMining, vec![])),
+            MinerCommandRequest::SetToken { token } => {
+                Principal::from_text(&token).map_err(|_| format!("Invalid token canister ID: {}", token))?;
+                Ok((MinerCommandKind::SetToken, vec![json!(token)]))
+            }
+            MinerCommandRequest::SetSpeed { speed_percentage } => {
+                if !(1..=100).contains(&speed_percentage) {
+                    return Err("speed_percentage must be between 1 and 100".to_string());
+                }
+                Ok((MinerCommandKind::SetSpeed, vec![json!(speed_percentage)]))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CommandHistoryQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Send a control command to a miner with an update call from the server identity (miner operators)
+pub async fn send_miner_command(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
+    path: web::Path<String>,
+    request: web::Json<MinerCommandRequest>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    let admin = match require_permission(&req, Permission::ControlMiners) {
+        Ok(admin) => admin,
+        Err(e) => return e.into_response(),
+    };
+
+    let canister_id = path.into_inner();
+    let (kind, args) = match request.into_inner().into_parts() {
+        Ok(parts) => parts,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e)),
+    };
+    info!("Admin {} sending {} to miner {}", admin.username, kind, canister_id);
+
+    match repo.canisters().find_by_canister_id(&canister_id).await {
+        Ok(Some(canister)) if canister.canister_type == CanisterType::Miner => {}
+        Ok(Some(_)) => {
+            return HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::error(&format!("Canister {} is not a miner", canister_id)));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(ApiResponse::<()>::error(&format!("Canister with ID {} not found", canister_id)));
+        }
+        Err(e) => {
+            error!("Failed to get canister: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(&format!("Failed to get canister: {}", e)));
+        }
+    }
+
+    // Argument and reply types come from the miner's own interface
+    let candid_service = match load_interface(repo.get_ref(), ic.get_ref(), &canister_id).await {
+        Ok(candid_service) => candid_service,
+        Err(e) => {
+            error!("Failed to get interface of canister {}: {:#}", canister_id, e);
+            return HttpResponse::BadGateway()
+                .json(ApiResponse::<()>::error(&format!("Failed to get the canister's interface: {:#}", e)));
+        }
+    };
+    let method = match CandidMethod::from_service(&candid_service, kind.method()) {
+        Ok(method) => method,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&format!("{:#}", e))),
+    };
+    let arg = match method.encode_args(&args) {
+        Ok(arg) => arg,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&format!("{:#}", e))),
+    };
+
+    // Record the command before calling, so it is kept even if the call never returns
+    let mut command = MinerCommand::new(&canister_id, kind, Value::Array(args), &admin);
+    if let Err(e) = repo.miner_commands().save(&command).await {
+        error!("Failed to save miner command: {}", e);
+        return HttpResponse::InternalServerError()
+            .json(ApiResponse::<()>::error(&format!("Failed to save miner command: {}", e)));
+    }
+
+    match ic.update_raw(&canister_id, kind.method(), arg).await.and_then(|bytes| method.decode_reply(&bytes)) {
+        // Miners reject commands with an `Err` result
+        Ok(reply) => match reply.get("Err").cloned() {
+            Some(err) => {
+                let message = err.as_str().map(str::to_string).unwrap_or_else(|| err.to_string());
+                command.fail(Some(reply), format!("Rejected by the miner: {}", message));
+            }
+            None => command.succeed(reply),
+        },
+        Err(e) => {
+            error!("Failed to call {} on miner {}: {:#}", kind.method(), canister_id, e);
+            command.fail(None, format!("{:#}", e));
+        }
+    }
+
+    if let Err(e) = repo.miner_commands().save(&command).await {
+        error!("Failed to save outcome of miner command {}: {}", command.id, e);
+    }
+    audit::record(repo.get_ref(), &req, AuditLogEntry::new("miner.command", "miner", &canister_id)
+        .by_admin(&admin)
+        .with_after(&command)).await;
+
+    if let Some(error) = command.error.clone() {
+        return HttpResponse::BadGateway().json(ApiResponse { success: false, message: error, data: Some(command) });
+    }
+
+    // Pick up the new state now instead of on the next miner update
+    match ic.get_miner_info(&canister_id).await {
+        Ok((miner_info, _)) => {
+            if let Err(e) = repo.miners().save(&miner_info).await {
+                warn!("Failed to save miner info of {}: {}", canister_id, e);
+            }
+        }
+        Err(e) => warn!("Failed to refresh miner {} after {}: {}", canister_id, kind, e),
+    }
+
+    HttpResponse::Ok().json(ApiResponse::success(command, "Miner command completed successfully"))
+}
+
+/// List the commands sent to a miner, newest first (admin only)
+pub async fn get_miner_commands(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+    query: web::Query<CommandHistoryQuery>,
+) -> HttpResponse {
+    // Check the authenticated admin's role
+    match require_permission(&req, Permission::ViewRegistry) {
+        Ok(admin) => {
+            info!("Admin authenticated: {}", admin.username);
+
+            let canister_id = path.into_inner();
+            let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+            let offset = query.offset.unwrap_or(0).max(0);
+
+            match repo.miner_commands().find_by_canister_id(&canister_id, limit, offset).await {
+                Ok(commands) => {
+                    HttpResponse::Ok()
+                        .json(ApiResponse::success(commands, "Retrieved miner commands"))
+                }
+                Err(e) => {
+                    error!("Failed to get miner commands: {}", e);
+                    HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error(&format!("Failed to get miner commands: {}", e)))
+                }
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
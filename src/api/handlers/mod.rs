@@ -1,9 +1,20 @@
 pub mod canister;
 pub mod token;
+pub mod ledger;
 pub mod miner;
+pub mod miner_control;
+pub mod principal;
+pub mod interface;
+pub mod query;
 pub mod system;
 pub mod admin;
 pub mod claude;
+pub mod health;
+pub mod session;
+pub mod audit;
+pub mod export;
+pub mod backup;
+pub mod identity;
 
 use serde::{Deserialize, Serialize};
 
@@ -0,0 +1,165 @@
+use actix_web::{web, HttpResponse, Responder};
+use candid::Principal;
+use log::{info, error};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::api::handlers::ApiResponse;
+use crate::api::handlers::miner::format_token_amount;
+use crate::db::repository::Repository;
+
+/// A canister with the latest information the registry holds for its type
+#[derive(Serialize)]
+pub struct PortfolioEntry {
+    pub canister: Canister,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_info: Option<TokenInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledger_info: Option<LedgerInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub miner_info: Option<MinerInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mining_stats: Option<MiningStats>,
+}
+
+/// Rewards mined for one token, amounts of different tokens cannot be added up
+#[derive(Serialize)]
+pub struct TokenRewards {
+    pub token_canister_id: String,
+    pub ticker: Option<String>,
+    /// Total in the token's smallest unit
+    pub total_rewards: u64,
+    /// Total formatted with the token's decimals, when the token is known
+    pub formatted: Option<String>,
+}
+
+/// Totals across a principal's miners
+#[derive(Serialize)]
+pub struct MiningSummary {
+    pub miner_count: usize,
+    pub active_miners: usize,
+    pub total_hash_rate: f64,
+    pub blocks_mined: u64,
+    pub rewards: Vec<TokenRewards>,
+}
+
+#[derive(Serialize)]
+pub struct PrincipalPortfolio {
+    pub principal: String,
+    pub canister_count: usize,
+    /// Canisters grouped by type (`token`, `miner`, `wallet`, `ledger`)
+    pub canisters: BTreeMap<String, Vec<PortfolioEntry>>,
+    pub mining: MiningSummary,
+}
+
+// Look up the information stored for a canister's type
+async fn load_entry(repo: &dyn Repository, canister: Canister) -> anyhow::Result<PortfolioEntry> {
+    let mut entry = PortfolioEntry {
+        canister,
+        token_info: None,
+        ledger_info: None,
+        miner_info: None,
+        mining_stats: None,
+    };
+    let canister_id = entry.canister.canister_id.as_str();
+    match entry.canister.canister_type {
+        CanisterType::Token => entry.token_info = repo.tokens().find_by_canister_id(canister_id).await?,
+        CanisterType::Ledger => entry.ledger_info = repo.ledgers().find_by_canister_id(canister_id).await?,
+        CanisterType::Miner => {
+            entry.miner_info = repo.miners().find_by_canister_id(canister_id).await?;
+            entry.mining_stats = repo.mining_stats().find_by_canister_id(canister_id).await?;
+        }
+        CanisterType::Wallet => {}
+    }
+    Ok(entry)
+}
+
+// Add up hash rates and blocks of the miners, and their rewards per token
+async fn summarize_mining(repo: &dyn Repository, miners: &[PortfolioEntry]) -> anyhow::Result<MiningSummary> {
+    let mut summary = MiningSummary {
+        miner_count: miners.len(),
+        active_miners: 0,
+        total_hash_rate: 0.0,
+        blocks_mined: 0,
+        rewards: Vec::new(),
+    };
+
+    let mut rewards_by_token: BTreeMap<String, u64> = BTreeMap::new();
+    for miner in miners {
+        let is_mining = miner.miner_info.as_ref().is_some_and(|info| info.is_mining);
+        if is_mining {
+            summary.active_miners += 1;
+        }
+        if let Some(stats) = &miner.mining_stats {
+            // An idle miner's last hash rate is not part of what it mines now
+            if is_mining {
+                summary.total_hash_rate += stats.last_hash_rate;
+            }
+            summary.blocks_mined += stats.blocks_mined;
+            if let Some(token) = miner.miner_info.as_ref().and_then(|info| info.current_token.clone()) {
+                *rewards_by_token.entry(token).or_default() += stats.total_rewards;
+            }
+        }
+    }
+
+    for (token_canister_id, total_rewards) in rewards_by_token {
+        let token = repo.tokens().find_by_canister_id(&token_canister_id).await?;
+        summary.rewards.push(TokenRewards {
+            token_canister_id,
+            ticker: token.as_ref().map(|token| token.ticker.clone()),
+            total_rewards,
+            formatted: token.map(|token| format_token_amount(total_rewards, token.decimals)),
+        });
+    }
+    Ok(summary)
+}
+
+/// Get every canister a principal registered, grouped by type, with totals across its miners
+pub async fn get_principal_portfolio(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let principal = path.into_inner();
+    info!("API: Get portfolio of principal: {}", principal);
+
+    if Principal::from_text(&principal).is_err() {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<PrincipalPortfolio>::error(&format!("Invalid principal: {}", principal))
+        );
+    }
+
+    let result = async {
+        let canisters = repo.canisters().find_by_principal(&principal).await?;
+        let canister_count = canisters.len();
+
+        let mut groups: BTreeMap<String, Vec<PortfolioEntry>> = BTreeMap::new();
+        for canister in canisters {
+            let canister_type = canister.canister_type.to_string();
+            let entry = load_entry(repo.get_ref(), canister).await?;
+            groups.entry(canister_type).or_default().push(entry);
+        }
+
+        let miners = groups.get("miner").map(Vec::as_slice).unwrap_or_default();
+        let mining = summarize_mining(repo.get_ref(), miners).await?;
+        anyhow::Ok(PrincipalPortfolio { principal: principal.clone(), canister_count, canisters: groups, mining })
+    }.await;
+
+    match result {
+        Ok(portfolio) => {
+            HttpResponse::Ok().json(
+                ApiResponse::success(portfolio, "Portfolio retrieved successfully")
+            )
+        },
+        Err(e) => {
+            error!("Failed to get portfolio of {}: {}", principal, e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<PrincipalPortfolio>::error(&format!("Failed to get portfolio: {}", e))
+            )
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn, error};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+
+use crate::api::handlers::ApiResponse;
+use crate::db::models::canister::CanisterType;
+use crate::db::models::canister_interface::CanisterInterface;
+use crate::db::repository::Repository;
+use crate::ic::candid::json::CandidMethod;
+use crate::ic::client::IcClient;
+
+// How long a query reply is served before querying the canister again
+const QUERY_CACHE_SECS: i64 = 5;
+
+// Methods that may be queried when QUERY_PROXY_METHODS is not set
+const DEFAULT_QUERY_METHODS: &str = "token:get_all_info,miner:get_info,miner:get_mining_stats,\
+    ledger:icrc1_*,ledger:icrc2_allowance,ledger:icrc3_*";
+
+#[derive(Clone)]
+struct QueryEntry {
+    reply: Value,
+    fetched_at: DateTime<Utc>,
+}
+
+// Recent replies, keyed by canister, method and arguments
+lazy_static::lazy_static! {
+    static ref QUERY_CACHE: Mutex<HashMap<String, QueryEntry>> = Mutex::new(HashMap::new());
+}
+
+/// A `type:method` entry of the allowlist, the method may end in `*` to match a prefix
+struct AllowedMethod {
+    canister_type: String,
+    method: String,
+}
+
+impl AllowedMethod {
+    fn parse(entry: &str) -> Option<Self> {
+        let (canister_type, method) = entry.split_once(':')?;
+        let canister_type = CanisterType::try_from(canister_type.trim().to_string()).ok()?;
+        let method = method.trim();
+        if method.is_empty() {
+            return None;
+        }
+        Some(Self { canister_type: canister_type.to_string(), method: method.to_string() })
+    }
+
+    fn allows(&self, canister_type: &str, method: &str) -> bool {
+        self.canister_type == canister_type && match self.method.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => self.method == method,
+        }
+    }
+}
+
+static ALLOWED_METHODS: OnceLock<Vec<AllowedMethod>> = OnceLock::new();
+
+// Load the allowlist from QUERY_PROXY_METHODS
+fn allowed_methods() -> &'static [AllowedMethod] {
+    ALLOWED_METHODS.get_or_init(|| {
+        env::var("QUERY_PROXY_METHODS")
+            .unwrap_or_else(|_| DEFAULT_QUERY_METHODS.to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let allowed = AllowedMethod::parse(entry);
+                if allowed.is_none() {
+                    warn!("Ignoring invalid QUERY_PROXY_METHODS entry: {}", entry);
+                }
+                allowed
+            })
+            .collect()
+    })
+}
+
+#[derive(Deserialize)]
+pub struct QueryRequest {
+    /// Arguments as JSON values, converted to the method's Candid argument types
+    #[serde(default)]
+    pub args: Vec<Value>,
+}
+
+/// Query a method of a registered canister with JSON arguments and return the reply as JSON
+pub async fn query_canister(
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
+    path: web::Path<(String, String)>,
+    request: web::Json<QueryRequest>,
+) -> impl Responder {
+    let (canister_id, method) = path.into_inner();
+    info!("API: Query {} on canister {}", method, canister_id);
+
+    let canister = match repo.canisters().find_by_canister_id(&canister_id).await {
+        Ok(Some(canister)) => canister,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<Value>::error(&format!("Canister with ID {} not found", canister_id))
+            );
+        }
+        Err(e) => {
+            error!("Failed to get canister: {}", e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<Value>::error(&format!("Failed to get canister: {}", e))
+            );
+        }
+    };
+
+    let canister_type = canister.canister_type.to_string();
+    if !allowed_methods().iter().any(|allowed| allowed.allows(&canister_type, &method)) {
+        return HttpResponse::Forbidden().json(
+            ApiResponse::<Value>::error(&format!("Method {} may not be queried on {} canisters", method, canister_type))
+        );
+    }
+
+    // Serve a recent reply from the cache
+    let key = format!("{}:{}:{}", canister_id, method, Value::Array(request.args.clone()));
+    let cached = QUERY_CACHE.lock().unwrap().get(&key).cloned()
+        .filter(|entry| Utc::now() - entry.fetched_at < Duration::seconds(QUERY_CACHE_SECS));
+    if let Some(entry) = cached {
+        return HttpResponse::Ok().json(ApiResponse::success(entry.reply, "Query completed successfully"));
+    }
+
+    let candid_service = match load_interface(repo.get_ref(), ic.get_ref(), &canister_id).await {
+        Ok(candid_service) => candid_service,
+        Err(e) => {
+            error!("Failed to get interface of canister {}: {:#}", canister_id, e);
+            return HttpResponse::BadGateway().json(
+                ApiResponse::<Value>::error(&format!("Failed to get the canister's interface: {:#}", e))
+            );
+        }
+    };
+
+    let query = match CandidMethod::from_service(&candid_service, &method) {
+        Ok(query) if query.is_query() => query,
+        Ok(_) => {
+            return HttpResponse::BadRequest().json(
+                ApiResponse::<Value>::error(&format!("{} is not a query method", method))
+            );
+        }
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<Value>::error(&format!("{:#}", e))),
+    };
+    let arg = match query.encode_args(&request.args) {
+        Ok(arg) => arg,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<Value>::error(&format!("{:#}", e))),
+    };
+
+    let reply = match ic.query_raw(&canister_id, &method, arg).await
+        .and_then(|bytes| query.decode_reply(&bytes))
+    {
+        Ok(reply) => reply,
+        Err(e) => {
+            error!("Failed to query {} on canister {}: {:#}", method, canister_id, e);
+            return HttpResponse::BadGateway().json(
+                ApiResponse::<Value>::error(&format!("Failed to query {}: {:#}", method, e))
+            );
+        }
+    };
+
+    let mut cache = QUERY_CACHE.lock().unwrap();
+    cache.retain(|_, entry| Utc::now() - entry.fetched_at < Duration::seconds(QUERY_CACHE_SECS));
+    cache.insert(key, QueryEntry { reply: reply.clone(), fetched_at: Utc::now() });
+    drop(cache);
+
+    HttpResponse::Ok().json(ApiResponse::success(reply, "Query completed successfully"))
+}
+
+/// The stored service definition of a canister, read from its metadata if none is stored yet
+pub async fn load_interface(repo: &dyn Repository, ic: &dyn IcClient, canister_id: &str) -> anyhow::Result<String> {
+    if let Some(interface) = repo.interfaces().find_by_canister_id(canister_id).await? {
+        return Ok(interface.candid_service);
+    }
+    let candid_service = ic.get_candid_service(canister_id).await?;
+    repo.interfaces().save(&CanisterInterface::new(canister_id.to_string(), candid_service.clone())).await?;
+    Ok(candid_service)
+}
@@ -0,0 +1,256 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::api::auth::{issue_token, verify_token, TokenClaims, ACCESS_TOKEN_TYPE, REFRESH_TOKEN_TYPE};
+use crate::api::handlers::ApiResponse;
+use crate::db::models::admin::Admin;
+use crate::db::models::admin_session::AdminSession;
+use crate::db::repository::Repository;
+
+// Default lifetime of an access token (15 minutes)
+const DEFAULT_ACCESS_TOKEN_TTL_SECS: i64 = 900;
+// Default lifetime of a session and its refresh token (12 hours)
+const DEFAULT_REFRESH_TOKEN_TTL_SECS: i64 = 43_200;
+// Default number of failed logins before an account is locked
+const DEFAULT_MAX_LOGIN_ATTEMPTS: i64 = 5;
+// Default time an account stays locked (15 minutes)
+const DEFAULT_LOCKOUT_SECS: i64 = 900;
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct SessionTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub refresh_expires_in: i64,
+}
+
+/// Exchange a username and password for session tokens
+pub async fn login(
+    repo: web::Data<dyn Repository>,
+    request: web::Json<LoginRequest>,
+) -> HttpResponse {
+    info!("API: Admin login attempt for {}", request.username);
+
+    let admin = match repo.admins().find_by_username(&request.username).await {
+        Ok(Some(admin)) if admin.is_active => admin,
+        Ok(_) => {
+            warn!("Login failed for unknown or inactive admin {}", request.username);
+            Admin::verify_dummy_password(&request.password);
+            return invalid_credentials();
+        }
+        Err(e) => {
+            error!("Failed to get admin: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error("Database error"));
+        }
+    };
+
+    // The password is checked even for locked accounts, and both fail alike, so
+    // neither timing nor status reveals whether a username exists
+    match admin.verify_password(&request.password) {
+        Ok(_) if admin.is_locked() => {
+            warn!("Login rejected for locked admin {}", admin.username);
+            return invalid_credentials();
+        }
+        Ok(true) => {}
+        Ok(false) => {
+            warn!("Login failed for admin {}: wrong password", admin.username);
+            if let Err(e) = repo.admins().record_login_failure(&admin.id, max_login_attempts(), lockout_secs()).await {
+                error!("Failed to record login failure: {}", e);
+            }
+            return invalid_credentials();
+        }
+        Err(e) => {
+            error!("Failed to verify password: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error("Failed to verify password"));
+        }
+    }
+
+    if let Err(e) = repo.admins().reset_login_failures(&admin.id).await {
+        error!("Failed to reset login failures: {}", e);
+    }
+    if let Err(e) = repo.admins().touch_last_used(&admin.id).await {
+        error!("Failed to record admin last use: {}", e);
+    }
+    if let Err(e) = repo.sessions().delete_expired().await {
+        error!("Failed to clean up expired sessions: {}", e);
+    }
+
+    let session = AdminSession::new(admin.id.clone(), env_secs("ADMIN_REFRESH_TOKEN_TTL_SECS", DEFAULT_REFRESH_TOKEN_TTL_SECS));
+    match repo.sessions().save(&session).await {
+        Ok(_) => {
+            info!("Admin {} logged in, session {}", admin.username, session.id);
+            HttpResponse::Ok()
+                .json(ApiResponse::success(session_tokens(&session), "Login successful"))
+        }
+        Err(e) => {
+            error!("Failed to create session: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(&format!("Failed to create session: {}", e)))
+        }
+    }
+}
+
+/// Exchange a refresh token for new session tokens
+pub async fn refresh(
+    repo: web::Data<dyn Repository>,
+    request: web::Json<RefreshRequest>,
+) -> HttpResponse {
+    info!("API: Admin token refresh");
+
+    let claims = match verify_token(&request.refresh_token, REFRESH_TOKEN_TYPE) {
+        Some(claims) => claims,
+        None => {
+            return HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::error("Invalid or expired refresh token"));
+        }
+    };
+
+    let mut session = match repo.sessions().find_by_id(&claims.sid).await {
+        Ok(Some(session)) if session.is_valid() && session.admin_id == claims.sub => session,
+        Ok(_) => {
+            return HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::error("Session expired or revoked"));
+        }
+        Err(e) => {
+            error!("Failed to get session: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error("Database error"));
+        }
+    };
+
+    // A refresh token that was already exchanged means it leaked, so end the session
+    if claims.jti.as_deref() != Some(session.refresh_token_id.as_str()) {
+        warn!("Refresh token reuse detected for session {}, revoking it", session.id);
+        if let Err(e) = repo.sessions().revoke(&session.id).await {
+            error!("Failed to revoke session: {}", e);
+        }
+        return HttpResponse::Unauthorized()
+            .json(ApiResponse::<()>::error("Refresh token already used"));
+    }
+
+    match repo.admins().find_by_id(&session.admin_id).await {
+        Ok(Some(admin)) if admin.is_active => {}
+        Ok(_) => {
+            return HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::error("Admin account is inactive"));
+        }
+        Err(e) => {
+            error!("Failed to get admin: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error("Database error"));
+        }
+    }
+
+    session.rotate_refresh_token(env_secs("ADMIN_REFRESH_TOKEN_TTL_SECS", DEFAULT_REFRESH_TOKEN_TTL_SECS));
+    match repo.sessions().save(&session).await {
+        Ok(_) => HttpResponse::Ok()
+            .json(ApiResponse::success(session_tokens(&session), "Session refreshed")),
+        Err(e) => {
+            error!("Failed to refresh session: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(&format!("Failed to refresh session: {}", e)))
+        }
+    }
+}
+
+/// End the session of the presented access token
+pub async fn logout(
+    req: HttpRequest,
+    repo: web::Data<dyn Repository>,
+) -> HttpResponse {
+    let claims = match req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| verify_token(token.trim(), ACCESS_TOKEN_TYPE))
+    {
+        Some(claims) => claims,
+        None => {
+            return HttpResponse::Unauthorized()
+                .json(ApiResponse::<()>::error("Valid bearer token required"));
+        }
+    };
+
+    match repo.sessions().revoke(&claims.sid).await {
+        Ok(_) => {
+            info!("Admin session {} logged out", claims.sid);
+            HttpResponse::Ok()
+                .json(ApiResponse::<()>::success((), "Logged out"))
+        }
+        Err(e) => {
+            error!("Failed to revoke session: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(&format!("Failed to revoke session: {}", e)))
+        }
+    }
+}
+
+/// Helper function to issue an access and refresh token pair for a session
+fn session_tokens(session: &AdminSession) -> SessionTokens {
+    let now = Utc::now().timestamp();
+    let access_ttl = env_secs("ADMIN_ACCESS_TOKEN_TTL_SECS", DEFAULT_ACCESS_TOKEN_TTL_SECS);
+
+    let access_token = issue_token(&TokenClaims {
+        sid: session.id.clone(),
+        sub: session.admin_id.clone(),
+        typ: ACCESS_TOKEN_TYPE.to_string(),
+        jti: None,
+        exp: (now + access_ttl).min(session.expires_at),
+    });
+    let refresh_token = issue_token(&TokenClaims {
+        sid: session.id.clone(),
+        sub: session.admin_id.clone(),
+        typ: REFRESH_TOKEN_TYPE.to_string(),
+        jti: Some(session.refresh_token_id.clone()),
+        exp: session.expires_at,
+    });
+
+    SessionTokens {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: access_ttl.min(session.expires_at - now),
+        refresh_expires_in: session.expires_at - now,
+    }
+}
+
+/// Helper function to build the response for a failed login, without revealing why it failed
+fn invalid_credentials() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .json(ApiResponse::<()>::error("Invalid username or password"))
+}
+
+fn max_login_attempts() -> i64 {
+    env_secs("ADMIN_MAX_LOGIN_ATTEMPTS", DEFAULT_MAX_LOGIN_ATTEMPTS)
+}
+
+fn lockout_secs() -> i64 {
+    env_secs("ADMIN_LOCKOUT_SECS", DEFAULT_LOCKOUT_SECS)
+}
+
+// Read a positive integer setting from the environment
+fn env_secs(name: &str, default: i64) -> i64 {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default)
+}
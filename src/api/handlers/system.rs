@@ -4,10 +4,11 @@ use serde::Serialize;
 use serde_json::json;
 use std::collections::HashMap;
 
-use crate::db::DbPool;
 use crate::api::handlers::ApiResponse;
-use crate::ic::utils::interface_util::generate_interface_files;
 use crate::websocket;
+use crate::metrics;
+use crate::api::handlers::health;
+use crate::db::repository::Repository;
 
 #[derive(Serialize)]
 pub struct SystemStatus {
@@ -16,68 +17,34 @@ pub struct SystemStatus {
     pub database_connected: bool,
     pub canisters_count: usize,
     pub tokens_count: usize,
+    pub ledgers_count: usize,
     pub miners_count: usize,
 }
 
 /// Get system status
-pub async fn get_system_status(db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn get_system_status(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get system status");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
+    // Get counts from database
+    let stats = match repo.registry().stats().await {
+        Ok(stats) => stats,
         Err(e) => {
-            error!("Failed to get database connection: {}", e);
+            error!("Failed to get registry statistics: {}", e);
             return HttpResponse::InternalServerError().json(
                 ApiResponse::<SystemStatus>::error(&format!("Database error: {}", e))
             );
         }
     };
     
-    // Get counts from database
-    let canisters_count = match conn.query_row(
-        "SELECT COUNT(*) FROM canisters",
-        [],
-        |row| row.get::<_, usize>(0),
-    ) {
-        Ok(count) => count,
-        Err(e) => {
-            error!("Failed to get canisters count: {}", e);
-            0
-        }
-    };
-    
-    let tokens_count = match conn.query_row(
-        "SELECT COUNT(*) FROM token_info",
-        [],
-        |row| row.get::<_, usize>(0),
-    ) {
-        Ok(count) => count,
-        Err(e) => {
-            error!("Failed to get tokens count: {}", e);
-            0
-        }
-    };
-    
-    let miners_count = match conn.query_row(
-        "SELECT COUNT(*) FROM miner_info",
-        [],
-        |row| row.get::<_, usize>(0),
-    ) {
-        Ok(count) => count,
-        Err(e) => {
-            error!("Failed to get miners count: {}", e);
-            0
-        }
-    };
-    
     // Create status response
     let status = SystemStatus {
         version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: 0, // TODO: Track uptime
+        uptime: health::uptime_secs(),
         database_connected: true,
-        canisters_count,
-        tokens_count,
-        miners_count,
+        canisters_count: stats.canisters() as usize,
+        tokens_count: stats.tokens as usize,
+        ledgers_count: stats.ledgers as usize,
+        miners_count: stats.miners as usize,
     };
     
     HttpResponse::Ok().json(
@@ -86,7 +53,7 @@ pub async fn get_system_status(db_pool: web::Data<DbPool>) -> impl Responder {
 }
 
 /// Trigger a manual refresh notification via WebSockets
-pub async fn trigger_refresh(_db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn trigger_refresh() -> impl Responder {
     info!("API: Trigger refresh notification");
     
     // Instead of running background tasks, we'll just broadcast a notification
@@ -104,41 +71,14 @@ pub async fn trigger_refresh(_db_pool: web::Data<DbPool>) -> impl Responder {
     )
 }
 
-/// Generate interface files for clients
-pub async fn generate_interfaces(
-    query: web::Query<HashMap<String, String>>,
-) -> impl Responder {
-    info!("API: Generate interface files");
-    
-    // Get output directory from query params, default to "interfaces"
-    let output_dir = query.get("output_dir").map_or("interfaces", |s| s.as_str());
-    
-    match generate_interface_files(output_dir) {
-        Ok(_) => {
-            HttpResponse::Ok().json(
-                ApiResponse::success(
-                    output_dir, 
-                    &format!("Interface files generated successfully to {}", output_dir)
-                )
-            )
-        },
-        Err(e) => {
-            error!("Failed to generate interface files: {}", e);
-            HttpResponse::InternalServerError().json(
-                ApiResponse::<String>::error(&format!("Failed to generate interface files: {}", e))
-            )
-        }
-    }
-}
-
-/// Get aggregate statistics (token and miner counts, totals)
-pub async fn get_statistics(db_pool: web::Data<DbPool>) -> impl Responder {
+/// Get aggregate statistics (token, ledger and miner counts, totals)
+pub async fn get_statistics(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get aggregate statistics");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
+    let registry_stats = match repo.registry().stats().await {
+        Ok(stats) => stats,
         Err(e) => {
-            error!("Failed to get database connection: {}", e);
+            error!("Failed to get registry statistics: {}", e);
             return HttpResponse::InternalServerError().json(
                 ApiResponse::<HashMap<String, i64>>::error(&format!("Database error: {}", e))
             );
@@ -147,69 +87,31 @@ pub async fn get_statistics(db_pool: web::Data<DbPool>) -> impl Responder {
     
     // Create stats object
     let mut stats = HashMap::new();
+    stats.insert("token_count".to_string(), registry_stats.tokens);
+    stats.insert("ledger_count".to_string(), registry_stats.ledgers);
+    stats.insert("miner_count".to_string(), registry_stats.miners);
+    stats.insert("blocks_mined".to_string(), registry_stats.blocks_mined);
+    stats.insert("total_rewards".to_string(), registry_stats.total_rewards);
     
-    // Get total token count
-    match conn.query_row(
-        "SELECT COUNT(*) FROM token_info",
-        [],
-        |row| row.get::<_, i64>(0),
-    ) {
-        Ok(count) => {
-            stats.insert("token_count".to_string(), count);
-        },
-        Err(e) => {
-            error!("Failed to get token count: {}", e);
-            stats.insert("token_count".to_string(), 0);
-        }
-    }
-    
-    // Get total miner count
-    match conn.query_row(
-        "SELECT COUNT(*) FROM miner_info",
-        [],
-        |row| row.get::<_, i64>(0),
-    ) {
-        Ok(count) => {
-            stats.insert("miner_count".to_string(), count);
-        },
-        Err(e) => {
-            error!("Failed to get miner count: {}", e);
-            stats.insert("miner_count".to_string(), 0);
-        }
-    }
-    
-    // Get total blocks mined
-    match conn.query_row(
-        "SELECT SUM(blocks_mined) FROM mining_stats",
-        [],
-        |row| row.get::<_, i64>(0),
-    ) {
-        Ok(count) => {
-            stats.insert("blocks_mined".to_string(), count);
-        },
-        Err(e) => {
-            error!("Failed to get blocks mined: {}", e);
-            stats.insert("blocks_mined".to_string(), 0);
-        }
-    }
+    // Return stats
+    HttpResponse::Ok().json(
+        ApiResponse::success(stats, "Aggregate statistics retrieved successfully")
+    )
+}
+
+/// Export metrics in the Prometheus text format
+pub async fn get_metrics(repo: web::Data<dyn Repository>) -> impl Responder {
+    // Sample the pool before checking out our own connection
+    metrics::update_pool_gauges(repo.pool_status());
     
-    // Get total rewards
-    match conn.query_row(
-        "SELECT SUM(total_rewards) FROM mining_stats",
-        [],
-        |row| row.get::<_, i64>(0),
-    ) {
-        Ok(count) => {
-            stats.insert("total_rewards".to_string(), count);
-        },
+    match repo.registry().stats().await {
+        Ok(stats) => metrics::update_registry_gauges(&stats),
         Err(e) => {
-            error!("Failed to get total rewards: {}", e);
-            stats.insert("total_rewards".to_string(), 0);
+            error!("Failed to update registry metrics: {}", e);
         }
     }
     
-    // Return stats
-    HttpResponse::Ok().json(
-        ApiResponse::success(stats, "Aggregate statistics retrieved successfully")
-    )
-} 
\ No newline at end of file
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics::render())
+}
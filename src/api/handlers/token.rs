@@ -1,25 +1,92 @@
 use actix_web::{web, HttpResponse, Responder};
+use candid::Principal;
+use chrono::{DateTime, Duration, Utc};
 use log::{info, error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
 
-use crate::db::DbPool;
+use crate::db::models::canister::CanisterType;
+use crate::db::models::ledger_transaction::AccountBalance;
 use crate::db::models::token_info::TokenInfo;
 use crate::api::handlers::ApiResponse;
+use crate::api::handlers::ledger::parse_subaccount;
+use crate::db::repository::Repository;
+use crate::ic::client::IcClient;
+
+// How long a balance read from a ledger is served before asking the ledger again
+const BALANCE_CACHE_SECS: i64 = 10;
+// Default and maximum number of top holders returned
+const DEFAULT_TOP_HOLDERS: i64 = 10;
+const MAX_TOP_HOLDERS: i64 = 100;
+
+#[derive(Clone)]
+struct BalanceEntry {
+    balance: String,
+    fetched_at: DateTime<Utc>,
+}
+
+// Recent balances, keyed by ledger, owner and subaccount
+lazy_static::lazy_static! {
+    static ref BALANCE_CACHE: Mutex<HashMap<String, BalanceEntry>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Deserialize)]
+pub struct BalanceQuery {
+    /// Hex-encoded 32-byte subaccount, the default subaccount when omitted
+    pub subaccount: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenBalance {
+    pub ledger_id: String,
+    pub owner: String,
+    pub subaccount: Option<String>,
+    /// Decimal string in the token's smallest unit
+    pub balance: String,
+    /// When the balance was read from the ledger
+    pub fetched_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct HoldersQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct TokenHolders {
+    pub ledger_id: String,
+    /// Accounts with a non-zero balance
+    pub holder_count: i64,
+    pub total_supply: String,
+    pub top_holders: Vec<AccountBalance>,
+    /// Percentage of the total supply held by `top_holders`
+    pub top_holders_share: f64,
+    /// Blocks below this index are counted in the balances
+    pub indexed_block: u64,
+    pub log_length: u64,
+}
+
+// Ledger holding a token's balances: the canister itself when it is a ledger,
+// otherwise the `ledger_id` the token reports
+async fn resolve_ledger(repo: &dyn Repository, canister_id: &str) -> anyhow::Result<Option<String>> {
+    match repo.canisters().find_by_canister_id(canister_id).await? {
+        Some(canister) if canister.canister_type == CanisterType::Ledger => Ok(Some(canister_id.to_string())),
+        Some(canister) if canister.canister_type == CanisterType::Token => {
+            let token = repo.tokens().find_by_canister_id(canister_id).await?;
+            Ok(token
+                .and_then(|token| serde_json::from_str::<serde_json::Value>(&token.raw_info).ok())
+                .and_then(|raw_info| raw_info.get("ledger_id")?.as_str().map(str::to_string)))
+        }
+        _ => Ok(None),
+    }
+}
 
 /// Get all tokens
-pub async fn get_all_tokens(db_pool: web::Data<DbPool>) -> impl Responder {
+pub async fn get_all_tokens(repo: web::Data<dyn Repository>) -> impl Responder {
     info!("API: Get all tokens");
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<Vec<TokenInfo>>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match TokenInfo::find_all(&conn) {
+    match repo.tokens().find_all().await {
         Ok(tokens) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(tokens, "Tokens retrieved successfully")
@@ -36,23 +103,13 @@ pub async fn get_all_tokens(db_pool: web::Data<DbPool>) -> impl Responder {
 
 /// Get a specific token
 pub async fn get_token(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Get token: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(
-                ApiResponse::<TokenInfo>::error(&format!("Database error: {}", e))
-            );
-        }
-    };
-    
-    match TokenInfo::find_by_canister_id(&conn, &canister_id) {
+    match repo.tokens().find_by_canister_id(&canister_id).await {
         Ok(Some(token)) => {
             HttpResponse::Ok().json(
                 ApiResponse::success(token, "Token retrieved successfully")
@@ -74,25 +131,17 @@ pub async fn get_token(
 
 /// Delete a token
 pub async fn delete_token(
-    db_pool: web::Data<DbPool>,
+    repo: web::Data<dyn Repository>,
     path: web::Path<String>,
 ) -> impl Responder {
     let canister_id = path.into_inner();
     info!("API: Delete token: {}", canister_id);
     
-    let conn = match db_pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-            return HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Database error"));
-        }
-    };
-    
     // First check if the token exists
-    match TokenInfo::find_by_canister_id(&conn, &canister_id) {
+    match repo.tokens().find_by_canister_id(&canister_id).await {
         Ok(Some(_)) => {
             // Delete the token from the database
-            match TokenInfo::delete(&conn, &canister_id) {
+            match repo.tokens().delete(&canister_id).await {
                 Ok(true) => {
                     info!("Token deleted: {}", canister_id);
                     HttpResponse::Ok().json(ApiResponse::success(true, "Token deleted"))
@@ -116,4 +165,146 @@ pub async fn delete_token(
             HttpResponse::InternalServerError().json(ApiResponse::<bool>::error("Failed to delete token"))
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Get the balance of an account, read from the token's ledger with `icrc1_balance_of`
+pub async fn get_token_balance(
+    repo: web::Data<dyn Repository>,
+    ic: web::Data<dyn IcClient>,
+    path: web::Path<(String, String)>,
+    query: web::Query<BalanceQuery>,
+) -> impl Responder {
+    let (canister_id, owner) = path.into_inner();
+    info!("API: Get balance of {} for token {}", owner, canister_id);
+
+    if Principal::from_text(&owner).is_err() {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<TokenBalance>::error(&format!("Invalid account owner: {}", owner))
+        );
+    }
+    let subaccount = match parse_subaccount(query.subaccount.as_deref()) {
+        Ok(subaccount) => subaccount,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<TokenBalance>::error(&e)),
+    };
+
+    let ledger_id = match resolve_ledger(repo.get_ref(), &canister_id).await {
+        Ok(Some(ledger_id)) => ledger_id,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<TokenBalance>::error(&format!("No ledger found for token {}", canister_id))
+            );
+        }
+        Err(e) => {
+            error!("Failed to find ledger of token {}: {}", canister_id, e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<TokenBalance>::error(&format!("Failed to find ledger: {}", e))
+            );
+        }
+    };
+
+    // Serve a recent balance from the cache
+    let key = format!("{}:{}:{}", ledger_id, owner, subaccount.as_deref().unwrap_or(""));
+    let cached = BALANCE_CACHE.lock().unwrap().get(&key).cloned()
+        .filter(|entry| Utc::now() - entry.fetched_at < Duration::seconds(BALANCE_CACHE_SECS));
+
+    let entry = match cached {
+        Some(entry) => entry,
+        None => match ic.get_balance(&ledger_id, &owner, subaccount.as_deref()).await {
+            Ok(balance) => {
+                let entry = BalanceEntry { balance, fetched_at: Utc::now() };
+                let mut cache = BALANCE_CACHE.lock().unwrap();
+                cache.retain(|_, entry| Utc::now() - entry.fetched_at < Duration::seconds(BALANCE_CACHE_SECS));
+                cache.insert(key, entry.clone());
+                entry
+            }
+            Err(e) => {
+                error!("Failed to get balance of {} on ledger {}: {}", owner, ledger_id, e);
+                return HttpResponse::BadGateway().json(
+                    ApiResponse::<TokenBalance>::error(&format!("Failed to get balance: {}", e))
+                );
+            }
+        },
+    };
+
+    HttpResponse::Ok().json(ApiResponse::success(
+        TokenBalance {
+            ledger_id,
+            owner,
+            subaccount,
+            balance: entry.balance,
+            fetched_at: entry.fetched_at.timestamp(),
+        },
+        "Balance retrieved successfully",
+    ))
+}
+
+/// Get the holder count, top holders and their share of the supply, from the indexed ledger blocks
+pub async fn get_token_holders(
+    repo: web::Data<dyn Repository>,
+    path: web::Path<String>,
+    query: web::Query<HoldersQuery>,
+) -> impl Responder {
+    let canister_id = path.into_inner();
+    info!("API: Get holders of token {}", canister_id);
+    let limit = query.limit.unwrap_or(DEFAULT_TOP_HOLDERS).clamp(1, MAX_TOP_HOLDERS);
+
+    let ledger_id = match resolve_ledger(repo.get_ref(), &canister_id).await {
+        Ok(Some(ledger_id)) => ledger_id,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                ApiResponse::<TokenHolders>::error(&format!("No ledger found for token {}", canister_id))
+            );
+        }
+        Err(e) => {
+            error!("Failed to find ledger of token {}: {}", canister_id, e);
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<TokenHolders>::error(&format!("Failed to find ledger: {}", e))
+            );
+        }
+    };
+
+    let result = async {
+        let ledger = repo.ledgers().find_by_canister_id(&ledger_id).await?;
+        let state = repo.ledger_transactions().index_state(&ledger_id).await?;
+        let (ledger, state) = match (ledger, state) {
+            (Some(ledger), Some(state)) => (ledger, state),
+            _ => return Ok(None),
+        };
+        let holder_count = repo.ledger_transactions().count_holders(&ledger_id).await?;
+        let top_holders = repo.ledger_transactions().top_holders(&ledger_id, limit).await?;
+        anyhow::Ok(Some((ledger, state, holder_count, top_holders)))
+    }.await;
+
+    match result {
+        Ok(Some((ledger, state, holder_count, top_holders))) => {
+            // Shares are approximate, amounts can exceed what f64 holds exactly
+            let total_supply = ledger.total_supply.parse::<f64>().unwrap_or(0.0);
+            let top_total: f64 = top_holders.iter().filter_map(|holder| holder.balance.parse::<f64>().ok()).sum();
+            let top_holders_share = if total_supply > 0.0 { top_total * 100.0 / total_supply } else { 0.0 };
+
+            HttpResponse::Ok().json(ApiResponse::success(
+                TokenHolders {
+                    ledger_id,
+                    holder_count,
+                    total_supply: ledger.total_supply,
+                    top_holders,
+                    top_holders_share,
+                    indexed_block: state.next_block,
+                    log_length: state.log_length,
+                },
+                "Holders retrieved successfully",
+            ))
+        }
+        Ok(None) => {
+            HttpResponse::NotFound().json(
+                ApiResponse::<TokenHolders>::error(&format!("Ledger {} has not been indexed yet", ledger_id))
+            )
+        }
+        Err(e) => {
+            error!("Failed to get holders of ledger {}: {}", ledger_id, e);
+            HttpResponse::InternalServerError().json(
+                ApiResponse::<TokenHolders>::error(&format!("Failed to get holders: {}", e))
+            )
+        }
+    }
+}
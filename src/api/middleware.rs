@@ -1,22 +1,22 @@
-// NOTE: This middleware is not currently used. We're using a simpler approach with
-// direct authentication in the handler functions. This file is kept for reference
-// in case we want to switch to middleware-based authentication in the future.
-
 use actix_web::{
+    body::EitherBody,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpResponse,
+    web, Error, HttpMessage, HttpResponse,
 };
 use futures::future::{ok, Ready};
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 use log::{info, error};
 
-use crate::db::DbPool;
-use crate::db::models::admin::Admin;
+use crate::api::auth::resolve_admin;
 use crate::api::handlers::ApiResponse;
+use crate::db::repository::Repository;
 
 // Admin authentication middleware
+// Resolves the admin from a bearer token or API key and stores it in the request extensions,
+// where handlers pick it up with `auth::current_admin` or `auth::require_permission`
 pub struct AdminAuth;
 
 impl AdminAuth {
@@ -28,34 +28,33 @@ impl AdminAuth {
 // Middleware factory for AdminAuth
 impl<S, B> Transform<S, ServiceRequest> for AdminAuth
 where
-    // We need Clone because the service is cloned inside the call method
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static + Clone,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type Transform = AdminAuthMiddleware<S>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AdminAuthMiddleware { service })
+        ok(AdminAuthMiddleware { service: Rc::new(service) })
     }
 }
 
 pub struct AdminAuthMiddleware<S> {
-    service: S,
+    // Shared so the inner service can be called from inside the returned future
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for AdminAuthMiddleware<S>
 where
-    // We need Clone because we need to clone the service inside the async block
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static + Clone,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
 
@@ -64,75 +63,38 @@ where
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // Check for API key in headers
-        let api_key = if let Some(key) = req.headers().get("X-API-KEY") {
-            if let Ok(key_str) = key.to_str() {
-                key_str.to_string()
-            } else {
-                // Create response with error message
-                let _resp = HttpResponse::Unauthorized()
-                    .json(ApiResponse::<()>::error("Invalid API key format"));
-                return Box::pin(async {
-                    Err(actix_web::error::ErrorUnauthorized("Invalid API key format"))
-                });
-            }
-        } else {
-            // Create response with error message
-            let _resp = HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("API key required"));
-            return Box::pin(async {
-                Err(actix_web::error::ErrorUnauthorized("API key required"))
-            });
-        };
-
-        // Get the database pool from app data
-        let db_pool = match req.app_data::<actix_web::web::Data<DbPool>>() {
-            Some(pool) => pool.clone(),
-            _ => {
-                error!("DB pool not found in app data");
-                let _resp = HttpResponse::InternalServerError()
-                    .json(ApiResponse::<()>::error("Server configuration error"));
-                return Box::pin(async {
-                    Err(actix_web::error::ErrorInternalServerError("Server configuration error"))
-                });
-            }
-        };
+        let service = Rc::clone(&self.service);
 
-        // Verify the API key
-        let service = self.service.clone();
         Box::pin(async move {
-            let conn = match db_pool.get() {
-                Ok(conn) => conn,
-                Err(e) => {
-                    error!("Failed to get database connection: {}", e);
-                    let _resp = HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                    return Err(actix_web::error::ErrorInternalServerError("Database error"));
+            // Get the repository from app data
+            let repo = match req.app_data::<web::Data<dyn Repository>>() {
+                Some(repo) => repo.clone(),
+                _ => {
+                    error!("Repository not found in app data");
+                    let resp = HttpResponse::InternalServerError()
+                        .json(ApiResponse::<()>::error("Server configuration error"));
+                    return Ok(req.into_response(resp).map_into_right_body());
                 }
             };
 
-            // Check if the API key is valid
-            match Admin::find_by_api_key(&conn, &api_key) {
-                Ok(Some(admin)) => {
-                    info!("Admin authenticated: {}", admin.username);
-                    // Store admin in request extensions
-                    req.extensions_mut().insert(admin);
-                    // Continue with the request
-                    let fut = service.call(req);
-                    fut.await
-                }
-                Ok(_) => {
-                    let _resp = HttpResponse::Unauthorized()
-                        .json(ApiResponse::<()>::error("Invalid API key"));
-                    Err(actix_web::error::ErrorUnauthorized("Invalid API key"))
+            let admin = match resolve_admin(req.headers(), repo.get_ref()).await {
+                Ok(admin) => {
+                    if let Err(e) = repo.admins().touch_last_used(&admin.id).await {
+                        error!("Failed to record admin last use: {}", e);
+                    }
+                    admin
                 }
                 Err(e) => {
-                    error!("DB error while verifying API key: {}", e);
-                    let _resp = HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Database error"));
-                    Err(actix_web::error::ErrorInternalServerError(format!("Database error: {}", e)))
+                    return Ok(req.into_response(e.into_response()).map_into_right_body());
                 }
-            }
+            };
+
+            info!("Admin authenticated: {} ({})", admin.username, admin.role);
+            // Store admin in request extensions
+            req.extensions_mut().insert(admin);
+
+            // Continue with the request
+            service.call(req).await.map(ServiceResponse::map_into_left_body)
         })
     }
-} 
\ No newline at end of file
+}
@@ -2,5 +2,7 @@ pub mod routes;
 pub mod handlers;
 pub mod middleware;
 pub mod auth;
+pub mod client_ip;
+pub mod rate_limit;
 
 pub use routes::configure_routes; 
\ No newline at end of file
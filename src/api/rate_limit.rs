@@ -0,0 +1,333 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpResponse,
+};
+use futures::future::{ok, Ready};
+use log::{warn, error};
+use ring::digest;
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::api::client_ip::client_ip;
+use crate::api::handlers::ApiResponse;
+use crate::metrics;
+
+// Buckets that have not been used for this long are dropped by the cleanup task
+const IDLE_BUCKET_SECS: u64 = 600;
+
+/// What a token bucket is keyed by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitScope {
+    Ip,
+    ApiKey,
+    Canister,
+}
+
+impl RateLimitScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitScope::Ip => "ip",
+            RateLimitScope::ApiKey => "api_key",
+            RateLimitScope::Canister => "canister",
+        }
+    }
+
+    fn env_var(&self) -> &'static str {
+        match self {
+            RateLimitScope::Ip => "RATE_LIMIT_PER_IP",
+            RateLimitScope::ApiKey => "RATE_LIMIT_PER_API_KEY",
+            RateLimitScope::Canister => "RATE_LIMIT_PER_CANISTER",
+        }
+    }
+
+    fn default_rule(&self) -> RateLimitRule {
+        let (per_minute, burst) = match self {
+            RateLimitScope::Ip => (60.0, 20.0),
+            RateLimitScope::ApiKey => (120.0, 40.0),
+            RateLimitScope::Canister => (30.0, 10.0),
+        };
+        RateLimitRule { per_minute, burst }
+    }
+}
+
+/// Endpoint with its own per-canister buckets, so one endpoint cannot use up another's budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanisterRoute {
+    Registration,
+    Claude,
+    MinerNotification,
+}
+
+impl CanisterRoute {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CanisterRoute::Registration => "registration",
+            CanisterRoute::Claude => "claude",
+            CanisterRoute::MinerNotification => "miner_notification",
+        }
+    }
+
+    // Overrides RATE_LIMIT_PER_CANISTER for this endpoint
+    fn env_var(&self) -> &'static str {
+        match self {
+            CanisterRoute::Registration => "RATE_LIMIT_PER_CANISTER_REGISTRATION",
+            CanisterRoute::Claude => "RATE_LIMIT_PER_CANISTER_CLAUDE",
+            CanisterRoute::MinerNotification => "RATE_LIMIT_PER_CANISTER_MINER_NOTIFICATION",
+        }
+    }
+}
+
+/// Refill rate and capacity of a token bucket
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    pub per_minute: f64,
+    pub burst: f64,
+}
+
+impl RateLimitRule {
+    // Parse `per_minute` or `per_minute:burst`; `0` disables the limit
+    fn parse(value: &str) -> Result<Option<Self>, String> {
+        let (per_minute, burst) = match value.split_once(':') {
+            Some((per_minute, burst)) => (per_minute.trim(), Some(burst.trim())),
+            None => (value.trim(), None),
+        };
+        let per_minute: f64 = per_minute.parse().map_err(|_| format!("invalid rate: {}", value))?;
+        if per_minute <= 0.0 {
+            return Ok(None);
+        }
+        let burst = match burst {
+            Some(burst) => burst.parse().map_err(|_| format!("invalid burst: {}", value))?,
+            None => per_minute,
+        };
+        Ok(Some(Self { per_minute, burst: f64::max(burst, 1.0) }))
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+// Rules per scope and per-canister endpoint, read once from the environment
+static RULES: OnceLock<HashMap<RateLimitScope, Option<RateLimitRule>>> = OnceLock::new();
+static ROUTE_RULES: OnceLock<HashMap<CanisterRoute, Option<RateLimitRule>>> = OnceLock::new();
+
+// Buckets are keyed by scope, the endpoint for per-canister limits, and the IP, key or canister ID
+type BucketKey = (RateLimitScope, Option<CanisterRoute>, String);
+
+lazy_static::lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<BucketKey, TokenBucket>> = Mutex::new(HashMap::new());
+}
+
+fn rule(scope: RateLimitScope) -> Option<RateLimitRule> {
+    let rules = RULES.get_or_init(|| {
+        [RateLimitScope::Ip, RateLimitScope::ApiKey, RateLimitScope::Canister]
+            .into_iter()
+            .map(|scope| {
+                let rule = match env::var(scope.env_var()) {
+                    Ok(value) => RateLimitRule::parse(&value).unwrap_or_else(|e| {
+                        error!("Ignoring {}: {}", scope.env_var(), e);
+                        Some(scope.default_rule())
+                    }),
+                    Err(_) => Some(scope.default_rule()),
+                };
+                (scope, rule)
+            })
+            .collect()
+    });
+    rules.get(&scope).copied().flatten()
+}
+
+fn route_rule(route: CanisterRoute) -> Option<RateLimitRule> {
+    let rules = ROUTE_RULES.get_or_init(|| {
+        [CanisterRoute::Registration, CanisterRoute::Claude, CanisterRoute::MinerNotification]
+            .into_iter()
+            .map(|route| {
+                let rule = match env::var(route.env_var()) {
+                    Ok(value) => RateLimitRule::parse(&value).unwrap_or_else(|e| {
+                        error!("Ignoring {}: {}", route.env_var(), e);
+                        rule(RateLimitScope::Canister)
+                    }),
+                    Err(_) => rule(RateLimitScope::Canister),
+                };
+                (route, rule)
+            })
+            .collect()
+    });
+    rules.get(&route).copied().flatten()
+}
+
+/// Take a token from the bucket of `key` in `scope`
+/// Returns how long to wait before retrying if the bucket is empty
+pub fn check(scope: RateLimitScope, key: &str) -> Result<(), Duration> {
+    take((scope, None, key.to_string()), rule(scope))
+}
+
+/// Take a token from the bucket of a canister for one endpoint
+/// Returns how long to wait before retrying if the bucket is empty
+pub fn check_canister(route: CanisterRoute, canister_id: &str) -> Result<(), Duration> {
+    take((RateLimitScope::Canister, Some(route), canister_id.to_string()), route_rule(route))
+}
+
+/// Give back a token taken by `check_canister`, for requests that ended up doing nothing
+pub fn refund_canister(route: CanisterRoute, canister_id: &str) {
+    let rule = match route_rule(route) {
+        Some(rule) => rule,
+        None => return,
+    };
+    let key = (RateLimitScope::Canister, Some(route), canister_id.to_string());
+    if let Some(bucket) = BUCKETS.lock().unwrap().get_mut(&key) {
+        bucket.tokens = f64::min(rule.burst, bucket.tokens + 1.0);
+    }
+}
+
+fn take(key: BucketKey, rule: Option<RateLimitRule>) -> Result<(), Duration> {
+    let rule = match rule {
+        Some(rule) => rule,
+        None => return Ok(()),
+    };
+
+    let scope = key.0;
+    let refill_per_sec = rule.per_minute / 60.0;
+    let now = Instant::now();
+    let result = {
+        let mut buckets = BUCKETS.lock().unwrap();
+        let bucket = buckets
+            .entry(key.clone())
+            .or_insert(TokenBucket { tokens: rule.burst, updated_at: now });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = f64::min(rule.burst, bucket.tokens + elapsed * refill_per_sec);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+        }
+    };
+
+    metrics::record_rate_limit(scope.as_str(), result.is_ok());
+    if result.is_err() {
+        match key.1 {
+            Some(route) => warn!("Rate limit exceeded for {} {} on {}", scope.as_str(), key.2, route.as_str()),
+            None => warn!("Rate limit exceeded for {} {}", scope.as_str(), key.2),
+        }
+    }
+    result
+}
+
+/// Build a 429 response telling the client when to retry
+pub fn too_many_requests(retry_after: Duration) -> HttpResponse {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", secs.to_string()))
+        .json(ApiResponse::<()>::error(&format!("Rate limit exceeded, retry in {} seconds", secs)))
+}
+
+// API keys are only kept in memory as a hash
+fn api_key_bucket(api_key: &str) -> String {
+    digest::digest(&digest::SHA256, api_key.as_bytes())
+        .as_ref()
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Drop buckets that have been idle long enough to be full again
+pub fn clean_idle_buckets() {
+    let now = Instant::now();
+    let mut buckets = BUCKETS.lock().unwrap();
+    buckets.retain(|_, bucket| now.duration_since(bucket.updated_at).as_secs() < IDLE_BUCKET_SECS);
+}
+
+// Start a background task to clean up idle buckets
+pub fn start_cleanup_task() {
+    std::thread::spawn(|| {
+        loop {
+            // Sleep for 5 minutes
+            std::thread::sleep(std::time::Duration::from_secs(300));
+            clean_idle_buckets();
+        }
+    });
+}
+
+// Rate limiting middleware
+// Limits requests per client IP and, when an `X-API-KEY` header is present, per API key.
+// Per-canister limits depend on the request body and are checked by the handlers.
+#[derive(Default)]
+pub struct RateLimit;
+
+impl RateLimit {
+    pub fn new() -> Self {
+        RateLimit
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware { service: Rc::new(service) })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let ip = client_ip(req.request()).map(|ip| ip.to_string());
+        let api_key = req
+            .headers()
+            .get("X-API-KEY")
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(api_key_bucket);
+
+        let limited = ip
+            .map(|ip| check(RateLimitScope::Ip, &ip))
+            .unwrap_or(Ok(()))
+            .and_then(|_| api_key.map(|key| check(RateLimitScope::ApiKey, &key)).unwrap_or(Ok(())));
+
+        if let Err(retry_after) = limited {
+            let resp = too_many_requests(retry_after);
+            return Box::pin(async move { Ok(req.into_response(resp).map_into_right_body()) });
+        }
+
+        let service = Rc::clone(&self.service);
+        Box::pin(async move { service.call(req).await.map(ServiceResponse::map_into_left_body) })
+    }
+}
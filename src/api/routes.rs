@@ -1,5 +1,7 @@
-use actix_web::web;
-use crate::api::handlers::{canister, token, miner, system, admin, claude};
+use actix_web::{guard, web};
+use crate::api::middleware::AdminAuth;
+use crate::api::rate_limit::RateLimit;
+use crate::api::handlers::{canister, token, ledger, miner, miner_control, principal, interface, query, system, admin, claude, health, session, audit, export, backup, identity};
 
 /// Configure the API routes
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
@@ -7,10 +9,28 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/canisters")
             .route("", web::get().to(canister::get_all_canisters))
-            .route("", web::post().to(canister::register_canister))
+            // Registration triggers IC calls, so it is rate limited
+            .service(
+                web::resource("")
+                    .guard(guard::Post())
+                    .wrap(RateLimit::new())
+                    .to(canister::register_canister)
+            )
+            .service(
+                web::resource("/batch")
+                    .wrap(RateLimit::new())
+                    .route(web::post().to(canister::register_canisters_batch))
+            )
             .route("/type/{canister_type}", web::get().to(canister::get_canisters_by_type))
             .route("/{canister_id}", web::get().to(canister::get_canister))
             .route("/{canister_id}", web::put().to(canister::update_canister))
+            .route("/{canister_id}/interface", web::get().to(canister::get_canister_interface))
+            // Queries are proxied to the IC, so they are rate limited
+            .service(
+                web::resource("/{canister_id}/query/{method}")
+                    .wrap(RateLimit::new())
+                    .route(web::post().to(query::query_canister))
+            )
     );
     
     // Token routes
@@ -18,6 +38,17 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         web::scope("/tokens")
             .route("", web::get().to(token::get_all_tokens))
             .route("/{canister_id}", web::get().to(token::get_token))
+            .route("/{canister_id}/balances/{account}", web::get().to(token::get_token_balance))
+            .route("/{canister_id}/holders", web::get().to(token::get_token_holders))
+    );
+    
+    // Ledger routes
+    cfg.service(
+        web::scope("/ledgers")
+            .route("", web::get().to(ledger::get_all_ledgers))
+            .route("/{canister_id}", web::get().to(ledger::get_ledger))
+            .route("/{canister_id}/index", web::get().to(ledger::get_ledger_index_state))
+            .route("/{canister_id}/accounts/{owner}/transactions", web::get().to(ledger::get_account_transactions))
     );
     
     // Miner routes
@@ -26,32 +57,85 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route("", web::get().to(miner::get_all_miners))
             .route("/{canister_id}", web::get().to(miner::get_miner))
             .route("/{canister_id}/stats", web::get().to(miner::get_miner_stats))
+            .route("/{canister_id}/projection", web::get().to(miner::get_miner_projection))
             .route("/by-token/{token_canister_id}", web::get().to(miner::get_miners_by_token))
             .route("/stats", web::get().to(miner::get_all_mining_stats))
     );
     
+    // Principal routes
+    cfg.service(
+        web::scope("/principals")
+            .route("/{principal}", web::get().to(principal::get_principal_portfolio))
+    );
+    
+    // Candid interfaces and client bindings
+    cfg.service(
+        web::scope("/interfaces")
+            .route("", web::get().to(interface::list_interfaces))
+            .route("/{file_name}", web::get().to(interface::get_interface_file))
+    );
+    
     // Public module hash routes
     cfg.service(
         web::scope("/module-hashes")
             .route("", web::get().to(canister::get_all_verified_module_hashes))
     );
     
-    // Admin module hash routes - using admin handlers with built-in authentication
-    cfg.route("/admin/module-hashes", web::post().to(admin::add_verified_module_hash));
-    cfg.route("/admin/module-hashes/{hash}", web::delete().to(admin::remove_verified_module_hash));
-    
-    // Admin canister management routes
-    cfg.route("/admin/canisters/{canister_id}", web::delete().to(admin::delete_canister));
-    cfg.route("/admin/tokens/{canister_id}", web::delete().to(admin::delete_token));
-    cfg.route("/admin/miners/{canister_id}", web::delete().to(admin::delete_miner));
+    // Admin session routes, registered before the authenticated /admin scope
+    cfg.route("/admin/login", web::post().to(session::login));
+    cfg.route("/admin/refresh", web::post().to(session::refresh));
+    cfg.route("/admin/logout", web::post().to(session::logout));
     
-    // Admin module hash management routes
-    cfg.route("/admin/canisters/module-hashes", web::get().to(admin::get_all_module_hashes));
-    cfg.route("/admin/canisters/{canister_id}/module-hash", web::put().to(admin::set_module_hash));
+    // Admin routes - AdminAuth authenticates, each handler checks the admin's role
+    cfg.service(
+        web::scope("/admin")
+            .wrap(AdminAuth::new())
+            // Module hash routes
+            .route("/module-hashes", web::post().to(admin::add_verified_module_hash))
+            .route("/module-hashes/{hash}", web::delete().to(admin::remove_verified_module_hash))
+            // Canister management routes
+            .route("/canisters/{canister_id}", web::delete().to(admin::delete_canister))
+            .route("/canisters/trash", web::get().to(admin::get_deleted_canisters))
+            .route("/canisters/{canister_id}/restore", web::post().to(admin::restore_canister))
+            .route("/tokens/{canister_id}", web::delete().to(admin::delete_token))
+            .route("/ledgers/{canister_id}", web::delete().to(admin::delete_ledger))
+            .route("/miners/{canister_id}", web::delete().to(admin::delete_miner))
+            // Miner control routes
+            .route("/miners/{canister_id}/commands", web::post().to(miner_control::send_miner_command))
+            .route("/miners/{canister_id}/commands", web::get().to(miner_control::get_miner_commands))
+            // Module hash management routes
+            .route("/canisters/module-hashes", web::get().to(admin::get_all_module_hashes))
+            .route("/canisters/{canister_id}/module-hash", web::put().to(admin::set_module_hash))
+            // Account management routes
+            .route("/admins", web::get().to(admin::list_admins))
+            .route("/admins", web::post().to(admin::create_admin))
+            .route("/admins/{admin_id}", web::get().to(admin::get_admin))
+            .route("/admins/{admin_id}", web::put().to(admin::update_admin))
+            .route("/admins/{admin_id}", web::delete().to(admin::deactivate_admin))
+            .route("/admins/{admin_id}/rotate-key", web::post().to(admin::rotate_admin_api_key))
+            // Audit log routes
+            .route("/audit-log", web::get().to(audit::get_audit_log))
+            .route("/audit-log/export", web::get().to(audit::export_audit_log))
+            // Backup routes
+            .route("/backups", web::get().to(backup::list_backups))
+            .route("/backups", web::post().to(backup::create_backup))
+            .route("/backups/latest", web::get().to(backup::download_latest_backup))
+            // Server identity routes
+            .route("/identity", web::get().to(identity::get_identity))
+            .route("/identity/rotate", web::post().to(identity::rotate_identity))
+            // Registry export and import routes
+            .route("/export", web::get().to(export::export_registry))
+            .service(
+                web::resource("/import")
+                    .app_data(web::PayloadConfig::new(export::MAX_IMPORT_BYTES))
+                    .route(web::post().to(export::import_registry))
+            )
+    );
     
     // Claude API route
     cfg.service(
         web::scope("/claude")
+            .wrap(RateLimit::new())
             .route("", web::post().to(claude::handle_claude_request))
     );
     
@@ -60,7 +144,17 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         web::scope("/system")
             .route("/status", web::get().to(system::get_system_status))
             .route("/refresh", web::post().to(system::trigger_refresh))
-            .route("/interfaces", web::get().to(system::generate_interfaces))
             .route("/statistics", web::get().to(system::get_statistics))
     );
+    
+    // Health routes - liveness for process supervision, readiness for the load balancer
+    cfg.service(
+        web::scope("/health")
+            .route("", web::get().to(health::liveness))
+            .route("/live", web::get().to(health::liveness))
+            .route("/ready", web::get().to(health::readiness))
+    );
+    
+    // Prometheus metrics
+    cfg.route("/metrics", web::get().to(system::get_metrics));
 } 
@@ -6,10 +6,11 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use crate::websocket;
-use crate::db::DbPool;
-use crate::ic::agent::create_agent;
-use crate::ic::services::token::get_token_all_info;
-use crate::db::models::canister::{Canister, CanisterType};
+use crate::metrics;
+use crate::api::rate_limit::{self, CanisterRoute};
+use crate::db::repository::Repository;
+use crate::ic::client::IcClient;
+use crate::db::models::canister::CanisterType;
 
 // Structure for canister notifications
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -38,11 +39,20 @@ pub async fn handle_canister_notification(
     req: HttpRequest, 
     data: web::Json<NotificationData>
 ) -> HttpResponse {
-    // Extract db_pool from app_data
-    let db_pool = match req.app_data::<web::Data<DbPool>>() {
-        Some(pool) => pool.clone(),
+    // Extract the repository from app_data
+    let repo = match req.app_data::<web::Data<dyn Repository>>() {
+        Some(repo) => repo.clone(),
         None => {
-            log::error!("Failed to get database pool from app_data");
+            log::error!("Failed to get repository from app_data");
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            }));
+        }
+    };
+    let ic = match req.app_data::<web::Data<dyn IcClient>>() {
+        Some(ic) => ic.clone(),
+        None => {
+            log::error!("Failed to get IC client from app_data");
             return HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": "Internal server error"
             }));
@@ -76,12 +86,25 @@ pub async fn handle_canister_notification(
         cache.get(&cache_key).map(|entry| entry.response_data.clone())
     };
     
+    metrics::record_dedup_lookup("miner_notifications", cached_response_data.is_some());
+    
     // If we have a cached response, return it
     if let Some(response_data) = cached_response_data {
         log::info!("Returning cached response for duplicate notification: {}", cache_key);
         return HttpResponse::Ok().json(response_data);
     }
     
+    // Limit new notifications per miner, cached duplicates are free
+    if let Err(retry_after) = rate_limit::check_canister(CanisterRoute::MinerNotification, &canister_id) {
+        let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        return HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", secs.to_string()))
+            .json(serde_json::json!({
+                "error": "Rate limit exceeded",
+                "retry_after": secs
+            }));
+    }
+    
     // First time seeing this notification - process it
     log::info!("Processing new notification: {} from {}", event_type, canister_id);
     
@@ -118,11 +141,12 @@ pub async fn handle_canister_notification(
                 log::info!("Updating token info for: {}", token_id);
                 
                 // Update token info in the background
-                let db_pool_clone = db_pool.clone();
+                let repo_clone = repo.clone();
+                let ic_clone = ic.clone();
                 let token_id_clone = token_id.to_string();
                 
                 actix_web::rt::spawn(async move {
-                    match update_token_info(&db_pool_clone, &token_id_clone).await {
+                    match update_token_info(&repo_clone, &ic_clone, &token_id_clone).await {
                         Ok(_) => log::info!("Successfully updated token info for: {}", token_id_clone),
                         Err(e) => log::error!("Failed to update token info for {}: {}", token_id_clone, e),
                     }
@@ -145,11 +169,12 @@ pub async fn handle_canister_notification(
                 log::info!("Updating token info for: {}", token_id);
                 
                 // Update token info in the background
-                let db_pool_clone = db_pool.clone();
+                let repo_clone = repo.clone();
+                let ic_clone = ic.clone();
                 let token_id_clone = token_id.to_string();
                 
                 actix_web::rt::spawn(async move {
-                    match update_token_info(&db_pool_clone, &token_id_clone).await {
+                    match update_token_info(&repo_clone, &ic_clone, &token_id_clone).await {
                         Ok(_) => log::info!("Successfully updated token info for: {}", token_id_clone),
                         Err(e) => log::error!("Failed to update token info for {}: {}", token_id_clone, e),
                     }
@@ -198,27 +223,30 @@ pub fn start_cache_cleanup_task() {
     });
 }
 
-// Helper function to update token info
-async fn update_token_info(db_pool: &web::Data<DbPool>, token_id: &str) -> Result<(), anyhow::Error> {
-    // Get a database connection
-    let conn = db_pool.get()?;
-    
+// Helper function to update token info, tokens registered as ICRC-1 ledgers get their ledger info updated
+async fn update_token_info(
+    repo: &web::Data<dyn Repository>,
+    ic: &web::Data<dyn IcClient>,
+    token_id: &str,
+) -> Result<(), anyhow::Error> {
     // Check if the token exists in our registry
-    match Canister::find_by_canister_id(&conn, token_id)? {
+    match repo.canisters().find_by_canister_id(token_id).await? {
         Some(canister) if canister.canister_type == CanisterType::Token => {
-            // Create an IC agent
-            let agent = create_agent("https://ic0.app").await?;
-            
             // Get token info
-            let token_info = get_token_all_info(&agent, token_id).await?;
+            let token_info = ic.get_token_all_info(token_id).await?;
             
             // Save the token info
-            token_info.save(&conn)?;
+            repo.tokens().save(&token_info).await?;
             
             Ok(())
         },
+        Some(canister) if canister.canister_type == CanisterType::Ledger => {
+            let ledger_info = ic.get_ledger_info(token_id).await?;
+            repo.ledgers().save(&ledger_info).await?;
+            Ok(())
+        },
         Some(_) => {
-            log::warn!("Canister {} is not registered as a token", token_id);
+            log::warn!("Canister {} is not registered as a token or ledger", token_id);
             Ok(())
         },
         None => {
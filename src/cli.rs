@@ -0,0 +1,132 @@
+use log::info;
+use std::fs;
+use std::io::{self, Write};
+
+use crate::db::export::{ExportFormat, ImportMode, RegistryExport};
+use crate::db::repository::Repository;
+use crate::ic::agent;
+
+const USAGE: &str = "Usage:
+  https-outcall export [--format json|csv] [--table TABLE] [--output FILE]
+  https-outcall import FILE [--format json|csv] [--table TABLE] [--mode upsert|skip-existing] [--dry-run]
+  https-outcall encrypt-identity [FILE]
+
+Tables: canisters, token_info, ledger_info, miner_info, mining_stats, verified_module_hashes
+
+encrypt-identity encrypts FILE (default data/identity/identity.pem) and the plaintext keys
+in the retired directory next to it with the passphrase from IDENTITY_PASSPHRASE or
+IDENTITY_PASSPHRASE_FILE";
+
+// Options shared by the export and import commands
+#[derive(Default)]
+struct Options {
+    format: Option<String>,
+    table: Option<String>,
+    output: Option<String>,
+    mode: Option<String>,
+    dry_run: bool,
+    file: Option<String>,
+}
+
+fn invalid_input(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
+
+fn parse_options(args: &[String]) -> io::Result<Options> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| invalid_input(format!("{} needs a value\n{}", arg, USAGE)));
+        match arg.as_str() {
+            "--format" => options.format = Some(value()?),
+            "--table" => options.table = Some(value()?),
+            "--output" | "-o" => options.output = Some(value()?),
+            "--mode" => options.mode = Some(value()?),
+            "--dry-run" => options.dry_run = true,
+            other if !other.starts_with('-') && options.file.is_none() => options.file = Some(other.to_string()),
+            other => return Err(invalid_input(format!("Unknown argument: {}\n{}", other, USAGE))),
+        }
+    }
+    Ok(options)
+}
+
+/// Run a command-line subcommand instead of the server
+///
+/// Returns `None` when the arguments do not name a subcommand.
+pub async fn run(args: &[String], repo: &dyn Repository) -> Option<io::Result<()>> {
+    let (command, rest) = args.split_first()?;
+    let result = match command.as_str() {
+        "export" => match parse_options(rest) {
+            Ok(options) => export(&options, repo).await,
+            Err(e) => Err(e),
+        },
+        "import" => match parse_options(rest) {
+            Ok(options) => import(&options, repo).await,
+            Err(e) => Err(e),
+        },
+        "encrypt-identity" => match rest {
+            [] => encrypt_identity(None),
+            [file] if !file.starts_with('-') => encrypt_identity(Some(file)),
+            _ => Err(invalid_input(format!("encrypt-identity takes at most one file\n{}", USAGE))),
+        },
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+async fn export(options: &Options, repo: &dyn Repository) -> io::Result<()> {
+    let format = ExportFormat::parse(options.format.as_deref(), options.table.as_deref()).map_err(invalid_input)?;
+    let export = RegistryExport::load(repo, &format.tables()).await.map_err(io::Error::other)?;
+
+    let body = match format {
+        ExportFormat::Json(_) => serde_json::to_string_pretty(&export)?,
+        ExportFormat::Csv(table) => export.to_csv(table).map_err(io::Error::other)?,
+    };
+
+    match &options.output {
+        Some(path) => {
+            fs::write(path, body)?;
+            info!("Registry exported to {}", path);
+        }
+        None => io::stdout().write_all(body.as_bytes())?,
+    }
+    Ok(())
+}
+
+async fn import(options: &Options, repo: &dyn Repository) -> io::Result<()> {
+    let path = options.file.as_deref().ok_or_else(|| invalid_input(format!("import needs a file\n{}", USAGE)))?;
+    let format = ExportFormat::parse(options.format.as_deref(), options.table.as_deref()).map_err(invalid_input)?;
+    let mode = options
+        .mode
+        .clone()
+        .map(ImportMode::try_from)
+        .transpose()
+        .map_err(invalid_input)?
+        .unwrap_or_default();
+
+    let data = fs::read(path)?;
+    let export = RegistryExport::parse(format, &data).map_err(invalid_input)?;
+    let report = export.import(repo, mode, options.dry_run).await.map_err(io::Error::other)?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    match report.error_count() {
+        0 => Ok(()),
+        errors => Err(invalid_input(format!("{} invalid rows, nothing was imported", errors))),
+    }
+}
+
+fn encrypt_identity(path: Option<&str>) -> io::Result<()> {
+    let encrypted = agent::encrypt_identity_file(path).map_err(|e| io::Error::other(format!("{:#}", e)))?;
+    match encrypted.identity {
+        Some(identity) => println!("Encrypted {} identity {} at {}", identity.key_type, identity.principal, identity.path),
+        None => println!("Identity file is already encrypted"),
+    }
+    for retired in encrypted.retired {
+        println!("Encrypted retired identity at {}", retired);
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use anyhow::{anyhow, bail, Context};
+use chrono::{NaiveDateTime, Utc};
+use log::{info, warn};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// Snapshot files are named registry-<UTC timestamp>.db
+const FILE_PREFIX: &str = "registry-";
+const FILE_SUFFIX: &str = ".db";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+// Pages copied per backup step, with a pause in between so writers are not starved
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE_MS: u64 = 10;
+
+static CONFIG: OnceLock<BackupConfig> = OnceLock::new();
+
+// Only one backup runs at a time
+static BACKUP_LOCK: Mutex<()> = Mutex::new(());
+
+/// Backup settings, read once from the environment
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// Directory snapshots are written to (`BACKUP_DIR`, default `data/backups`)
+    pub dir: PathBuf,
+    /// Seconds between scheduled backups (`BACKUP_INTERVAL_SECS`, default 6 hours, 0 disables)
+    pub interval_secs: u64,
+    /// Number of most recent snapshots to keep (`BACKUP_KEEP_LAST`, default 14)
+    pub keep_last: usize,
+    /// Snapshots older than this are removed (`BACKUP_MAX_AGE_DAYS`, default 30, 0 disables)
+    pub max_age_days: i64,
+}
+
+impl BackupConfig {
+    pub fn get() -> &'static BackupConfig {
+        CONFIG.get_or_init(|| {
+            fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+                env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+            }
+            BackupConfig {
+                dir: PathBuf::from(env::var("BACKUP_DIR").unwrap_or_else(|_| "data/backups".to_string())),
+                interval_secs: var("BACKUP_INTERVAL_SECS", 6 * 3600),
+                keep_last: var("BACKUP_KEEP_LAST", 14).max(1),
+                max_age_days: var("BACKUP_MAX_AGE_DAYS", 30),
+            }
+        })
+    }
+}
+
+/// A snapshot in the backup directory
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub created_at: i64,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl BackupInfo {
+    fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_string();
+        let timestamp = file_name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_SUFFIX)?;
+        let created_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?.and_utc().timestamp();
+        let size_bytes = fs::metadata(path).ok()?.len();
+        Some(Self { file_name, size_bytes, created_at, path: path.to_path_buf() })
+    }
+}
+
+/// Write a consistent snapshot of the database at `db_path` into `dir`
+///
+/// Uses SQLite's online backup API on a separate read-only connection, copying a few
+/// pages at a time so requests keep being served. The snapshot is integrity checked
+/// before it is given its final name. This blocks, run it off the async runtime.
+pub fn create_backup(db_path: &Path, dir: &Path) -> anyhow::Result<BackupInfo> {
+    let _guard = BACKUP_LOCK.try_lock().map_err(|_| anyhow!("A backup is already running"))?;
+
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create backup directory {:?}", dir))?;
+    let file_name = format!("{}{}{}", FILE_PREFIX, Utc::now().format(TIMESTAMP_FORMAT), FILE_SUFFIX);
+    let path = dir.join(&file_name);
+    let tmp_path = dir.join(format!("{}.tmp", file_name));
+
+    let result = write_snapshot(db_path, &tmp_path).and_then(|_| verify_backup(&tmp_path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    fs::rename(&tmp_path, &path).context("Failed to move backup into place")?;
+
+    let info = BackupInfo::from_path(&path).ok_or_else(|| anyhow!("Failed to read backup {:?}", path))?;
+    info!("Database backup written to {:?} ({} bytes)", path, info.size_bytes);
+    Ok(info)
+}
+
+fn write_snapshot(db_path: &Path, target: &Path) -> anyhow::Result<()> {
+    let source = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .with_context(|| format!("Failed to open database {:?}", db_path))?;
+    let mut destination = Connection::open(target)
+        .with_context(|| format!("Failed to create backup file {:?}", target))?;
+
+    let backup = Backup::new(&source, &mut destination)?;
+    backup.run_to_completion(PAGES_PER_STEP, Duration::from_millis(STEP_PAUSE_MS), None)?;
+    Ok(())
+}
+
+/// Run SQLite's integrity check on a snapshot
+pub fn verify_backup(path: &Path) -> anyhow::Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let result: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if result != "ok" {
+        bail!("Integrity check of {:?} failed: {}", path, result);
+    }
+    Ok(())
+}
+
+/// List the snapshots in `dir`, newest first
+pub fn list_backups(dir: &Path) -> anyhow::Result<Vec<BackupInfo>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups: Vec<BackupInfo> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| BackupInfo::from_path(&entry.path()))
+        .collect();
+    backups.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(backups)
+}
+
+/// Delete snapshots beyond the newest `keep_last` or older than `max_age_days`
+///
+/// The newest snapshot is always kept. Returns the number of deleted snapshots.
+pub fn apply_retention(dir: &Path, keep_last: usize, max_age_days: i64) -> anyhow::Result<usize> {
+    let cutoff = Utc::now().timestamp() - max_age_days * 86_400;
+    let mut deleted = 0;
+    for (index, backup) in list_backups(dir)?.iter().enumerate() {
+        let expired = index >= keep_last || (max_age_days > 0 && backup.created_at < cutoff);
+        if index > 0 && expired {
+            match fs::remove_file(&backup.path) {
+                Ok(_) => deleted += 1,
+                Err(e) => warn!("Failed to delete old backup {:?}: {}", backup.path, e),
+            }
+        }
+    }
+    Ok(deleted)
+}
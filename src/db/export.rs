@@ -0,0 +1,459 @@
+use anyhow::{anyhow, bail};
+use candid::Principal;
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::db::models::verified_module_hash::VerifiedModuleHash;
+use crate::db::repository::Repository;
+
+/// Version of the export format, bumped when fields change incompatibly
+pub const EXPORT_VERSION: u32 = 1;
+
+// First line of a CSV export, followed by the version and table name
+const CSV_HEADER_PREFIX: &str = "# registry-export";
+
+/// A table that can be exported and imported
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryTable {
+    Canisters,
+    TokenInfo,
+    LedgerInfo,
+    MinerInfo,
+    MiningStats,
+    VerifiedModuleHashes,
+}
+
+impl RegistryTable {
+    /// All tables, in the order they are imported
+    pub const ALL: [RegistryTable; 6] = [
+        RegistryTable::Canisters,
+        RegistryTable::TokenInfo,
+        RegistryTable::LedgerInfo,
+        RegistryTable::MinerInfo,
+        RegistryTable::MiningStats,
+        RegistryTable::VerifiedModuleHashes,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegistryTable::Canisters => "canisters",
+            RegistryTable::TokenInfo => "token_info",
+            RegistryTable::LedgerInfo => "ledger_info",
+            RegistryTable::MinerInfo => "miner_info",
+            RegistryTable::MiningStats => "mining_stats",
+            RegistryTable::VerifiedModuleHashes => "verified_module_hashes",
+        }
+    }
+
+    /// Column identifying a row when checking whether it already exists
+    pub fn key_column(&self) -> &'static str {
+        match self {
+            RegistryTable::VerifiedModuleHashes => "hash",
+            _ => "canister_id",
+        }
+    }
+}
+
+impl fmt::Display for RegistryTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<String> for RegistryTable {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        RegistryTable::ALL
+            .into_iter()
+            .find(|table| table.as_str() == s.to_lowercase())
+            .ok_or_else(|| anyhow!("Invalid table: {}", s))
+    }
+}
+
+/// Serialization of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// All tables, or only the given one, in a single JSON document
+    Json(Option<RegistryTable>),
+    /// One table as CSV
+    Csv(RegistryTable),
+}
+
+impl ExportFormat {
+    /// Parse a format name (`json` when omitted) and an optional table name
+    pub fn parse(format: Option<&str>, table: Option<&str>) -> anyhow::Result<Self> {
+        let table = table.map(|table| RegistryTable::try_from(table.to_string())).transpose()?;
+        match format.unwrap_or("json").to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json(table)),
+            "csv" => table
+                .map(ExportFormat::Csv)
+                .ok_or_else(|| anyhow!("CSV exports contain a single table, which must be given")),
+            other => Err(anyhow!("Invalid format: {}", other)),
+        }
+    }
+
+    /// Tables included in the export
+    pub fn tables(&self) -> Vec<RegistryTable> {
+        match self {
+            ExportFormat::Json(Some(table)) | ExportFormat::Csv(table) => vec![*table],
+            ExportFormat::Json(None) => RegistryTable::ALL.to_vec(),
+        }
+    }
+}
+
+/// How rows that already exist are handled on import
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Overwrite existing rows
+    #[default]
+    Upsert,
+    /// Leave existing rows untouched
+    SkipExisting,
+}
+
+impl TryFrom<String> for ImportMode {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "upsert" => Ok(ImportMode::Upsert),
+            "skip_existing" => Ok(ImportMode::SkipExisting),
+            _ => Err(anyhow!("Invalid import mode: {}", s)),
+        }
+    }
+}
+
+/// A snapshot of the registry; canisters in the trash are not included
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegistryExport {
+    pub version: u32,
+    pub exported_at: i64,
+    #[serde(default)]
+    pub canisters: Vec<Canister>,
+    #[serde(default)]
+    pub token_info: Vec<TokenInfo>,
+    #[serde(default)]
+    pub ledger_info: Vec<LedgerInfo>,
+    #[serde(default)]
+    pub miner_info: Vec<MinerInfo>,
+    #[serde(default)]
+    pub mining_stats: Vec<MiningStats>,
+    #[serde(default)]
+    pub verified_module_hashes: Vec<VerifiedModuleHash>,
+}
+
+/// An import row that failed validation
+#[derive(Debug, Serialize, Clone)]
+pub struct RowError {
+    /// 1-based position of the row within its table
+    pub row: usize,
+    pub key: String,
+    pub message: String,
+}
+
+/// What an import did, or would do in a dry run, to one table
+#[derive(Debug, Serialize, Clone)]
+pub struct TableReport {
+    pub table: RegistryTable,
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub errors: Vec<RowError>,
+}
+
+/// Result of an import
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub mode: ImportMode,
+    /// Whether the rows were written; false for dry runs and when any row is invalid
+    pub applied: bool,
+    pub tables: Vec<TableReport>,
+}
+
+impl ImportReport {
+    pub fn error_count(&self) -> usize {
+        self.tables.iter().map(|table| table.errors.len()).sum()
+    }
+}
+
+impl RegistryExport {
+    fn empty() -> Self {
+        Self {
+            version: EXPORT_VERSION,
+            exported_at: Utc::now().timestamp(),
+            canisters: Vec::new(),
+            token_info: Vec::new(),
+            ledger_info: Vec::new(),
+            miner_info: Vec::new(),
+            mining_stats: Vec::new(),
+            verified_module_hashes: Vec::new(),
+        }
+    }
+
+    /// Read the given tables from the database
+    pub async fn load(repo: &dyn Repository, tables: &[RegistryTable]) -> anyhow::Result<Self> {
+        let mut export = Self::empty();
+        for table in tables {
+            match table {
+                RegistryTable::Canisters => export.canisters = repo.canisters().find_all().await?,
+                RegistryTable::TokenInfo => export.token_info = repo.tokens().find_all().await?,
+                RegistryTable::LedgerInfo => export.ledger_info = repo.ledgers().find_all().await?,
+                RegistryTable::MinerInfo => export.miner_info = repo.miners().find_all().await?,
+                RegistryTable::MiningStats => export.mining_stats = repo.mining_stats().find_all().await?,
+                RegistryTable::VerifiedModuleHashes => {
+                    export.verified_module_hashes = repo.module_hashes().find_all().await?
+                }
+            }
+        }
+        Ok(export)
+    }
+
+    /// Parse an export in the given format
+    pub fn parse(format: ExportFormat, data: &[u8]) -> anyhow::Result<Self> {
+        match format {
+            ExportFormat::Json(_) => Self::from_json(data),
+            ExportFormat::Csv(table) => Self::from_csv(table, data),
+        }
+    }
+
+    /// Parse a JSON export, rejecting unsupported versions
+    pub fn from_json(data: &[u8]) -> anyhow::Result<Self> {
+        let export: Self = serde_json::from_slice(data).map_err(|e| anyhow!("Invalid JSON export: {}", e))?;
+        if export.version != EXPORT_VERSION {
+            bail!("Unsupported export version {} (expected {})", export.version, EXPORT_VERSION);
+        }
+        Ok(export)
+    }
+
+    /// Write one table as CSV, preceded by a `# registry-export <version> <table>` line
+    pub fn to_csv(&self, table: RegistryTable) -> anyhow::Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        match table {
+            RegistryTable::Canisters => write_rows(&mut writer, &self.canisters)?,
+            RegistryTable::TokenInfo => write_rows(&mut writer, &self.token_info)?,
+            RegistryTable::LedgerInfo => write_rows(&mut writer, &self.ledger_info)?,
+            RegistryTable::MinerInfo => write_rows(&mut writer, &self.miner_info)?,
+            RegistryTable::MiningStats => write_rows(&mut writer, &self.mining_stats)?,
+            RegistryTable::VerifiedModuleHashes => write_rows(&mut writer, &self.verified_module_hashes)?,
+        }
+        let rows = String::from_utf8(writer.into_inner().map_err(|e| anyhow!("{}", e))?)?;
+        Ok(format!("{} {} {}\n{}", CSV_HEADER_PREFIX, EXPORT_VERSION, table, rows))
+    }
+
+    /// Parse a CSV export of one table
+    ///
+    /// The `# registry-export` line is optional, but when present its version and
+    /// table must match.
+    pub fn from_csv(table: RegistryTable, data: &[u8]) -> anyhow::Result<Self> {
+        let text = std::str::from_utf8(data).map_err(|_| anyhow!("CSV export is not valid UTF-8"))?;
+        let rows = match text.strip_prefix(CSV_HEADER_PREFIX) {
+            Some(rest) => {
+                let (header, rows) = rest.split_once('\n').unwrap_or((rest, ""));
+                let mut parts = header.split_whitespace();
+                let version = parts.next().and_then(|v| v.parse::<u32>().ok());
+                if version != Some(EXPORT_VERSION) {
+                    bail!("Unsupported export version in CSV header (expected {})", EXPORT_VERSION);
+                }
+                if let Some(header_table) = parts.next() {
+                    if header_table != table.as_str() {
+                        bail!("CSV export contains {}, not {}", header_table, table);
+                    }
+                }
+                rows
+            }
+            None => text,
+        };
+
+        let mut export = Self::empty();
+        let mut reader = csv::Reader::from_reader(rows.as_bytes());
+        match table {
+            RegistryTable::Canisters => export.canisters = read_rows(&mut reader)?,
+            RegistryTable::TokenInfo => export.token_info = read_rows(&mut reader)?,
+            RegistryTable::LedgerInfo => export.ledger_info = read_rows(&mut reader)?,
+            RegistryTable::MinerInfo => export.miner_info = read_rows(&mut reader)?,
+            RegistryTable::MiningStats => export.mining_stats = read_rows(&mut reader)?,
+            RegistryTable::VerifiedModuleHashes => export.verified_module_hashes = read_rows(&mut reader)?,
+        }
+        Ok(export)
+    }
+
+    /// Validate every row and, unless this is a dry run or a row is invalid, write
+    /// all of them in a single transaction
+    pub async fn import(&self, repo: &dyn Repository, mode: ImportMode, dry_run: bool) -> anyhow::Result<ImportReport> {
+        let existing = repo.registry().keys().await?;
+        let has_key = |table: RegistryTable, key: &str| existing.get(&table).is_some_and(|keys| keys.contains(key));
+        // Rows written for trashed canisters would stay hidden, so they must be restored first
+        let trashed: HashSet<String> = repo.canisters().find_deleted().await?
+            .into_iter()
+            .map(|c| c.canister_id)
+            .collect();
+
+        // Canisters in the import count as existing for the rows that reference them
+        let mut known_canisters: HashSet<&str> = self.canisters.iter().map(|c| c.canister_id.as_str()).collect();
+        if let Some(keys) = existing.get(&RegistryTable::Canisters) {
+            known_canisters.extend(keys.iter().map(String::as_str));
+        }
+
+        let mut writes = Self::empty();
+        let mut tables = Vec::new();
+        for table in RegistryTable::ALL {
+            let rows: Vec<(String, Result<(), String>)> = match table {
+                RegistryTable::Canisters => self.canisters.iter()
+                    .map(|c| (c.canister_id.clone(), validate_canister(c)))
+                    .collect(),
+                RegistryTable::TokenInfo => self.token_info.iter()
+                    .map(|t| (t.canister_id.clone(), validate_reference(&t.canister_id, &known_canisters)))
+                    .collect(),
+                RegistryTable::LedgerInfo => self.ledger_info.iter()
+                    .map(|l| (l.canister_id.clone(), validate_ledger(l, &known_canisters)))
+                    .collect(),
+                RegistryTable::MinerInfo => self.miner_info.iter()
+                    .map(|m| (m.canister_id.clone(), validate_miner(m, &known_canisters)))
+                    .collect(),
+                RegistryTable::MiningStats => self.mining_stats.iter()
+                    .map(|s| (s.canister_id.clone(), validate_reference(&s.canister_id, &known_canisters)))
+                    .collect(),
+                RegistryTable::VerifiedModuleHashes => self.verified_module_hashes.iter()
+                    .map(|h| (h.hash.clone(), validate_module_hash(h)))
+                    .collect(),
+            };
+
+            let mut report = TableReport { table, inserted: 0, updated: 0, skipped: 0, errors: Vec::new() };
+            let mut seen = HashSet::new();
+            for (index, (key, result)) in rows.into_iter().enumerate() {
+                let result = result.and_then(|_| {
+                    if table != RegistryTable::VerifiedModuleHashes && trashed.contains(&key) {
+                        Err(format!("Canister {} is in the trash, restore it first", key))
+                    } else if seen.insert(key.clone()) {
+                        Ok(())
+                    } else {
+                        Err("Duplicate row".to_string())
+                    }
+                });
+                if let Err(message) = result {
+                    report.errors.push(RowError { row: index + 1, key, message });
+                    continue;
+                }
+
+                if !has_key(table, &key) {
+                    report.inserted += 1;
+                } else if mode == ImportMode::Upsert {
+                    report.updated += 1;
+                } else {
+                    report.skipped += 1;
+                    continue;
+                }
+                self.copy_row(&mut writes, table, index);
+            }
+            tables.push(report);
+        }
+
+        let mut report = ImportReport { dry_run, mode, applied: false, tables };
+        if !dry_run && report.error_count() == 0 {
+            repo.registry().save(&writes).await?;
+            report.applied = true;
+        }
+        Ok(report)
+    }
+
+    // Add one row of this export to the rows that will be written
+    fn copy_row(&self, writes: &mut Self, table: RegistryTable, index: usize) {
+        match table {
+            RegistryTable::Canisters => writes.canisters.push(self.canisters[index].clone()),
+            RegistryTable::TokenInfo => writes.token_info.push(self.token_info[index].clone()),
+            RegistryTable::LedgerInfo => writes.ledger_info.push(self.ledger_info[index].clone()),
+            RegistryTable::MinerInfo => writes.miner_info.push(self.miner_info[index].clone()),
+            RegistryTable::MiningStats => writes.mining_stats.push(self.mining_stats[index].clone()),
+            RegistryTable::VerifiedModuleHashes => writes.verified_module_hashes.push(self.verified_module_hashes[index].clone()),
+        }
+    }
+}
+
+fn write_rows<T: Serialize>(writer: &mut csv::Writer<Vec<u8>>, rows: &[T]) -> anyhow::Result<()> {
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_rows<T: DeserializeOwned>(reader: &mut csv::Reader<&[u8]>) -> anyhow::Result<Vec<T>> {
+    let mut rows = Vec::new();
+    for (index, row) in reader.deserialize().enumerate() {
+        rows.push(row.map_err(|e| anyhow!("Invalid CSV row {}: {}", index + 1, e))?);
+    }
+    Ok(rows)
+}
+
+fn validate_principal(value: &str, field: &str) -> Result<(), String> {
+    Principal::from_text(value)
+        .map(|_| ())
+        .map_err(|_| format!("Invalid {}: {}", field, value))
+}
+
+fn validate_hash(hash: &str) -> Result<(), String> {
+    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(format!("Invalid module hash: {}", hash))
+    }
+}
+
+fn validate_canister(canister: &Canister) -> Result<(), String> {
+    validate_principal(&canister.canister_id, "canister_id")?;
+    validate_principal(&canister.principal, "principal")?;
+    if let Some(hash) = &canister.module_hash {
+        validate_hash(hash)?;
+    }
+    Ok(())
+}
+
+fn validate_reference(canister_id: &str, known_canisters: &HashSet<&str>) -> Result<(), String> {
+    validate_principal(canister_id, "canister_id")?;
+    if !known_canisters.contains(canister_id) {
+        return Err(format!("Canister {} is neither registered nor part of the import", canister_id));
+    }
+    Ok(())
+}
+
+fn validate_ledger(ledger: &LedgerInfo, known_canisters: &HashSet<&str>) -> Result<(), String> {
+    validate_reference(&ledger.canister_id, known_canisters)?;
+    for (field, value) in [("fee", &ledger.fee), ("total_supply", &ledger.total_supply)] {
+        if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Invalid {}: {}", field, value));
+        }
+    }
+    if serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&ledger.metadata).is_err() {
+        return Err("Invalid metadata: not a JSON object".to_string());
+    }
+    Ok(())
+}
+
+fn validate_miner(miner: &MinerInfo, known_canisters: &HashSet<&str>) -> Result<(), String> {
+    validate_reference(&miner.canister_id, known_canisters)?;
+    if let Some(token) = &miner.current_token {
+        validate_principal(token, "current_token")?;
+    }
+    if miner.speed_percentage > 100 {
+        return Err(format!("Invalid speed_percentage: {}", miner.speed_percentage));
+    }
+    Ok(())
+}
+
+fn validate_module_hash(hash: &VerifiedModuleHash) -> Result<(), String> {
+    validate_hash(&hash.hash)?;
+    CanisterType::try_from(hash.canister_type.clone()).map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 pub mod schema;
 pub mod models;
 pub mod pool;
-
-pub use pool::{init_pool, DbPool}; 
\ No newline at end of file
+pub mod export;
+pub mod backup;
+pub mod repository;
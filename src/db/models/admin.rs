@@ -9,7 +9,13 @@ use argon2::{
     },
     Argon2
 };
+use ring::{constant_time, digest};
 use std::fmt;
+use std::env;
+use std::sync::OnceLock;
+use log::{info, warn};
+
+use crate::db::repository::AdminRepository;
 
 // Custom error type to handle argon2 password hash errors
 #[derive(Debug)]
@@ -29,46 +35,156 @@ impl From<argon2::password_hash::Error> for PasswordError {
     }
 }
 
+/// Role of an admin account, deciding which admin endpoints it may use
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    /// Read-only access to the admin endpoints
+    Viewer,
+    /// Maintains the verified module hash list
+    HashCurator,
+    /// Removes canisters, tokens and miners from the registry
+    RegistryModerator,
+    /// Sends control commands to miners
+    MinerOperator,
+    /// Full access, including admin account management
+    Superadmin,
+}
+
+/// An action on the admin API that requires a specific role
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    ViewRegistry,
+    ManageModuleHashes,
+    ManageRegistry,
+    ManageAdmins,
+    ViewAuditLog,
+    ImportRegistry,
+    ManageBackups,
+    ControlMiners,
+    ManageIdentity,
+}
+
+impl AdminRole {
+    /// Whether this role grants the given permission
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        match self {
+            AdminRole::Superadmin => true,
+            AdminRole::HashCurator => matches!(permission, Permission::ViewRegistry | Permission::ManageModuleHashes),
+            AdminRole::RegistryModerator => matches!(permission, Permission::ViewRegistry | Permission::ManageRegistry),
+            AdminRole::MinerOperator => matches!(permission, Permission::ViewRegistry | Permission::ControlMiners),
+            AdminRole::Viewer => permission == Permission::ViewRegistry,
+        }
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::HashCurator => "hash_curator",
+            AdminRole::RegistryModerator => "registry_moderator",
+            AdminRole::MinerOperator => "miner_operator",
+            AdminRole::Superadmin => "superadmin",
+        };
+        write!(f, "{}", role)
+    }
+}
+
+impl TryFrom<String> for AdminRole {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "viewer" => Ok(AdminRole::Viewer),
+            "hash_curator" => Ok(AdminRole::HashCurator),
+            "registry_moderator" => Ok(AdminRole::RegistryModerator),
+            "miner_operator" => Ok(AdminRole::MinerOperator),
+            "superadmin" => Ok(AdminRole::Superadmin),
+            _ => Err(anyhow::anyhow!("Invalid admin role: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Admin {
     pub id: String,
     pub username: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
-    pub api_key: String,
+    /// First characters of the API key, used to find the admin without storing the key
+    pub api_key_prefix: String,
+    #[serde(skip_serializing)]
+    pub api_key_hash: String,
+    pub previous_api_key_prefix: Option<String>,
+    #[serde(skip_serializing)]
+    pub previous_api_key_hash: Option<String>,
+    pub previous_api_key_expires_at: Option<i64>,
+    pub role: AdminRole,
     pub is_active: bool,
+    pub last_used_at: Option<i64>,
+    pub failed_login_attempts: i64,
+    pub locked_until: Option<i64>,
     pub created_at: i64,
     pub last_updated: i64,
 }
 
+/// Minimum accepted admin password length, also required of bootstrap admin passwords
+pub const MIN_PASSWORD_LENGTH: usize = 12;
+
+// Number of leading API key characters stored in plaintext for lookups
+const API_KEY_PREFIX_LENGTH: usize = 12;
+
+// Columns selected for every admin query
+// The api_key and previous_api_key columns hold SHA-256 hashes of the keys
+const ADMIN_COLUMNS: &str = "id, username, password_hash, api_key, api_key_prefix, previous_api_key,
+             previous_api_key_prefix, previous_api_key_expires_at, role, is_active, last_used_at, failed_login_attempts, locked_until, created_at, last_updated";
+
 impl Admin {
+    /// Create an admin with a freshly generated API key
+    /// Returns the admin and the plaintext key, which is not stored anywhere
     pub fn new(
         username: String,
         password: &str,
-    ) -> Result<Self, anyhow::Error> {
+        role: AdminRole,
+    ) -> Result<(Self, String), anyhow::Error> {
         let now = Utc::now().timestamp();
         let id = Uuid::new_v4().to_string();
         
         // Generate API key
-        let api_key = Uuid::new_v4().to_string();
+        let api_key = generate_api_key();
         
         // Hash the password
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let password_hash = match argon2.hash_password(password.as_bytes(), &salt) {
-            Ok(hash) => hash.to_string(),
-            Err(e) => return Err(PasswordError(e.to_string()).into()),
-        };
+        let password_hash = Self::hash_password(password)?;
         
-        Ok(Self {
+        let admin = Self {
             id,
             username,
             password_hash,
-            api_key,
+            api_key_prefix: api_key_prefix(&api_key),
+            api_key_hash: hash_api_key(&api_key),
+            previous_api_key_prefix: None,
+            previous_api_key_hash: None,
+            previous_api_key_expires_at: None,
+            role,
             is_active: true,
+            last_used_at: None,
+            failed_login_attempts: 0,
+            locked_until: None,
             created_at: now,
             last_updated: now,
-        })
+        };
+        
+        Ok((admin, api_key))
+    }
+
+    fn hash_password(password: &str) -> Result<String, anyhow::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2 = Argon2::default();
+        match argon2.hash_password(password.as_bytes(), &salt) {
+            Ok(hash) => Ok(hash.to_string()),
+            Err(e) => Err(PasswordError(e.to_string()).into()),
+        }
     }
 
     pub fn from_row(row: &Row) -> Result<Self> {
@@ -76,8 +192,16 @@ impl Admin {
             id: row.get("id")?,
             username: row.get("username")?,
             password_hash: row.get("password_hash")?,
-            api_key: row.get("api_key")?,
+            api_key_prefix: row.get("api_key_prefix")?,
+            api_key_hash: row.get("api_key")?,
+            previous_api_key_prefix: row.get("previous_api_key_prefix")?,
+            previous_api_key_hash: row.get("previous_api_key")?,
+            previous_api_key_expires_at: row.get("previous_api_key_expires_at")?,
+            role: AdminRole::try_from(row.get::<_, String>("role")?).unwrap_or(AdminRole::Viewer),
             is_active: row.get::<_, i64>("is_active")? != 0,
+            last_used_at: row.get("last_used_at")?,
+            failed_login_attempts: row.get("failed_login_attempts")?,
+            locked_until: row.get("locked_until")?,
             created_at: row.get("created_at")?,
             last_updated: row.get("last_updated")?,
         })
@@ -85,18 +209,29 @@ impl Admin {
 
     pub fn save(&self, conn: &Connection) -> Result<()> {
         conn.execute(
-            "INSERT INTO admins (id, username, password_hash, api_key, is_active, created_at, last_updated)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "INSERT INTO admins (id, username, password_hash, api_key, api_key_prefix, previous_api_key,
+             previous_api_key_prefix, previous_api_key_expires_at, role, is_active, created_at, last_updated)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
              ON CONFLICT(username) DO UPDATE SET
              password_hash = ?3,
              api_key = ?4,
-             is_active = ?5,
-             last_updated = ?7",
+             api_key_prefix = ?5,
+             previous_api_key = ?6,
+             previous_api_key_prefix = ?7,
+             previous_api_key_expires_at = ?8,
+             role = ?9,
+             is_active = ?10,
+             last_updated = ?12",
             params![
                 self.id,
                 self.username,
                 self.password_hash,
-                self.api_key,
+                self.api_key_hash,
+                self.api_key_prefix,
+                self.previous_api_key_hash,
+                self.previous_api_key_prefix,
+                self.previous_api_key_expires_at,
+                self.role.to_string(),
                 self.is_active as i64,
                 self.created_at,
                 self.last_updated,
@@ -114,14 +249,53 @@ impl Admin {
         Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
     }
 
-    pub fn find_by_username(conn: &Connection, username: &str) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, username, password_hash, api_key, is_active, created_at, last_updated
-             FROM admins
-             WHERE username = ?1",
-        )?;
+    /// Check a password against a throwaway hash, for logins to usernames without an active account
+    ///
+    /// Takes as long as a real check, so response times do not reveal which usernames exist.
+    pub fn verify_dummy_password(password: &str) {
+        static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+        let hash = DUMMY_HASH.get_or_init(|| Self::hash_password(&generate_api_key()).unwrap_or_default());
+        if let Ok(parsed_hash) = PasswordHash::new(hash) {
+            let _ = Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
+        }
+    }
+
+    /// Replace the password hash
+    pub fn set_password(&mut self, password: &str) -> Result<(), anyhow::Error> {
+        self.password_hash = Self::hash_password(password)?;
+        self.last_updated = Utc::now().timestamp();
+        Ok(())
+    }
+
+    /// Issue a new API key, keeping the current one valid for `grace_period_secs`
+    /// Returns the new key
+    pub fn rotate_api_key(&mut self, grace_period_secs: i64) -> String {
+        let now = Utc::now().timestamp();
+        let new_key = generate_api_key();
         
-        let mut rows = stmt.query(params![username])?;
+        if grace_period_secs > 0 {
+            self.previous_api_key_prefix = Some(self.api_key_prefix.clone());
+            self.previous_api_key_hash = Some(self.api_key_hash.clone());
+            self.previous_api_key_expires_at = Some(now + grace_period_secs);
+        } else {
+            self.previous_api_key_prefix = None;
+            self.previous_api_key_hash = None;
+            self.previous_api_key_expires_at = None;
+        }
+        
+        self.api_key_prefix = api_key_prefix(&new_key);
+        self.api_key_hash = hash_api_key(&new_key);
+        self.last_updated = now;
+        new_key
+    }
+
+    pub fn find_by_id(conn: &Connection, id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM admins WHERE id = ?1",
+            ADMIN_COLUMNS
+        ))?;
+        
+        let mut rows = stmt.query(params![id])?;
         
         if let Some(row) = rows.next()? {
             Ok(Some(Self::from_row(row)?))
@@ -130,14 +304,73 @@ impl Admin {
         }
     }
 
-    pub fn find_by_api_key(conn: &Connection, api_key: &str) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, username, password_hash, api_key, is_active, created_at, last_updated
-             FROM admins
-             WHERE api_key = ?1 AND is_active = 1",
+    pub fn find_all(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM admins ORDER BY created_at ASC",
+            ADMIN_COLUMNS
+        ))?;
+        
+        let rows = stmt.query_map([], Self::from_row)?;
+        
+        let mut admins = Vec::new();
+        for admin in rows {
+            admins.push(admin?);
+        }
+        
+        Ok(admins)
+    }
+
+    /// Count active admins with full access
+    pub fn count_active_superadmins(conn: &Connection) -> Result<i64> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM admins WHERE is_active = 1 AND role = 'superadmin'",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Record that the admin just authenticated
+    pub fn touch_last_used(conn: &Connection, id: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE admins SET last_used_at = ?2 WHERE id = ?1",
+            params![id, Utc::now().timestamp()],
         )?;
+        Ok(())
+    }
+
+    /// Whether the account is locked after too many failed logins
+    pub fn is_locked(&self) -> bool {
+        self.locked_until.is_some_and(|until| until > Utc::now().timestamp())
+    }
+
+    /// Count a failed login, locking the account once `max_attempts` is reached
+    pub fn record_login_failure(conn: &Connection, id: &str, max_attempts: i64, lockout_secs: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE admins SET
+             failed_login_attempts = failed_login_attempts + 1,
+             locked_until = CASE WHEN failed_login_attempts + 1 >= ?2 THEN ?3 ELSE locked_until END
+             WHERE id = ?1",
+            params![id, max_attempts, Utc::now().timestamp() + lockout_secs],
+        )?;
+        Ok(())
+    }
+
+    /// Clear the failed login counter after a successful login
+    pub fn reset_login_failures(conn: &Connection, id: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE admins SET failed_login_attempts = 0, locked_until = NULL WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    pub fn find_by_username(conn: &Connection, username: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM admins WHERE username = ?1",
+            ADMIN_COLUMNS
+        ))?;
         
-        let mut rows = stmt.query(params![api_key])?;
+        let mut rows = stmt.query(params![username])?;
         
         if let Some(row) = rows.next()? {
             Ok(Some(Self::from_row(row)?))
@@ -146,25 +379,200 @@ impl Admin {
         }
     }
 
-    pub fn create_admin_if_none_exists(conn: &Connection, username: &str, password: &str) -> Result<(), anyhow::Error> {
-        // Check if any admin exists
-        let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM admins",
-            [],
-            |row| row.get(0),
+    /// Find an active admin by its current API key, or by its previous key while the grace period lasts
+    ///
+    /// Candidates are looked up by key prefix and the key is then checked against the stored hash.
+    pub fn find_by_api_key(conn: &Connection, api_key: &str) -> Result<Option<Self>> {
+        let now = Utc::now().timestamp();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM admins
+             WHERE is_active = 1
+             AND (api_key_prefix = ?1 OR (previous_api_key_prefix = ?1 AND previous_api_key_expires_at > ?2))",
+            ADMIN_COLUMNS
+        ))?;
+        
+        let rows = stmt.query_map(params![api_key_prefix(api_key), now], Self::from_row)?;
+        
+        for admin in rows {
+            let admin = admin?;
+            if admin.matches_api_key(api_key, now) {
+                return Ok(Some(admin));
+            }
+        }
+        
+        Ok(None)
+    }
+
+    /// Whether a key is the admin's current key, or its previous key still within the grace period
+    pub fn matches_api_key(&self, api_key: &str, now: i64) -> bool {
+        let previous_key_valid = self.previous_api_key_expires_at.is_some_and(|expires| expires > now);
+        let matches_current = verify_api_key(api_key, &self.api_key_hash);
+        let matches_previous = previous_key_valid
+            && self.previous_api_key_hash.as_deref().is_some_and(|hash| verify_api_key(api_key, hash));
+        matches_current || matches_previous
+    }
+
+    /// Replace API keys stored in plaintext by earlier versions with their prefix and hash
+    pub fn migrate_plaintext_api_keys(conn: &Connection) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "SELECT id, api_key, previous_api_key FROM admins WHERE api_key_prefix IS NULL",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+        
+        let mut plaintext = Vec::new();
+        for row in rows {
+            plaintext.push(row?);
+        }
+        
+        for (id, api_key, previous_api_key) in plaintext {
+            conn.execute(
+                "UPDATE admins SET api_key = ?2, api_key_prefix = ?3, previous_api_key = ?4, previous_api_key_prefix = ?5
+                 WHERE id = ?1",
+                params![
+                    id,
+                    hash_api_key(&api_key),
+                    api_key_prefix(&api_key),
+                    previous_api_key.as_deref().map(hash_api_key),
+                    previous_api_key.as_deref().map(api_key_prefix),
+                ],
+            )?;
+            info!("Migrated API key of admin {} to hashed storage", id);
+        }
+        
+        Ok(())
+    }
+
+    /// Insert the admin only if the table is still empty
+    /// Returns whether it was inserted
+    pub fn insert_if_none_exists(&self, conn: &Connection) -> Result<bool> {
+        let rows_affected = conn.execute(
+            "INSERT INTO admins (id, username, password_hash, api_key, api_key_prefix, role, is_active, created_at, last_updated)
+             SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9
+             WHERE NOT EXISTS (SELECT 1 FROM admins)",
+            params![
+                self.id,
+                self.username,
+                self.password_hash,
+                self.api_key_hash,
+                self.api_key_prefix,
+                self.role.to_string(),
+                self.is_active as i64,
+                self.created_at,
+                self.last_updated,
+            ],
         )?;
+        Ok(rows_affected > 0)
+    }
+    
+    /// Bootstrap the first admin account on an empty database
+    ///
+    /// Credentials are taken from `ADMIN_USERNAME`/`ADMIN_PASSWORD`, or from the
+    /// `username:password` file named by `ADMIN_CREDENTIALS_FILE`. Without either,
+    /// a random password is generated and printed once together with the API key.
+    /// The credentials are only resolved and checked while no admin exists.
+    pub async fn check_or_create_default(admins: &dyn AdminRepository) -> Result<(), anyhow::Error> {
+        if !admins.find_all().await?.is_empty() {
+            warn_default_password(admins).await?;
+            return Ok(());
+        }
+        let (username, password, generated) = bootstrap_credentials()?;
         
-        if count == 0 {
-            // No admins exist, create the default one
-            let admin = Self::new(username.to_string(), password)?;
-            admin.save(conn)?;
+        // The bootstrap admin gets full access; when several instances start at once only one insert wins
+        let (admin, api_key) = Self::new(username, &password, AdminRole::Superadmin)?;
+        if admins.insert_if_none_exists(&admin).await? {
+            info!("Created bootstrap admin account: {}", admin.username);
+            if generated {
+                // Printed once on stdout rather than through the logger; under systemd this still ends up in the journal
+                println!("==================================================");
+                println!("Bootstrap admin account created");
+                println!("  username: {}", admin.username);
+                println!("  password: {}", password);
+                println!("  api key:  {}", api_key);
+                println!("Store these now, they will not be shown again.");
+                println!("==================================================");
+            } else {
+                println!("Bootstrap admin '{}' created, API key: {}", admin.username, api_key);
+            }
+        } else {
+            warn_default_password(admins).await?;
         }
         
         Ok(())
     }
+}
+
+// Warn about installations still using the old hardcoded default
+async fn warn_default_password(admins: &dyn AdminRepository) -> Result<(), anyhow::Error> {
+    if let Some(admin) = admins.find_by_username("admin").await? {
+        if admin.is_active && admin.verify_password("admin123").unwrap_or(false) {
+            warn!("Admin account 'admin' still uses the default password, change it immediately");
+        }
+    }
+    Ok(())
+}
+
+// Resolve the bootstrap admin credentials
+// Returns (username, password, whether the password was generated)
+fn bootstrap_credentials() -> Result<(String, String, bool), anyhow::Error> {
+    let username = env::var("ADMIN_USERNAME").unwrap_or_else(|_| "admin".to_string());
     
-    pub fn check_or_create_default(conn: &Connection) -> Result<(), anyhow::Error> {
-        // Use the existing method with default credentials
-        Self::create_admin_if_none_exists(conn, "admin", "admin123")
+    if let Ok(password) = env::var("ADMIN_PASSWORD") {
+        if !password.is_empty() {
+            check_bootstrap_password(&password, "ADMIN_PASSWORD")?;
+            return Ok((username, password, false));
+        }
     }
-} 
\ No newline at end of file
+    
+    if let Ok(path) = env::var("ADMIN_CREDENTIALS_FILE") {
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read admin credentials file {}: {}", path, e))?;
+        let line = contents.lines().next().unwrap_or("").trim();
+        return match line.split_once(':') {
+            Some((file_username, password)) if !file_username.is_empty() && !password.is_empty() => {
+                check_bootstrap_password(password, &format!("The password in {}", path))?;
+                Ok((file_username.to_string(), password.to_string(), false))
+            }
+            _ => Err(anyhow::anyhow!("Admin credentials file {} must contain username:password", path)),
+        };
+    }
+    
+    Ok((username, Uuid::new_v4().simple().to_string(), true))
+}
+
+// Apply the admin API's password rules to configured bootstrap passwords
+fn check_bootstrap_password(password: &str, source: &str) -> Result<(), anyhow::Error> {
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Err(anyhow::anyhow!("{} must be at least {} characters", source, MIN_PASSWORD_LENGTH));
+    }
+    Ok(())
+}
+
+// Generate a new random API key
+fn generate_api_key() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// The plaintext part of an API key stored for lookups
+pub fn api_key_prefix(api_key: &str) -> String {
+    api_key.chars().take(API_KEY_PREFIX_LENGTH).collect()
+}
+
+// Hex-encoded SHA-256 hash of an API key
+fn hash_api_key(api_key: &str) -> String {
+    digest::digest(&digest::SHA256, api_key.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Compare an API key with a stored hash in constant time
+fn verify_api_key(api_key: &str, hash: &str) -> bool {
+    constant_time::verify_slices_are_equal(hash_api_key(api_key).as_bytes(), hash.as_bytes()).is_ok()
+}
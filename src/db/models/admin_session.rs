@@ -0,0 +1,115 @@
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+
+/// A password login session, backing the short-lived bearer tokens issued to an admin
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdminSession {
+    pub id: String,
+    pub admin_id: String,
+    /// Identifier of the only refresh token currently accepted for this session
+    #[serde(skip_serializing)]
+    pub refresh_token_id: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+impl AdminSession {
+    pub fn new(admin_id: String, ttl_secs: i64) -> Self {
+        let now = Utc::now().timestamp();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            admin_id,
+            refresh_token_id: Uuid::new_v4().to_string(),
+            created_at: now,
+            expires_at: now + ttl_secs,
+            revoked_at: None,
+        }
+    }
+
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            admin_id: row.get("admin_id")?,
+            refresh_token_id: row.get("refresh_token_id")?,
+            created_at: row.get("created_at")?,
+            expires_at: row.get("expires_at")?,
+            revoked_at: row.get("revoked_at")?,
+        })
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT INTO admin_sessions (id, admin_id, refresh_token_id, created_at, expires_at, revoked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+             refresh_token_id = ?3,
+             expires_at = ?5,
+             revoked_at = ?6",
+            params![
+                self.id,
+                self.admin_id,
+                self.refresh_token_id,
+                self.created_at,
+                self.expires_at,
+                self.revoked_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Whether tokens of this session may still be used
+    pub fn is_valid(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > Utc::now().timestamp()
+    }
+
+    /// Issue a new refresh token identifier, invalidating the previous refresh token
+    pub fn rotate_refresh_token(&mut self, ttl_secs: i64) -> String {
+        self.refresh_token_id = Uuid::new_v4().to_string();
+        self.expires_at = Utc::now().timestamp() + ttl_secs;
+        self.refresh_token_id.clone()
+    }
+
+    pub fn find_by_id(conn: &Connection, id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, admin_id, refresh_token_id, created_at, expires_at, revoked_at
+             FROM admin_sessions
+             WHERE id = ?1",
+        )?;
+        
+        let mut rows = stmt.query(params![id])?;
+        
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn revoke(conn: &Connection, id: &str) -> Result<bool> {
+        let rows_affected = conn.execute(
+            "UPDATE admin_sessions SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+            params![id, Utc::now().timestamp()],
+        )?;
+        
+        Ok(rows_affected > 0)
+    }
+
+    /// Revoke every open session of an admin, e.g. when the account is deactivated
+    pub fn revoke_all_for_admin(conn: &Connection, admin_id: &str) -> Result<usize> {
+        conn.execute(
+            "UPDATE admin_sessions SET revoked_at = ?2 WHERE admin_id = ?1 AND revoked_at IS NULL",
+            params![admin_id, Utc::now().timestamp()],
+        )
+    }
+
+    /// Remove sessions that can no longer be used
+    pub fn delete_expired(conn: &Connection) -> Result<usize> {
+        conn.execute(
+            "DELETE FROM admin_sessions WHERE expires_at < ?1 OR revoked_at IS NOT NULL",
+            params![Utc::now().timestamp()],
+        )
+    }
+}
@@ -0,0 +1,218 @@
+use rusqlite::{params_from_iter, types::Value as SqlValue, Connection, Result, Row};
+use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::Utc;
+
+use crate::db::models::admin::Admin;
+
+/// A recorded mutation of the registry or of admin accounts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditLogEntry {
+    pub id: String,
+    /// `admin` for authenticated admins, `public` for unauthenticated API calls
+    pub actor_type: String,
+    pub actor_id: Option<String>,
+    pub actor_name: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    /// State before the change; for updates only the fields that changed
+    pub before: Option<Value>,
+    /// State after the change; for updates only the fields that changed
+    pub after: Option<Value>,
+    pub ip_address: Option<String>,
+    pub created_at: i64,
+}
+
+/// Filters for querying the audit log
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl AuditLogEntry {
+    pub fn new(action: &str, target_type: &str, target_id: &str) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            actor_type: "public".to_string(),
+            actor_id: None,
+            actor_name: None,
+            action: action.to_string(),
+            target_type: target_type.to_string(),
+            target_id: target_id.to_string(),
+            before: None,
+            after: None,
+            ip_address: None,
+            created_at: Utc::now().timestamp(),
+        }
+    }
+
+    /// Attribute the entry to an authenticated admin
+    pub fn by_admin(mut self, admin: &Admin) -> Self {
+        self.actor_type = "admin".to_string();
+        self.actor_id = Some(admin.id.clone());
+        self.actor_name = Some(admin.username.clone());
+        self
+    }
+
+    pub fn with_ip(mut self, ip_address: Option<&str>) -> Self {
+        self.ip_address = ip_address.map(|ip| ip.to_string());
+        self
+    }
+
+    /// Record the state before the change; `None` values are left out
+    pub fn with_before<T: Serialize>(mut self, before: &T) -> Self {
+        self.before = serde_json::to_value(before).ok().filter(|value| !value.is_null());
+        self
+    }
+
+    /// Record the state after the change; `None` values are left out
+    pub fn with_after<T: Serialize>(mut self, after: &T) -> Self {
+        self.after = serde_json::to_value(after).ok().filter(|value| !value.is_null());
+        self
+    }
+
+    pub fn from_row(row: &Row) -> Result<Self> {
+        let before: Option<String> = row.get("before_state")?;
+        let after: Option<String> = row.get("after_state")?;
+        Ok(Self {
+            id: row.get("id")?,
+            actor_type: row.get("actor_type")?,
+            actor_id: row.get("actor_id")?,
+            actor_name: row.get("actor_name")?,
+            action: row.get("action")?,
+            target_type: row.get("target_type")?,
+            target_id: row.get("target_id")?,
+            before: before.and_then(|json| serde_json::from_str(&json).ok()),
+            after: after.and_then(|json| serde_json::from_str(&json).ok()),
+            ip_address: row.get("ip_address")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    /// The before/after states as stored: JSON reduced to the fields that changed
+    pub fn changed_states(&self) -> (Option<String>, Option<String>) {
+        let (before, after) = diff(self.before.clone(), self.after.clone());
+        (before.map(|value| value.to_string()), after.map(|value| value.to_string()))
+    }
+
+    /// Store the entry, reducing before/after to the fields that changed
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        let (before, after) = self.changed_states();
+        conn.execute(
+            "INSERT INTO audit_log (id, actor_type, actor_id, actor_name, action, target_type, target_id,
+             before_state, after_state, ip_address, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![
+                self.id,
+                self.actor_type,
+                self.actor_id,
+                self.actor_name,
+                self.action,
+                self.target_type,
+                self.target_id,
+                before,
+                after,
+                self.ip_address,
+                self.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Find entries matching a filter, newest first
+    pub fn find(conn: &Connection, filter: &AuditLogFilter, limit: i64, offset: i64) -> Result<Vec<Self>> {
+        let (where_clause, mut values) = filter.to_sql();
+        values.push(SqlValue::Integer(limit));
+        values.push(SqlValue::Integer(offset));
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, actor_type, actor_id, actor_name, action, target_type, target_id,
+             before_state, after_state, ip_address, created_at
+             FROM audit_log
+             {}
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ? OFFSET ?",
+            where_clause
+        ))?;
+
+        let rows = stmt.query_map(params_from_iter(values), Self::from_row)?;
+
+        let mut entries = Vec::new();
+        for entry in rows {
+            entries.push(entry?);
+        }
+
+        Ok(entries)
+    }
+
+    /// Count entries matching a filter
+    pub fn count(conn: &Connection, filter: &AuditLogFilter) -> Result<i64> {
+        let (where_clause, values) = filter.to_sql();
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM audit_log {}", where_clause),
+            params_from_iter(values),
+            |row| row.get(0),
+        )
+    }
+}
+
+impl AuditLogFilter {
+    // Build the WHERE clause and its positional parameters
+    fn to_sql(&self) -> (String, Vec<SqlValue>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        let text_filters = [
+            ("actor_id", &self.actor_id),
+            ("action", &self.action),
+            ("target_type", &self.target_type),
+            ("target_id", &self.target_id),
+        ];
+        for (column, value) in text_filters {
+            if let Some(value) = value {
+                conditions.push(format!("{} = ?", column));
+                values.push(SqlValue::Text(value.clone()));
+            }
+        }
+        if let Some(since) = self.since {
+            conditions.push("created_at >= ?".to_string());
+            values.push(SqlValue::Integer(since));
+        }
+        if let Some(until) = self.until {
+            conditions.push("created_at <= ?".to_string());
+            values.push(SqlValue::Integer(until));
+        }
+
+        if conditions.is_empty() {
+            (String::new(), values)
+        } else {
+            (format!("WHERE {}", conditions.join(" AND ")), values)
+        }
+    }
+}
+
+// Drop the top-level fields that are equal on both sides of an update
+fn diff(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+    match (before, after) {
+        (Some(Value::Object(mut before)), Some(Value::Object(mut after))) => {
+            let unchanged: Vec<String> = before
+                .iter()
+                .filter(|(key, value)| after.get(key.as_str()) == Some(value))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in unchanged {
+                before.remove(&key);
+                after.remove(&key);
+            }
+            (Some(Value::Object(before)), Some(Value::Object(after)))
+        }
+        (before, after) => (before, after),
+    }
+}
@@ -45,8 +45,19 @@ pub struct Canister {
     pub module_hash: Option<String>,
     pub created_at: i64,
     pub last_updated: i64,
+    /// Set when the canister was moved to the trash
+    pub deleted_at: Option<i64>,
 }
 
+// Columns selected for every canister query
+const CANISTER_COLUMNS: &str = "id, principal, canister_id, type, module_hash, created_at, last_updated, deleted_at";
+
+// Tables holding data that belongs to a canister, deleted together with it
+const DEPENDENT_TABLES: &[&str] = &[
+    "mining_stats", "miner_info", "token_info", "ledger_info", "ledger_transactions", "ledger_index_state",
+    "ledger_balances", "canister_interfaces",
+];
+
 impl Canister {
     pub fn new(
         principal: String,
@@ -63,6 +74,7 @@ impl Canister {
             module_hash,
             created_at: now,
             last_updated: now,
+            deleted_at: None,
         }
     }
 
@@ -79,6 +91,7 @@ impl Canister {
             module_hash: row.get("module_hash")?,
             created_at: row.get("created_at")?,
             last_updated: row.get("last_updated")?,
+            deleted_at: row.get("deleted_at")?,
         })
     }
 
@@ -107,9 +120,7 @@ impl Canister {
     #[allow(dead_code)]
     pub fn find_by_id(conn: &Connection, id: &str) -> Result<Option<Self>> {
         let mut stmt = conn.prepare(
-            "SELECT id, principal, canister_id, type, module_hash, created_at, last_updated
-             FROM canisters
-             WHERE id = ?1",
+            &format!("SELECT {} FROM canisters WHERE id = ?1", CANISTER_COLUMNS),
         )?;
         
         let mut rows = stmt.query(params![id])?;
@@ -123,9 +134,7 @@ impl Canister {
 
     pub fn find_by_canister_id(conn: &Connection, canister_id: &str) -> Result<Option<Self>> {
         let mut stmt = conn.prepare(
-            "SELECT id, principal, canister_id, type, module_hash, created_at, last_updated
-             FROM canisters
-             WHERE canister_id = ?1",
+            &format!("SELECT {} FROM canisters WHERE canister_id = ?1 AND deleted_at IS NULL", CANISTER_COLUMNS),
         )?;
         
         let mut rows = stmt.query(params![canister_id])?;
@@ -139,12 +148,13 @@ impl Canister {
 
     pub fn find_all(conn: &Connection) -> Result<Vec<Self>> {
         let mut stmt = conn.prepare(
-            "SELECT id, principal, canister_id, type, module_hash, created_at, last_updated
-             FROM canisters
-             ORDER BY last_updated DESC",
+            &format!(
+                "SELECT {} FROM canisters WHERE deleted_at IS NULL ORDER BY last_updated DESC",
+                CANISTER_COLUMNS
+            ),
         )?;
         
-        let rows = stmt.query_map([], |row| Self::from_row(row))?;
+        let rows = stmt.query_map([], Self::from_row)?;
         
         let mut canisters = Vec::new();
         for canister in rows {
@@ -156,13 +166,13 @@ impl Canister {
 
     pub fn find_by_type(conn: &Connection, canister_type: &CanisterType) -> Result<Vec<Self>> {
         let mut stmt = conn.prepare(
-            "SELECT id, principal, canister_id, type, module_hash, created_at, last_updated
-             FROM canisters
-             WHERE type = ?1
-             ORDER BY last_updated DESC",
+            &format!(
+                "SELECT {} FROM canisters WHERE type = ?1 AND deleted_at IS NULL ORDER BY last_updated DESC",
+                CANISTER_COLUMNS
+            ),
         )?;
         
-        let rows = stmt.query_map(params![canister_type.to_string()], |row| Self::from_row(row))?;
+        let rows = stmt.query_map(params![canister_type.to_string()], Self::from_row)?;
         
         let mut canisters = Vec::new();
         for canister in rows {
@@ -172,12 +182,95 @@ impl Canister {
         Ok(canisters)
     }
 
-    pub fn delete(conn: &Connection, canister_id: &str) -> Result<bool> {
+    /// Canisters registered by a principal, oldest first
+    pub fn find_by_principal(conn: &Connection, principal: &str) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {} FROM canisters WHERE principal = ?1 AND deleted_at IS NULL ORDER BY created_at ASC",
+                CANISTER_COLUMNS
+            ),
+        )?;
+        
+        let rows = stmt.query_map(params![principal], Self::from_row)?;
+        
+        let mut canisters = Vec::new();
+        for canister in rows {
+            canisters.push(canister?);
+        }
+        
+        Ok(canisters)
+    }
+
+    /// Find a canister whether or not it is in the trash
+    pub fn find_any_by_canister_id(conn: &Connection, canister_id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM canisters WHERE canister_id = ?1", CANISTER_COLUMNS),
+        )?;
+        
+        let mut rows = stmt.query(params![canister_id])?;
+        
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Find all canisters in the trash, most recently deleted first
+    pub fn find_deleted(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {} FROM canisters WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+                CANISTER_COLUMNS
+            ),
+        )?;
+        
+        let rows = stmt.query_map([], Self::from_row)?;
+        
+        let mut canisters = Vec::new();
+        for canister in rows {
+            canisters.push(canister?);
+        }
+        
+        Ok(canisters)
+    }
+
+    /// Move a canister to the trash, hiding it and its token, miner and mining data
+    pub fn soft_delete(conn: &Connection, canister_id: &str) -> Result<bool> {
         let rows_affected = conn.execute(
+            "UPDATE canisters SET deleted_at = ?2 WHERE canister_id = ?1 AND deleted_at IS NULL",
+            params![canister_id, Utc::now().timestamp()],
+        )?;
+        
+        Ok(rows_affected > 0)
+    }
+
+    /// Take a canister out of the trash
+    pub fn restore(conn: &Connection, canister_id: &str) -> Result<bool> {
+        let rows_affected = conn.execute(
+            "UPDATE canisters SET deleted_at = NULL WHERE canister_id = ?1 AND deleted_at IS NOT NULL",
+            params![canister_id],
+        )?;
+        
+        Ok(rows_affected > 0)
+    }
+
+    /// Permanently delete a canister together with all data that references it
+    pub fn delete(conn: &Connection, canister_id: &str) -> Result<bool> {
+        let tx = conn.unchecked_transaction()?;
+        
+        for table in DEPENDENT_TABLES {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE canister_id = ?1", table),
+                params![canister_id],
+            )?;
+        }
+        let rows_affected = tx.execute(
             "DELETE FROM canisters WHERE canister_id = ?1",
             params![canister_id],
         )?;
         
+        tx.commit()?;
         Ok(rows_affected > 0)
     }
 } 
\ No newline at end of file
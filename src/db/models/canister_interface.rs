@@ -0,0 +1,58 @@
+use rusqlite::{params, Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+
+/// A canister's Candid service definition, read from its `candid:service` metadata
+/// when it was registered
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CanisterInterface {
+    pub canister_id: String,
+    pub candid_service: String,
+    pub last_updated: i64,
+}
+
+impl CanisterInterface {
+    pub fn new(canister_id: String, candid_service: String) -> Self {
+        Self {
+            canister_id,
+            candid_service,
+            last_updated: Utc::now().timestamp(),
+        }
+    }
+
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            canister_id: row.get("canister_id")?,
+            candid_service: row.get("candid_service")?,
+            last_updated: row.get("last_updated")?,
+        })
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT INTO canister_interfaces (canister_id, candid_service, last_updated)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(canister_id) DO UPDATE SET
+             candid_service = ?2,
+             last_updated = ?3",
+            params![self.canister_id, self.candid_service, self.last_updated],
+        )?;
+        Ok(())
+    }
+
+    pub fn find_by_canister_id(conn: &Connection, canister_id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT canister_id, candid_service, last_updated
+             FROM canister_interfaces
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
+        )?;
+
+        let mut rows = stmt.query(params![canister_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use rusqlite::{params, Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+
+/// ICRC-1 ledger details, read from the ledger's `icrc1_*` queries
+///
+/// `fee` and `total_supply` are candid `nat` values, which may not fit in 64 bits,
+/// so they are kept as decimal strings.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LedgerInfo {
+    pub canister_id: String,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub fee: String,
+    pub total_supply: String,
+    pub logo: Option<String>,
+    /// Comma-separated names from `icrc1_supported_standards`, e.g. `ICRC-1,ICRC-2`
+    pub supported_standards: String,
+    /// `icrc1_metadata` as a JSON object
+    pub metadata: String,
+    pub last_updated: i64,
+}
+
+impl LedgerInfo {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        canister_id: String,
+        name: String,
+        symbol: String,
+        decimals: u8,
+        fee: String,
+        total_supply: String,
+        logo: Option<String>,
+        supported_standards: String,
+        metadata: String,
+    ) -> Self {
+        Self {
+            canister_id,
+            name,
+            symbol,
+            decimals,
+            fee,
+            total_supply,
+            logo,
+            supported_standards,
+            metadata,
+            last_updated: Utc::now().timestamp(),
+        }
+    }
+
+    /// Whether the ledger lists `standard` (e.g. `ICRC-3`) among its supported standards
+    pub fn supports(&self, standard: &str) -> bool {
+        self.supported_standards.split(',').any(|s| s.trim().eq_ignore_ascii_case(standard))
+    }
+
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            canister_id: row.get("canister_id")?,
+            name: row.get("name")?,
+            symbol: row.get("symbol")?,
+            decimals: row.get("decimals")?,
+            fee: row.get("fee")?,
+            total_supply: row.get("total_supply")?,
+            logo: row.get("logo")?,
+            supported_standards: row.get("supported_standards")?,
+            metadata: row.get("metadata")?,
+            last_updated: row.get("last_updated")?,
+        })
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT INTO ledger_info (
+                canister_id, name, symbol, decimals, fee, total_supply, logo, supported_standards, metadata, last_updated
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(canister_id) DO UPDATE SET
+             name = ?2,
+             symbol = ?3,
+             decimals = ?4,
+             fee = ?5,
+             total_supply = ?6,
+             logo = ?7,
+             supported_standards = ?8,
+             metadata = ?9,
+             last_updated = ?10",
+            params![
+                self.canister_id,
+                self.name,
+                self.symbol,
+                self.decimals,
+                self.fee,
+                self.total_supply,
+                self.logo,
+                self.supported_standards,
+                self.metadata,
+                self.last_updated,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn find_by_canister_id(conn: &Connection, canister_id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT canister_id, name, symbol, decimals, fee, total_supply, logo, supported_standards, metadata, last_updated
+             FROM ledger_info
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
+        )?;
+
+        let mut rows = stmt.query(params![canister_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn find_all(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT l.canister_id, l.name, l.symbol, l.decimals, l.fee, l.total_supply, l.logo,
+             l.supported_standards, l.metadata, l.last_updated
+             FROM ledger_info l
+             JOIN canisters c ON l.canister_id = c.canister_id
+             WHERE c.deleted_at IS NULL
+             ORDER BY l.last_updated DESC",
+        )?;
+
+        let rows = stmt.query_map([], Self::from_row)?;
+
+        let mut ledgers = Vec::new();
+        for ledger in rows {
+            ledgers.push(ledger?);
+        }
+
+        Ok(ledgers)
+    }
+
+    pub fn delete(conn: &Connection, canister_id: &str) -> Result<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM ledger_info WHERE canister_id = ?1",
+            params![canister_id],
+        )?;
+
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,291 @@
+use rusqlite::{params, Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Kind of an indexed ledger block
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Transfer,
+    Mint,
+    Burn,
+    /// An ICRC-2 approval, `amount` being the allowance; only its fee moves tokens
+    Approve,
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionKind::Transfer => write!(f, "transfer"),
+            TransactionKind::Mint => write!(f, "mint"),
+            TransactionKind::Burn => write!(f, "burn"),
+            TransactionKind::Approve => write!(f, "approve"),
+        }
+    }
+}
+
+impl TryFrom<String> for TransactionKind {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "transfer" => Ok(TransactionKind::Transfer),
+            "mint" => Ok(TransactionKind::Mint),
+            "burn" => Ok(TransactionKind::Burn),
+            "approve" => Ok(TransactionKind::Approve),
+            _ => Err(anyhow::anyhow!("Invalid transaction kind: {}", s)),
+        }
+    }
+}
+
+/// A transfer, mint, burn or approval read from a ledger's blocks
+///
+/// Accounts are stored as owner principal plus hex subaccount, `None` being the
+/// default subaccount. Amounts are decimal strings like in `LedgerInfo`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LedgerTransaction {
+    /// Ledger canister the block belongs to
+    pub canister_id: String,
+    pub block_index: u64,
+    pub kind: TransactionKind,
+    pub from_owner: Option<String>,
+    pub from_subaccount: Option<String>,
+    pub to_owner: Option<String>,
+    pub to_subaccount: Option<String>,
+    /// Owner of the account that spent an ICRC-2 allowance
+    pub spender: Option<String>,
+    pub amount: String,
+    pub fee: Option<String>,
+    /// Hex-encoded memo
+    pub memo: Option<String>,
+    /// Block timestamp in nanoseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// Balance of an account computed from the indexed blocks of a ledger
+///
+/// Only accounts with a non-zero balance are stored.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AccountBalance {
+    pub canister_id: String,
+    pub owner: String,
+    /// Hex subaccount, `None` for the default subaccount
+    pub subaccount: Option<String>,
+    /// Decimal string, like other ledger amounts
+    pub balance: String,
+}
+
+/// How far the indexer has read a ledger
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LedgerIndexState {
+    pub canister_id: String,
+    /// Index of the next block to read
+    pub next_block: u64,
+    /// Number of blocks in the ledger when it was last read
+    pub log_length: u64,
+    pub last_updated: i64,
+}
+
+// Columns selected for every transaction query
+const TRANSACTION_COLUMNS: &str = "canister_id, block_index, kind, from_owner, from_subaccount, to_owner, to_subaccount,
+    spender, amount, fee, memo, timestamp";
+
+// Matches transactions sent from or to the account in ?2 (owner) and ?3 (subaccount)
+const ACCOUNT_FILTER: &str = "canister_id = ?1
+    AND ((from_owner = ?2 AND from_subaccount IS ?3) OR (to_owner = ?2 AND to_subaccount IS ?3))
+    AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)";
+
+impl LedgerTransaction {
+    pub fn from_row(row: &Row) -> Result<Self> {
+        let kind_str: String = row.get("kind")?;
+        let kind = TransactionKind::try_from(kind_str)
+            .map_err(|_e| rusqlite::Error::InvalidColumnType(2, "Invalid transaction kind".to_string(), rusqlite::types::Type::Text))?;
+
+        Ok(Self {
+            canister_id: row.get("canister_id")?,
+            block_index: row.get("block_index")?,
+            kind,
+            from_owner: row.get("from_owner")?,
+            from_subaccount: row.get("from_subaccount")?,
+            to_owner: row.get("to_owner")?,
+            to_subaccount: row.get("to_subaccount")?,
+            spender: row.get("spender")?,
+            amount: row.get("amount")?,
+            fee: row.get("fee")?,
+            memo: row.get("memo")?,
+            timestamp: row.get("timestamp")?,
+        })
+    }
+
+    /// Insert the transaction, blocks never change so an already indexed block is left as is
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT OR IGNORE INTO ledger_transactions (
+                canister_id, block_index, kind, from_owner, from_subaccount, to_owner, to_subaccount,
+                spender, amount, fee, memo, timestamp
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                self.canister_id,
+                self.block_index,
+                self.kind.to_string(),
+                self.from_owner,
+                self.from_subaccount,
+                self.to_owner,
+                self.to_subaccount,
+                self.spender,
+                self.amount,
+                self.fee,
+                self.memo,
+                self.timestamp,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Transactions of an account on a ledger, newest first
+    pub fn find_by_account(
+        conn: &Connection,
+        canister_id: &str,
+        owner: &str,
+        subaccount: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM ledger_transactions WHERE {} ORDER BY block_index DESC LIMIT ?4 OFFSET ?5",
+            TRANSACTION_COLUMNS, ACCOUNT_FILTER
+        ))?;
+
+        let rows = stmt.query_map(params![canister_id, owner, subaccount, limit, offset], Self::from_row)?;
+
+        let mut transactions = Vec::new();
+        for transaction in rows {
+            transactions.push(transaction?);
+        }
+
+        Ok(transactions)
+    }
+
+    pub fn count_by_account(conn: &Connection, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<i64> {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM ledger_transactions WHERE {}", ACCOUNT_FILTER),
+            params![canister_id, owner, subaccount],
+            |row| row.get(0),
+        )
+    }
+}
+
+impl LedgerIndexState {
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            canister_id: row.get("canister_id")?,
+            next_block: row.get("next_block")?,
+            log_length: row.get("log_length")?,
+            last_updated: row.get("last_updated")?,
+        })
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT INTO ledger_index_state (canister_id, next_block, log_length, last_updated)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(canister_id) DO UPDATE SET
+             next_block = ?2,
+             log_length = ?3,
+             last_updated = ?4",
+            params![self.canister_id, self.next_block, self.log_length, self.last_updated],
+        )?;
+        Ok(())
+    }
+
+    pub fn find_by_canister_id(conn: &Connection, canister_id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT canister_id, next_block, log_length, last_updated FROM ledger_index_state WHERE canister_id = ?1",
+        )?;
+
+        let mut rows = stmt.query(params![canister_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl AccountBalance {
+    pub fn from_row(row: &Row) -> Result<Self> {
+        // The default subaccount is stored as an empty string, it is part of the primary key
+        let subaccount: String = row.get("subaccount")?;
+        Ok(Self {
+            canister_id: row.get("canister_id")?,
+            owner: row.get("owner")?,
+            subaccount: Some(subaccount).filter(|s| !s.is_empty()),
+            balance: row.get("balance")?,
+        })
+    }
+
+    /// Store the balance, removing the account once it is empty
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        let subaccount = self.subaccount.as_deref().unwrap_or("");
+        if self.balance == "0" {
+            conn.execute(
+                "DELETE FROM ledger_balances WHERE canister_id = ?1 AND owner = ?2 AND subaccount = ?3",
+                params![self.canister_id, self.owner, subaccount],
+            )?;
+        } else {
+            conn.execute(
+                "INSERT INTO ledger_balances (canister_id, owner, subaccount, balance)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(canister_id, owner, subaccount) DO UPDATE SET
+                 balance = ?4",
+                params![self.canister_id, self.owner, subaccount, self.balance],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn find(conn: &Connection, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT canister_id, owner, subaccount, balance FROM ledger_balances
+             WHERE canister_id = ?1 AND owner = ?2 AND subaccount = ?3",
+        )?;
+
+        let mut rows = stmt.query(params![canister_id, owner, subaccount.unwrap_or("")])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Largest balances of a ledger, decimal strings are ordered by length first
+    pub fn find_top(conn: &Connection, canister_id: &str, limit: i64) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT canister_id, owner, subaccount, balance FROM ledger_balances
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)
+             ORDER BY LENGTH(balance) DESC, balance DESC
+             LIMIT ?2",
+        )?;
+
+        let rows = stmt.query_map(params![canister_id, limit], Self::from_row)?;
+
+        let mut balances = Vec::new();
+        for balance in rows {
+            balances.push(balance?);
+        }
+
+        Ok(balances)
+    }
+
+    pub fn count(conn: &Connection, canister_id: &str) -> Result<i64> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM ledger_balances
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
+            params![canister_id],
+            |row| row.get(0),
+        )
+    }
+}
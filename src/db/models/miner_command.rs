@@ -0,0 +1,213 @@
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::Utc;
+use std::fmt;
+
+use crate::db::models::admin::Admin;
+
+/// A control operation sent to a miner with an update call
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MinerCommandKind {
+    StartMining,
+    StopMining,
+    SetToken,
+    SetSpeed,
+}
+
+impl MinerCommandKind {
+    /// Miner method the command calls
+    pub fn method(&self) -> &'static str {
+        match self {
+            MinerCommandKind::StartMining => "start_mining",
+            MinerCommandKind::StopMining => "stop_mining",
+            MinerCommandKind::SetToken => "set_current_token",
+            MinerCommandKind::SetSpeed => "set_speed_percentage",
+        }
+    }
+}
+
+impl fmt::Display for MinerCommandKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            MinerCommandKind::StartMining => "start_mining",
+            MinerCommandKind::StopMining => "stop_mining",
+            MinerCommandKind::SetToken => "set_token",
+            MinerCommandKind::SetSpeed => "set_speed",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+impl TryFrom<String> for MinerCommandKind {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "start_mining" => Ok(MinerCommandKind::StartMining),
+            "stop_mining" => Ok(MinerCommandKind::StopMining),
+            "set_token" => Ok(MinerCommandKind::SetToken),
+            "set_speed" => Ok(MinerCommandKind::SetSpeed),
+            _ => Err(anyhow::anyhow!("Invalid miner command: {}", s)),
+        }
+    }
+}
+
+/// Progress of a miner command
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandStatus {
+    /// Sent, no reply yet
+    Pending,
+    Succeeded,
+    /// Rejected by the canister, or the call failed
+    Failed,
+}
+
+impl fmt::Display for CommandStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            CommandStatus::Pending => "pending",
+            CommandStatus::Succeeded => "succeeded",
+            CommandStatus::Failed => "failed",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl TryFrom<String> for CommandStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "pending" => Ok(CommandStatus::Pending),
+            "succeeded" => Ok(CommandStatus::Succeeded),
+            "failed" => Ok(CommandStatus::Failed),
+            _ => Err(anyhow::anyhow!("Invalid command status: {}", s)),
+        }
+    }
+}
+
+/// A command an admin sent to a miner, with its outcome
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MinerCommand {
+    pub id: String,
+    pub canister_id: String,
+    pub command: MinerCommandKind,
+    /// Arguments of the update call as JSON
+    pub arguments: Value,
+    pub status: CommandStatus,
+    /// The canister's reply as JSON
+    pub reply: Option<Value>,
+    pub error: Option<String>,
+    pub admin_id: String,
+    pub admin_name: String,
+    pub created_at: i64,
+    pub completed_at: Option<i64>,
+}
+
+impl MinerCommand {
+    pub fn new(canister_id: &str, command: MinerCommandKind, arguments: Value, admin: &Admin) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            canister_id: canister_id.to_string(),
+            command,
+            arguments,
+            status: CommandStatus::Pending,
+            reply: None,
+            error: None,
+            admin_id: admin.id.clone(),
+            admin_name: admin.username.clone(),
+            created_at: Utc::now().timestamp(),
+            completed_at: None,
+        }
+    }
+
+    pub fn succeed(&mut self, reply: Value) {
+        self.status = CommandStatus::Succeeded;
+        self.reply = Some(reply);
+        self.completed_at = Some(Utc::now().timestamp());
+    }
+
+    pub fn fail(&mut self, reply: Option<Value>, error: String) {
+        self.status = CommandStatus::Failed;
+        self.reply = reply;
+        self.error = Some(error);
+        self.completed_at = Some(Utc::now().timestamp());
+    }
+
+    pub fn from_row(row: &Row) -> Result<Self> {
+        let command_str: String = row.get("command")?;
+        let command = MinerCommandKind::try_from(command_str)
+            .map_err(|_e| rusqlite::Error::InvalidColumnType(2, "Invalid miner command".to_string(), rusqlite::types::Type::Text))?;
+        let status_str: String = row.get("status")?;
+        let status = CommandStatus::try_from(status_str)
+            .map_err(|_e| rusqlite::Error::InvalidColumnType(4, "Invalid command status".to_string(), rusqlite::types::Type::Text))?;
+        let arguments: String = row.get("arguments")?;
+        let reply: Option<String> = row.get("reply")?;
+        Ok(Self {
+            id: row.get("id")?,
+            canister_id: row.get("canister_id")?,
+            command,
+            arguments: serde_json::from_str(&arguments).unwrap_or(Value::Null),
+            status,
+            reply: reply.and_then(|json| serde_json::from_str(&json).ok()),
+            error: row.get("error")?,
+            admin_id: row.get("admin_id")?,
+            admin_name: row.get("admin_name")?,
+            created_at: row.get("created_at")?,
+            completed_at: row.get("completed_at")?,
+        })
+    }
+
+    /// Insert the command or update its outcome
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT INTO miner_commands (
+                id, canister_id, command, arguments, status, reply, error, admin_id, admin_name, created_at, completed_at
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(id) DO UPDATE SET
+             status = ?5,
+             reply = ?6,
+             error = ?7,
+             completed_at = ?11",
+            params![
+                self.id,
+                self.canister_id,
+                self.command.to_string(),
+                self.arguments.to_string(),
+                self.status.to_string(),
+                self.reply.as_ref().map(|reply| reply.to_string()),
+                self.error,
+                self.admin_id,
+                self.admin_name,
+                self.created_at,
+                self.completed_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Commands sent to a miner, newest first
+    pub fn find_by_canister_id(conn: &Connection, canister_id: &str, limit: i64, offset: i64) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, canister_id, command, arguments, status, reply, error, admin_id, admin_name, created_at, completed_at
+             FROM miner_commands
+             WHERE canister_id = ?1
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ?2 OFFSET ?3",
+        )?;
+
+        let rows = stmt.query_map(params![canister_id, limit, offset], Self::from_row)?;
+
+        let mut commands = Vec::new();
+        for command in rows {
+            commands.push(command?);
+        }
+
+        Ok(commands)
+    }
+}
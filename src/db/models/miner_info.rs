@@ -113,7 +113,7 @@ impl MinerInfo {
         let mut stmt = conn.prepare(
             "SELECT canister_id, miner_type, is_mining, current_token, speed_percentage, chunks_per_refresh, last_updated, raw_info
              FROM miner_info
-             WHERE canister_id = ?1",
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
         )?;
         
         let mut rows = stmt.query(params![canister_id])?;
@@ -130,6 +130,7 @@ impl MinerInfo {
             "SELECT m.canister_id, m.miner_type, m.is_mining, m.current_token, m.speed_percentage, m.chunks_per_refresh, m.last_updated, m.raw_info
              FROM miner_info m
              JOIN canisters c ON m.canister_id = c.canister_id
+             WHERE c.deleted_at IS NULL
              ORDER BY m.last_updated DESC",
         )?;
         
@@ -148,7 +149,7 @@ impl MinerInfo {
             "SELECT m.canister_id, m.miner_type, m.is_mining, m.current_token, m.speed_percentage, m.chunks_per_refresh, m.last_updated, m.raw_info
              FROM miner_info m
              JOIN canisters c ON m.canister_id = c.canister_id
-             WHERE m.current_token = ?1
+             WHERE m.current_token = ?1 AND c.deleted_at IS NULL
              ORDER BY m.last_updated DESC",
         )?;
         
@@ -79,7 +79,7 @@ impl MiningStats {
         let mut stmt = conn.prepare(
             "SELECT canister_id, total_hashes, blocks_mined, chunks_since_refresh, total_rewards, last_hash_rate, start_time, last_updated
              FROM mining_stats
-             WHERE canister_id = ?1",
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
         )?;
         
         let mut rows = stmt.query(params![canister_id])?;
@@ -96,6 +96,7 @@ impl MiningStats {
             "SELECT ms.canister_id, ms.total_hashes, ms.blocks_mined, ms.chunks_since_refresh, ms.total_rewards, ms.last_hash_rate, ms.start_time, ms.last_updated
              FROM mining_stats ms
              JOIN canisters c ON ms.canister_id = c.canister_id
+             WHERE c.deleted_at IS NULL
              ORDER BY ms.last_updated DESC",
         )?;
         
@@ -109,6 +110,21 @@ impl MiningStats {
         Ok(stats)
     }
 
+    /// Sum of the last reported hash rates of all active miners working on a token
+    pub fn total_hash_rate_for_token(conn: &Connection, token_canister_id: &str) -> Result<f64> {
+        let total: Option<f64> = conn.query_row(
+            "SELECT SUM(ms.last_hash_rate)
+             FROM mining_stats ms
+             JOIN miner_info m ON ms.canister_id = m.canister_id
+             JOIN canisters c ON ms.canister_id = c.canister_id
+             WHERE m.current_token = ?1 AND m.is_mining = 1 AND c.deleted_at IS NULL",
+            params![token_canister_id],
+            |row| row.get(0),
+        )?;
+        
+        Ok(total.unwrap_or(0.0))
+    }
+
     pub fn delete(conn: &Connection, canister_id: &str) -> Result<bool> {
         let rows_affected = conn.execute(
             "DELETE FROM mining_stats WHERE canister_id = ?1",
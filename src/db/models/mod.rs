@@ -1,8 +1,14 @@
 pub mod canister;
+pub mod canister_interface;
 pub mod token_info;
+pub mod ledger_info;
+pub mod ledger_transaction;
 pub mod miner_info;
+pub mod miner_command;
 pub mod mining_stats;
 pub mod verified_module_hash;
 pub mod admin;
+pub mod admin_session;
+pub mod audit_log;
 
 // Export the model types from submodules as needed 
\ No newline at end of file
@@ -171,7 +171,7 @@ impl TokenInfo {
              average_block_time, formatted_block_time, block_time_rating, circulating_supply,
              mining_progress_percentage, current_block_reward, formatted_block_reward, current_block_height
              FROM token_info
-             WHERE canister_id = ?1",
+             WHERE canister_id = ?1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
         )?;
         
         let mut rows = stmt.query(params![canister_id])?;
@@ -190,6 +190,7 @@ impl TokenInfo {
              t.mining_progress_percentage, t.current_block_reward, t.formatted_block_reward, t.current_block_height
              FROM token_info t
              JOIN canisters c ON t.canister_id = c.canister_id
+             WHERE c.deleted_at IS NULL
              ORDER BY t.last_updated DESC",
         )?;
         
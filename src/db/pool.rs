@@ -26,8 +26,19 @@ pub fn init_pool(db_path: &Path) -> Result<DbPool, r2d2::Error> {
         }
     }
     
-    // Create the connection manager
-    let manager = SqliteConnectionManager::file(db_path)
+    build_pool(SqliteConnectionManager::file(db_path), 10)
+}
+
+/// Initialize a pool over an in-memory database
+/// The pool holds a single connection, the database lives as long as that connection
+pub fn init_memory_pool() -> Result<DbPool, r2d2::Error> {
+    info!("Initializing in-memory database connection pool");
+    build_pool(SqliteConnectionManager::memory(), 1)
+}
+
+// Set up every new connection and create the pool
+fn build_pool(manager: SqliteConnectionManager, max_size: u32) -> Result<DbPool, r2d2::Error> {
+    let manager = manager
         .with_init(|conn| {
             // Enable foreign keys
             conn.execute_batch("PRAGMA foreign_keys = ON;")?;
@@ -38,9 +49,9 @@ pub fn init_pool(db_path: &Path) -> Result<DbPool, r2d2::Error> {
     
     // Create the connection pool
     let pool = Pool::builder()
-        .max_size(10) // Maximum number of connections in the pool
+        .max_size(max_size) // Maximum number of connections in the pool
         .build(manager)?;
     
     info!("Database connection pool initialized successfully");
     Ok(pool)
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,299 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::db::export::{RegistryExport, RegistryTable};
+use crate::db::models::admin::Admin;
+use crate::db::models::admin_session::AdminSession;
+use crate::db::models::audit_log::{AuditLogEntry, AuditLogFilter};
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::canister_interface::CanisterInterface;
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::ledger_transaction::{AccountBalance, LedgerIndexState, LedgerTransaction};
+use crate::db::models::miner_command::MinerCommand;
+use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::db::models::verified_module_hash::VerifiedModuleHash;
+
+pub mod sqlite;
+pub mod postgres;
+
+pub use self::postgres::PostgresRepository;
+pub use self::sqlite::SqliteRepository;
+
+// Database used when DATABASE_URL is not set
+const DEFAULT_SQLITE_PATH: &str = "data/registry.db";
+// Default number of pooled connections
+const DEFAULT_POOL_SIZE: usize = 10;
+
+/// Which database backend to use, read from `DATABASE_URL`
+///
+/// `postgres://` and `postgresql://` URLs select PostgreSQL and `memory://` an empty
+/// in-memory database, anything else is taken as the path of a SQLite database file
+/// (`sqlite://` prefix optional).
+#[derive(Debug, Clone)]
+pub enum DatabaseConfig {
+    Sqlite { path: PathBuf },
+    Postgres { url: String, pool_size: usize },
+    Memory,
+}
+
+impl DatabaseConfig {
+    pub fn from_env() -> Self {
+        let pool_size = env::var("DATABASE_POOL_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|size| *size > 0)
+            .unwrap_or(DEFAULT_POOL_SIZE);
+        match env::var("DATABASE_URL") {
+            Ok(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+                DatabaseConfig::Postgres { url, pool_size }
+            }
+            Ok(url) if url == "memory://" => DatabaseConfig::Memory,
+            Ok(url) if !url.is_empty() => DatabaseConfig::Sqlite {
+                path: PathBuf::from(url.strip_prefix("sqlite://").unwrap_or(&url)),
+            },
+            _ => DatabaseConfig::Sqlite { path: PathBuf::from(DEFAULT_SQLITE_PATH) },
+        }
+    }
+}
+
+/// Open the configured database, creating its schema if needed
+pub async fn connect(config: &DatabaseConfig) -> Result<Arc<dyn Repository>> {
+    match config {
+        DatabaseConfig::Sqlite { path } => {
+            info!("Using SQLite database at {:?}", path);
+            Ok(Arc::new(SqliteRepository::open(path)?))
+        }
+        DatabaseConfig::Postgres { url, pool_size } => {
+            info!("Using PostgreSQL database");
+            Ok(Arc::new(PostgresRepository::connect(url, *pool_size).await?))
+        }
+        DatabaseConfig::Memory => {
+            info!("Using in-memory database, data is lost on shutdown");
+            Ok(Arc::new(SqliteRepository::open_in_memory()?))
+        }
+    }
+}
+
+/// Connection counts of the pool behind a repository
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStatus {
+    pub connections: usize,
+    pub idle_connections: usize,
+    pub max_size: usize,
+}
+
+/// Aggregate figures over the canisters that are not in the trash
+#[derive(Debug, Clone, Default)]
+pub struct RegistryStats {
+    pub canisters_by_type: HashMap<String, i64>,
+    pub tokens: i64,
+    pub ledgers: i64,
+    pub miners: i64,
+    pub active_miners: i64,
+    /// Sum of the last hash rates of all miners that are mining
+    pub network_hash_rate: f64,
+    pub blocks_mined: i64,
+    pub total_rewards: i64,
+}
+
+impl RegistryStats {
+    pub fn canisters(&self) -> i64 {
+        self.canisters_by_type.values().sum()
+    }
+}
+
+/// Keys of the rows already stored in each registry table
+pub type RegistryKeys = HashMap<RegistryTable, HashSet<String>>;
+
+/// Storage of the registry and admin data
+///
+/// Implemented for SQLite (file or in-memory) and PostgreSQL; handlers get it as `web::Data<dyn Repository>`.
+pub trait Repository: Send + Sync {
+    fn canisters(&self) -> &dyn CanisterRepository;
+    fn interfaces(&self) -> &dyn CanisterInterfaceRepository;
+    fn tokens(&self) -> &dyn TokenInfoRepository;
+    fn ledgers(&self) -> &dyn LedgerInfoRepository;
+    fn ledger_transactions(&self) -> &dyn LedgerTransactionRepository;
+    fn miners(&self) -> &dyn MinerInfoRepository;
+    fn mining_stats(&self) -> &dyn MiningStatsRepository;
+    fn miner_commands(&self) -> &dyn MinerCommandRepository;
+    fn module_hashes(&self) -> &dyn ModuleHashRepository;
+    fn admins(&self) -> &dyn AdminRepository;
+    fn sessions(&self) -> &dyn AdminSessionRepository;
+    fn audit_log(&self) -> &dyn AuditLogRepository;
+    fn registry(&self) -> &dyn RegistryRepository;
+
+    /// Name of the backend, e.g. for the status endpoints
+    fn backend(&self) -> &'static str;
+
+    /// Path of the database file, for backends that keep the database in a local file
+    fn file_path(&self) -> Option<&Path>;
+
+    fn pool_status(&self) -> PoolStatus;
+}
+
+#[async_trait]
+pub trait CanisterRepository: Send + Sync {
+    /// Insert a canister or update the one with the same canister ID
+    async fn save(&self, canister: &Canister) -> Result<()>;
+    /// Save several canisters in one transaction
+    async fn save_all(&self, canisters: &[Canister]) -> Result<()>;
+    async fn find_by_id(&self, id: &str) -> Result<Option<Canister>>;
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<Canister>>;
+    /// Find a canister whether or not it is in the trash
+    async fn find_any_by_canister_id(&self, canister_id: &str) -> Result<Option<Canister>>;
+    async fn find_all(&self) -> Result<Vec<Canister>>;
+    async fn find_by_type(&self, canister_type: &CanisterType) -> Result<Vec<Canister>>;
+    /// Canisters registered by a principal, oldest first
+    async fn find_by_principal(&self, principal: &str) -> Result<Vec<Canister>>;
+    /// Canisters in the trash, most recently deleted first
+    async fn find_deleted(&self) -> Result<Vec<Canister>>;
+    async fn soft_delete(&self, canister_id: &str) -> Result<bool>;
+    async fn restore(&self, canister_id: &str) -> Result<bool>;
+    /// Permanently delete a canister together with all data that references it
+    async fn delete(&self, canister_id: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait CanisterInterfaceRepository: Send + Sync {
+    async fn save(&self, interface: &CanisterInterface) -> Result<()>;
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<CanisterInterface>>;
+}
+
+#[async_trait]
+pub trait TokenInfoRepository: Send + Sync {
+    async fn save(&self, token: &TokenInfo) -> Result<()>;
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<TokenInfo>>;
+    async fn find_all(&self) -> Result<Vec<TokenInfo>>;
+    async fn delete(&self, canister_id: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait LedgerInfoRepository: Send + Sync {
+    async fn save(&self, ledger: &LedgerInfo) -> Result<()>;
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<LedgerInfo>>;
+    async fn find_all(&self) -> Result<Vec<LedgerInfo>>;
+    async fn delete(&self, canister_id: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait LedgerTransactionRepository: Send + Sync {
+    /// Store a page of indexed transactions with the balances they changed, and move the
+    /// ledger's checkpoint, in one transaction
+    async fn save_page(
+        &self,
+        transactions: &[LedgerTransaction],
+        balances: &[AccountBalance],
+        state: &LedgerIndexState,
+    ) -> Result<()>;
+    async fn index_state(&self, canister_id: &str) -> Result<Option<LedgerIndexState>>;
+    /// Transactions sent from or to an account, newest first
+    async fn find_by_account(
+        &self,
+        canister_id: &str,
+        owner: &str,
+        subaccount: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LedgerTransaction>>;
+    async fn count_by_account(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<i64>;
+    async fn find_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<Option<AccountBalance>>;
+    /// Largest balances of a ledger, largest first
+    async fn top_holders(&self, canister_id: &str, limit: i64) -> Result<Vec<AccountBalance>>;
+    /// Number of accounts with a non-zero balance
+    async fn count_holders(&self, canister_id: &str) -> Result<i64>;
+}
+
+#[async_trait]
+pub trait MinerInfoRepository: Send + Sync {
+    async fn save(&self, miner: &MinerInfo) -> Result<()>;
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<MinerInfo>>;
+    async fn find_all(&self) -> Result<Vec<MinerInfo>>;
+    async fn find_by_token(&self, token_canister_id: &str) -> Result<Vec<MinerInfo>>;
+    async fn delete(&self, canister_id: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait MiningStatsRepository: Send + Sync {
+    async fn save(&self, stats: &MiningStats) -> Result<()>;
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<MiningStats>>;
+    async fn find_all(&self) -> Result<Vec<MiningStats>>;
+    /// Sum of the last reported hash rates of all active miners working on a token
+    async fn total_hash_rate_for_token(&self, token_canister_id: &str) -> Result<f64>;
+    async fn delete(&self, canister_id: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait MinerCommandRepository: Send + Sync {
+    /// Insert a command or update its outcome
+    async fn save(&self, command: &MinerCommand) -> Result<()>;
+    /// Commands sent to a miner, newest first
+    async fn find_by_canister_id(&self, canister_id: &str, limit: i64, offset: i64) -> Result<Vec<MinerCommand>>;
+}
+
+#[async_trait]
+pub trait ModuleHashRepository: Send + Sync {
+    async fn save(&self, hash: &VerifiedModuleHash) -> Result<()>;
+    async fn find_all(&self) -> Result<Vec<VerifiedModuleHash>>;
+    async fn find_by_hash(&self, hash: &str) -> Result<Option<VerifiedModuleHash>>;
+    async fn is_hash_verified(&self, hash: &str, canister_type: &str) -> Result<bool>;
+    async fn delete(&self, hash: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait AdminRepository: Send + Sync {
+    /// Insert an admin or update the one with the same username
+    async fn save(&self, admin: &Admin) -> Result<()>;
+    /// Insert an admin only if there are no admins yet, returns whether it was inserted
+    async fn insert_if_none_exists(&self, admin: &Admin) -> Result<bool>;
+    async fn find_by_id(&self, id: &str) -> Result<Option<Admin>>;
+    async fn find_by_username(&self, username: &str) -> Result<Option<Admin>>;
+    /// Find an active admin by its current API key, or by its previous key while the grace period lasts
+    async fn find_by_api_key(&self, api_key: &str) -> Result<Option<Admin>>;
+    async fn find_all(&self) -> Result<Vec<Admin>>;
+    async fn count_active_superadmins(&self) -> Result<i64>;
+    /// Record that the admin just authenticated
+    async fn touch_last_used(&self, id: &str) -> Result<()>;
+    /// Count a failed login, locking the account once `max_attempts` is reached
+    async fn record_login_failure(&self, id: &str, max_attempts: i64, lockout_secs: i64) -> Result<()>;
+    /// Clear the failed login counter after a successful login
+    async fn reset_login_failures(&self, id: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait AdminSessionRepository: Send + Sync {
+    async fn save(&self, session: &AdminSession) -> Result<()>;
+    async fn find_by_id(&self, id: &str) -> Result<Option<AdminSession>>;
+    async fn revoke(&self, id: &str) -> Result<bool>;
+    /// Revoke every open session of an admin, e.g. when the account is deactivated
+    async fn revoke_all_for_admin(&self, admin_id: &str) -> Result<usize>;
+    /// Remove sessions that can no longer be used
+    async fn delete_expired(&self) -> Result<usize>;
+}
+
+#[async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    /// Store an entry, reducing before/after to the fields that changed
+    async fn save(&self, entry: &AuditLogEntry) -> Result<()>;
+    /// Find entries matching a filter, newest first
+    async fn find(&self, filter: &AuditLogFilter, limit: i64, offset: i64) -> Result<Vec<AuditLogEntry>>;
+    async fn count(&self, filter: &AuditLogFilter) -> Result<i64>;
+}
+
+#[async_trait]
+pub trait RegistryRepository: Send + Sync {
+    /// Check that the database answers queries
+    async fn ping(&self) -> Result<()>;
+    async fn stats(&self) -> Result<RegistryStats>;
+    /// Keys of all stored rows of every registry table, including canisters in the trash
+    async fn keys(&self) -> Result<RegistryKeys>;
+    /// Upsert every row of an export in one transaction
+    async fn save(&self, rows: &RegistryExport) -> Result<()>;
+}
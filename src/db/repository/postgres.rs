@@ -0,0 +1,1589 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use deadpool_postgres::{Config, GenericClient, ManagerConfig, Pool, PoolConfig, RecyclingMethod, Runtime};
+use log::info;
+use std::collections::HashSet;
+use std::path::Path;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{NoTls, Row};
+
+use crate::db::export::{RegistryExport, RegistryTable};
+use crate::db::models::admin::{api_key_prefix, Admin, AdminRole};
+use crate::db::models::admin_session::AdminSession;
+use crate::db::models::audit_log::{AuditLogEntry, AuditLogFilter};
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::canister_interface::CanisterInterface;
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::ledger_transaction::{AccountBalance, LedgerIndexState, LedgerTransaction, TransactionKind};
+use crate::db::models::miner_command::{CommandStatus, MinerCommand, MinerCommandKind};
+use crate::db::models::miner_info::{MinerInfo, MinerType};
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::db::models::verified_module_hash::VerifiedModuleHash;
+use crate::db::repository::*;
+
+// Held while the schema is created so instances starting together do not race
+const SCHEMA_LOCK_ID: i64 = 0x7265_6769_7374_7279;
+
+// Same tables as the SQLite schema, with native boolean and 64-bit integer columns
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS canisters (
+    id TEXT PRIMARY KEY,
+    principal TEXT NOT NULL,
+    canister_id TEXT NOT NULL UNIQUE,
+    type TEXT NOT NULL,
+    module_hash TEXT,
+    created_at BIGINT NOT NULL,
+    last_updated BIGINT NOT NULL,
+    deleted_at BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS canister_interfaces (
+    canister_id TEXT PRIMARY KEY REFERENCES canisters (canister_id),
+    candid_service TEXT NOT NULL,
+    last_updated BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS token_info (
+    canister_id TEXT PRIMARY KEY REFERENCES canisters (canister_id),
+    name TEXT NOT NULL,
+    ticker TEXT NOT NULL,
+    decimals SMALLINT NOT NULL,
+    total_supply BIGINT NOT NULL,
+    transfer_fee BIGINT NOT NULL,
+    logo TEXT,
+    last_updated BIGINT NOT NULL,
+    raw_info TEXT NOT NULL,
+    average_block_time DOUBLE PRECISION,
+    formatted_block_time TEXT,
+    block_time_rating TEXT,
+    circulating_supply BIGINT NOT NULL DEFAULT 0,
+    mining_progress_percentage TEXT NOT NULL DEFAULT '0',
+    current_block_reward BIGINT NOT NULL DEFAULT 0,
+    formatted_block_reward TEXT NOT NULL DEFAULT '0',
+    current_block_height BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS ledger_info (
+    canister_id TEXT PRIMARY KEY REFERENCES canisters (canister_id),
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    decimals SMALLINT NOT NULL,
+    fee TEXT NOT NULL,
+    total_supply TEXT NOT NULL,
+    logo TEXT,
+    supported_standards TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    last_updated BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ledger_transactions (
+    canister_id TEXT NOT NULL REFERENCES canisters (canister_id),
+    block_index BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    from_owner TEXT,
+    from_subaccount TEXT,
+    to_owner TEXT,
+    to_subaccount TEXT,
+    spender TEXT,
+    amount TEXT NOT NULL,
+    fee TEXT,
+    memo TEXT,
+    timestamp BIGINT NOT NULL,
+    PRIMARY KEY (canister_id, block_index)
+);
+
+CREATE TABLE IF NOT EXISTS ledger_index_state (
+    canister_id TEXT PRIMARY KEY REFERENCES canisters (canister_id),
+    next_block BIGINT NOT NULL,
+    log_length BIGINT NOT NULL,
+    last_updated BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ledger_balances (
+    canister_id TEXT NOT NULL REFERENCES canisters (canister_id),
+    owner TEXT NOT NULL,
+    subaccount TEXT NOT NULL DEFAULT '',
+    balance TEXT NOT NULL,
+    PRIMARY KEY (canister_id, owner, subaccount)
+);
+
+CREATE TABLE IF NOT EXISTS miner_info (
+    canister_id TEXT PRIMARY KEY REFERENCES canisters (canister_id),
+    miner_type TEXT NOT NULL,
+    is_mining BOOLEAN NOT NULL,
+    current_token TEXT,
+    speed_percentage SMALLINT NOT NULL,
+    chunks_per_refresh BIGINT NOT NULL,
+    last_updated BIGINT NOT NULL,
+    raw_info TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS mining_stats (
+    canister_id TEXT PRIMARY KEY REFERENCES canisters (canister_id),
+    total_hashes BIGINT NOT NULL,
+    blocks_mined BIGINT NOT NULL,
+    chunks_since_refresh BIGINT NOT NULL,
+    total_rewards BIGINT NOT NULL,
+    last_hash_rate DOUBLE PRECISION NOT NULL,
+    start_time BIGINT NOT NULL,
+    last_updated BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS verified_module_hashes (
+    id TEXT PRIMARY KEY,
+    hash TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL,
+    canister_type TEXT NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at BIGINT NOT NULL,
+    last_updated BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS admins (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    api_key TEXT NOT NULL UNIQUE,
+    api_key_prefix TEXT NOT NULL,
+    previous_api_key TEXT,
+    previous_api_key_prefix TEXT,
+    previous_api_key_expires_at BIGINT,
+    role TEXT NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    last_used_at BIGINT,
+    failed_login_attempts BIGINT NOT NULL DEFAULT 0,
+    locked_until BIGINT,
+    created_at BIGINT NOT NULL,
+    last_updated BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS admin_sessions (
+    id TEXT PRIMARY KEY,
+    admin_id TEXT NOT NULL REFERENCES admins (id),
+    refresh_token_id TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    revoked_at BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    actor_type TEXT NOT NULL,
+    actor_id TEXT,
+    actor_name TEXT,
+    action TEXT NOT NULL,
+    target_type TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    before_state TEXT,
+    after_state TEXT,
+    ip_address TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS miner_commands (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    canister_id TEXT NOT NULL,
+    command TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reply TEXT,
+    error TEXT,
+    admin_id TEXT NOT NULL,
+    admin_name TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    completed_at BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS idx_canisters_type ON canisters (type);
+CREATE INDEX IF NOT EXISTS idx_canisters_principal ON canisters (principal);
+CREATE INDEX IF NOT EXISTS idx_canisters_deleted_at ON canisters (deleted_at);
+CREATE INDEX IF NOT EXISTS idx_ledger_transactions_from ON ledger_transactions (canister_id, from_owner);
+CREATE INDEX IF NOT EXISTS idx_ledger_transactions_to ON ledger_transactions (canister_id, to_owner);
+CREATE INDEX IF NOT EXISTS idx_miner_info_current_token ON miner_info (current_token);
+CREATE INDEX IF NOT EXISTS idx_verified_module_hashes_type ON verified_module_hashes (canister_type);
+CREATE INDEX IF NOT EXISTS idx_admin_sessions_admin ON admin_sessions (admin_id);
+CREATE INDEX IF NOT EXISTS idx_admins_api_key_prefix ON admins (api_key_prefix);
+CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log (target_type, target_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log (actor_id);
+CREATE INDEX IF NOT EXISTS idx_miner_commands_canister ON miner_commands (canister_id, created_at);
+
+INSERT INTO verified_module_hashes (id, hash, description, canister_type, is_active, created_at, last_updated)
+VALUES ('default-hash', '5471eb4e9e70f245d8db1a1673d43ab5ff9443c6d1588f5bdf052bdc7e88f0a5', 'Default verified token hash', 'token', TRUE,
+        EXTRACT(EPOCH FROM NOW())::BIGINT, EXTRACT(EPOCH FROM NOW())::BIGINT)
+ON CONFLICT DO NOTHING;
+";
+
+// Columns selected for every query, in the order of the struct fields
+const CANISTER_COLUMNS: &str = "id, principal, canister_id, type, module_hash, created_at, last_updated, deleted_at";
+const TOKEN_COLUMNS: &str = "t.canister_id, t.name, t.ticker, t.decimals, t.total_supply, t.transfer_fee, t.logo, t.last_updated, t.raw_info,
+    t.average_block_time, t.formatted_block_time, t.block_time_rating, t.circulating_supply,
+    t.mining_progress_percentage, t.current_block_reward, t.formatted_block_reward, t.current_block_height";
+const LEDGER_COLUMNS: &str = "l.canister_id, l.name, l.symbol, l.decimals, l.fee, l.total_supply, l.logo, l.supported_standards, l.metadata, l.last_updated";
+const TRANSACTION_COLUMNS: &str = "canister_id, block_index, kind, from_owner, from_subaccount, to_owner, to_subaccount,
+    spender, amount, fee, memo, timestamp";
+const MINER_COLUMNS: &str = "m.canister_id, m.miner_type, m.is_mining, m.current_token, m.speed_percentage, m.chunks_per_refresh, m.last_updated, m.raw_info";
+const STATS_COLUMNS: &str = "ms.canister_id, ms.total_hashes, ms.blocks_mined, ms.chunks_since_refresh, ms.total_rewards, ms.last_hash_rate, ms.start_time, ms.last_updated";
+const HASH_COLUMNS: &str = "id, hash, description, canister_type, is_active, created_at, last_updated";
+const ADMIN_COLUMNS: &str = "id, username, password_hash, api_key, api_key_prefix, previous_api_key,
+    previous_api_key_prefix, previous_api_key_expires_at, role, is_active, last_used_at, failed_login_attempts, locked_until, created_at, last_updated";
+const SESSION_COLUMNS: &str = "id, admin_id, refresh_token_id, created_at, expires_at, revoked_at";
+const COMMAND_COLUMNS: &str = "id, canister_id, command, arguments, status, reply, error, admin_id, admin_name,
+    created_at, completed_at";
+const AUDIT_COLUMNS: &str = "id, actor_type, actor_id, actor_name, action, target_type, target_id,
+    before_state, after_state, ip_address, created_at";
+
+// Restricts token, ledger, miner and stats queries to canisters that are not in the trash
+const NOT_DELETED: &str = "canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)";
+
+// Matches transactions sent from or to the account in $2 (owner) and $3 (subaccount)
+const ACCOUNT_FILTER: &str = "canister_id = $1
+    AND ((from_owner = $2 AND from_subaccount IS NOT DISTINCT FROM $3) OR (to_owner = $2 AND to_subaccount IS NOT DISTINCT FROM $3))";
+
+/// Repository backed by PostgreSQL, shared by every server instance behind the load balancer
+pub struct PostgresRepository {
+    pool: Pool,
+}
+
+impl PostgresRepository {
+    /// Connect to the database at `url` and create the schema if needed
+    pub async fn connect(url: &str, pool_size: usize) -> Result<Self> {
+        let mut config = Config::new();
+        config.url = Some(url.to_string());
+        config.manager = Some(ManagerConfig { recycling_method: RecyclingMethod::Fast });
+        config.pool = Some(PoolConfig::new(pool_size));
+        let pool = config
+            .create_pool(Some(Runtime::Tokio1), NoTls)
+            .context("Failed to create PostgreSQL connection pool")?;
+
+        let repo = Self { pool };
+        repo.init_schema().await?;
+        info!("PostgreSQL connection pool initialized successfully");
+        Ok(repo)
+    }
+
+    async fn init_schema(&self) -> Result<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&SCHEMA_LOCK_ID]).await?;
+        tx.batch_execute(SCHEMA).await.context("Failed to initialize PostgreSQL schema")?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn client(&self) -> Result<deadpool_postgres::Client> {
+        self.pool.get().await.context("Failed to get database connection")
+    }
+}
+
+impl Repository for PostgresRepository {
+    fn canisters(&self) -> &dyn CanisterRepository { self }
+    fn tokens(&self) -> &dyn TokenInfoRepository { self }
+    fn interfaces(&self) -> &dyn CanisterInterfaceRepository { self }
+    fn ledgers(&self) -> &dyn LedgerInfoRepository { self }
+    fn ledger_transactions(&self) -> &dyn LedgerTransactionRepository { self }
+    fn miners(&self) -> &dyn MinerInfoRepository { self }
+    fn mining_stats(&self) -> &dyn MiningStatsRepository { self }
+    fn miner_commands(&self) -> &dyn MinerCommandRepository { self }
+    fn module_hashes(&self) -> &dyn ModuleHashRepository { self }
+    fn admins(&self) -> &dyn AdminRepository { self }
+    fn sessions(&self) -> &dyn AdminSessionRepository { self }
+    fn audit_log(&self) -> &dyn AuditLogRepository { self }
+    fn registry(&self) -> &dyn RegistryRepository { self }
+
+    fn backend(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn file_path(&self) -> Option<&Path> {
+        None
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        let status = self.pool.status();
+        PoolStatus {
+            connections: status.size,
+            idle_connections: status.available,
+            max_size: status.max_size,
+        }
+    }
+}
+
+// Unsigned values are stored in BIGINT columns, which hold up to i64::MAX
+fn to_i64(value: u64, field: &str) -> Result<i64> {
+    i64::try_from(value).with_context(|| format!("{} is too large to store: {}", field, value))
+}
+
+fn canister_from_row(row: &Row) -> Result<Canister> {
+    Ok(Canister {
+        id: row.try_get("id")?,
+        principal: row.try_get("principal")?,
+        canister_id: row.try_get("canister_id")?,
+        canister_type: CanisterType::try_from(row.try_get::<_, String>("type")?)?,
+        module_hash: row.try_get("module_hash")?,
+        created_at: row.try_get("created_at")?,
+        last_updated: row.try_get("last_updated")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn token_from_row(row: &Row) -> Result<TokenInfo> {
+    Ok(TokenInfo {
+        canister_id: row.try_get("canister_id")?,
+        name: row.try_get("name")?,
+        ticker: row.try_get("ticker")?,
+        decimals: row.try_get::<_, i16>("decimals")? as u8,
+        total_supply: row.try_get::<_, i64>("total_supply")? as u64,
+        transfer_fee: row.try_get::<_, i64>("transfer_fee")? as u64,
+        logo: row.try_get("logo")?,
+        last_updated: row.try_get("last_updated")?,
+        raw_info: row.try_get("raw_info")?,
+        average_block_time: row.try_get("average_block_time")?,
+        formatted_block_time: row.try_get("formatted_block_time")?,
+        block_time_rating: row.try_get("block_time_rating")?,
+        circulating_supply: row.try_get::<_, i64>("circulating_supply")? as u64,
+        mining_progress_percentage: row.try_get("mining_progress_percentage")?,
+        current_block_reward: row.try_get::<_, i64>("current_block_reward")? as u64,
+        formatted_block_reward: row.try_get("formatted_block_reward")?,
+        current_block_height: row.try_get::<_, i64>("current_block_height")? as u64,
+    })
+}
+
+fn interface_from_row(row: &Row) -> Result<CanisterInterface> {
+    Ok(CanisterInterface {
+        canister_id: row.try_get("canister_id")?,
+        candid_service: row.try_get("candid_service")?,
+        last_updated: row.try_get("last_updated")?,
+    })
+}
+
+fn command_from_row(row: &Row) -> Result<MinerCommand> {
+    let arguments: String = row.try_get("arguments")?;
+    let reply: Option<String> = row.try_get("reply")?;
+    Ok(MinerCommand {
+        id: row.try_get("id")?,
+        canister_id: row.try_get("canister_id")?,
+        command: MinerCommandKind::try_from(row.try_get::<_, String>("command")?)?,
+        arguments: serde_json::from_str(&arguments).unwrap_or(serde_json::Value::Null),
+        status: CommandStatus::try_from(row.try_get::<_, String>("status")?)?,
+        reply: reply.and_then(|json| serde_json::from_str(&json).ok()),
+        error: row.try_get("error")?,
+        admin_id: row.try_get("admin_id")?,
+        admin_name: row.try_get("admin_name")?,
+        created_at: row.try_get("created_at")?,
+        completed_at: row.try_get("completed_at")?,
+    })
+}
+
+fn ledger_from_row(row: &Row) -> Result<LedgerInfo> {
+    Ok(LedgerInfo {
+        canister_id: row.try_get("canister_id")?,
+        name: row.try_get("name")?,
+        symbol: row.try_get("symbol")?,
+        decimals: row.try_get::<_, i16>("decimals")? as u8,
+        fee: row.try_get("fee")?,
+        total_supply: row.try_get("total_supply")?,
+        logo: row.try_get("logo")?,
+        supported_standards: row.try_get("supported_standards")?,
+        metadata: row.try_get("metadata")?,
+        last_updated: row.try_get("last_updated")?,
+    })
+}
+
+fn transaction_from_row(row: &Row) -> Result<LedgerTransaction> {
+    Ok(LedgerTransaction {
+        canister_id: row.try_get("canister_id")?,
+        block_index: row.try_get::<_, i64>("block_index")? as u64,
+        kind: TransactionKind::try_from(row.try_get::<_, String>("kind")?)?,
+        from_owner: row.try_get("from_owner")?,
+        from_subaccount: row.try_get("from_subaccount")?,
+        to_owner: row.try_get("to_owner")?,
+        to_subaccount: row.try_get("to_subaccount")?,
+        spender: row.try_get("spender")?,
+        amount: row.try_get("amount")?,
+        fee: row.try_get("fee")?,
+        memo: row.try_get("memo")?,
+        timestamp: row.try_get::<_, i64>("timestamp")? as u64,
+    })
+}
+
+fn index_state_from_row(row: &Row) -> Result<LedgerIndexState> {
+    Ok(LedgerIndexState {
+        canister_id: row.try_get("canister_id")?,
+        next_block: row.try_get::<_, i64>("next_block")? as u64,
+        log_length: row.try_get::<_, i64>("log_length")? as u64,
+        last_updated: row.try_get("last_updated")?,
+    })
+}
+
+fn balance_from_row(row: &Row) -> Result<AccountBalance> {
+    // The default subaccount is stored as an empty string, it is part of the primary key
+    let subaccount: String = row.try_get("subaccount")?;
+    Ok(AccountBalance {
+        canister_id: row.try_get("canister_id")?,
+        owner: row.try_get("owner")?,
+        subaccount: Some(subaccount).filter(|s| !s.is_empty()),
+        balance: row.try_get("balance")?,
+    })
+}
+
+fn miner_from_row(row: &Row) -> Result<MinerInfo> {
+    Ok(MinerInfo {
+        canister_id: row.try_get("canister_id")?,
+        miner_type: MinerType::try_from(row.try_get::<_, String>("miner_type")?)?,
+        is_mining: row.try_get("is_mining")?,
+        current_token: row.try_get("current_token")?,
+        speed_percentage: row.try_get::<_, i16>("speed_percentage")? as u8,
+        chunks_per_refresh: row.try_get::<_, i64>("chunks_per_refresh")? as u64,
+        last_updated: row.try_get("last_updated")?,
+        raw_info: row.try_get("raw_info")?,
+    })
+}
+
+fn stats_from_row(row: &Row) -> Result<MiningStats> {
+    Ok(MiningStats {
+        canister_id: row.try_get("canister_id")?,
+        total_hashes: row.try_get::<_, i64>("total_hashes")? as u64,
+        blocks_mined: row.try_get::<_, i64>("blocks_mined")? as u64,
+        chunks_since_refresh: row.try_get::<_, i64>("chunks_since_refresh")? as u64,
+        total_rewards: row.try_get::<_, i64>("total_rewards")? as u64,
+        last_hash_rate: row.try_get("last_hash_rate")?,
+        start_time: row.try_get::<_, i64>("start_time")? as u64,
+        last_updated: row.try_get("last_updated")?,
+    })
+}
+
+fn hash_from_row(row: &Row) -> Result<VerifiedModuleHash> {
+    Ok(VerifiedModuleHash {
+        id: row.try_get("id")?,
+        hash: row.try_get("hash")?,
+        description: row.try_get("description")?,
+        canister_type: row.try_get("canister_type")?,
+        is_active: row.try_get("is_active")?,
+        created_at: row.try_get("created_at")?,
+        last_updated: row.try_get("last_updated")?,
+    })
+}
+
+fn admin_from_row(row: &Row) -> Result<Admin> {
+    Ok(Admin {
+        id: row.try_get("id")?,
+        username: row.try_get("username")?,
+        password_hash: row.try_get("password_hash")?,
+        api_key_prefix: row.try_get("api_key_prefix")?,
+        api_key_hash: row.try_get("api_key")?,
+        previous_api_key_prefix: row.try_get("previous_api_key_prefix")?,
+        previous_api_key_hash: row.try_get("previous_api_key")?,
+        previous_api_key_expires_at: row.try_get("previous_api_key_expires_at")?,
+        role: AdminRole::try_from(row.try_get::<_, String>("role")?).unwrap_or(AdminRole::Viewer),
+        is_active: row.try_get("is_active")?,
+        last_used_at: row.try_get("last_used_at")?,
+        failed_login_attempts: row.try_get("failed_login_attempts")?,
+        locked_until: row.try_get("locked_until")?,
+        created_at: row.try_get("created_at")?,
+        last_updated: row.try_get("last_updated")?,
+    })
+}
+
+fn session_from_row(row: &Row) -> Result<AdminSession> {
+    Ok(AdminSession {
+        id: row.try_get("id")?,
+        admin_id: row.try_get("admin_id")?,
+        refresh_token_id: row.try_get("refresh_token_id")?,
+        created_at: row.try_get("created_at")?,
+        expires_at: row.try_get("expires_at")?,
+        revoked_at: row.try_get("revoked_at")?,
+    })
+}
+
+fn audit_from_row(row: &Row) -> Result<AuditLogEntry> {
+    let before: Option<String> = row.try_get("before_state")?;
+    let after: Option<String> = row.try_get("after_state")?;
+    Ok(AuditLogEntry {
+        id: row.try_get("id")?,
+        actor_type: row.try_get("actor_type")?,
+        actor_id: row.try_get("actor_id")?,
+        actor_name: row.try_get("actor_name")?,
+        action: row.try_get("action")?,
+        target_type: row.try_get("target_type")?,
+        target_id: row.try_get("target_id")?,
+        before: before.and_then(|json| serde_json::from_str(&json).ok()),
+        after: after.and_then(|json| serde_json::from_str(&json).ok()),
+        ip_address: row.try_get("ip_address")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+// Map every row, failing on the first one that cannot be read
+fn map_rows<T>(rows: Vec<Row>, from_row: fn(&Row) -> Result<T>) -> Result<Vec<T>> {
+    rows.iter().map(from_row).collect()
+}
+
+// Build the WHERE clause and its numbered parameters for an audit log query
+fn audit_filter(filter: &AuditLogFilter) -> (String, Vec<Box<dyn ToSql + Sync + Send>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql + Sync + Send>> = Vec::new();
+
+    let text_filters = [
+        ("actor_id", &filter.actor_id),
+        ("action", &filter.action),
+        ("target_type", &filter.target_type),
+        ("target_id", &filter.target_id),
+    ];
+    for (column, value) in text_filters {
+        if let Some(value) = value {
+            values.push(Box::new(value.clone()));
+            conditions.push(format!("{} = ${}", column, values.len()));
+        }
+    }
+    if let Some(since) = filter.since {
+        values.push(Box::new(since));
+        conditions.push(format!("created_at >= ${}", values.len()));
+    }
+    if let Some(until) = filter.until {
+        values.push(Box::new(until));
+        conditions.push(format!("created_at <= ${}", values.len()));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), values)
+    }
+}
+
+fn param_refs(values: &[Box<dyn ToSql + Sync + Send>]) -> Vec<&(dyn ToSql + Sync)> {
+    values.iter().map(|value| value.as_ref() as &(dyn ToSql + Sync)).collect()
+}
+
+async fn upsert_canister(client: &impl GenericClient, canister: &Canister) -> Result<()> {
+    client.execute(
+        "INSERT INTO canisters (id, principal, canister_id, type, module_hash, created_at, last_updated)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (canister_id) DO UPDATE SET
+         principal = EXCLUDED.principal,
+         type = EXCLUDED.type,
+         module_hash = EXCLUDED.module_hash,
+         last_updated = EXCLUDED.last_updated",
+        &[
+            &canister.id,
+            &canister.principal,
+            &canister.canister_id,
+            &canister.canister_type.to_string(),
+            &canister.module_hash,
+            &canister.created_at,
+            &canister.last_updated,
+        ],
+    ).await?;
+    Ok(())
+}
+
+async fn upsert_token(client: &impl GenericClient, token: &TokenInfo) -> Result<()> {
+    client.execute(
+        "INSERT INTO token_info (
+            canister_id, name, ticker, decimals, total_supply, transfer_fee, logo, last_updated, raw_info,
+            average_block_time, formatted_block_time, block_time_rating, circulating_supply,
+            mining_progress_percentage, current_block_reward, formatted_block_reward, current_block_height
+         )
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+         ON CONFLICT (canister_id) DO UPDATE SET
+         name = EXCLUDED.name,
+         ticker = EXCLUDED.ticker,
+         decimals = EXCLUDED.decimals,
+         total_supply = EXCLUDED.total_supply,
+         transfer_fee = EXCLUDED.transfer_fee,
+         logo = EXCLUDED.logo,
+         last_updated = EXCLUDED.last_updated,
+         raw_info = EXCLUDED.raw_info,
+         average_block_time = EXCLUDED.average_block_time,
+         formatted_block_time = EXCLUDED.formatted_block_time,
+         block_time_rating = EXCLUDED.block_time_rating,
+         circulating_supply = EXCLUDED.circulating_supply,
+         mining_progress_percentage = EXCLUDED.mining_progress_percentage,
+         current_block_reward = EXCLUDED.current_block_reward,
+         formatted_block_reward = EXCLUDED.formatted_block_reward,
+         current_block_height = EXCLUDED.current_block_height",
+        &[
+            &token.canister_id,
+            &token.name,
+            &token.ticker,
+            &(token.decimals as i16),
+            &to_i64(token.total_supply, "total_supply")?,
+            &to_i64(token.transfer_fee, "transfer_fee")?,
+            &token.logo,
+            &token.last_updated,
+            &token.raw_info,
+            &token.average_block_time,
+            &token.formatted_block_time,
+            &token.block_time_rating,
+            &to_i64(token.circulating_supply, "circulating_supply")?,
+            &token.mining_progress_percentage,
+            &to_i64(token.current_block_reward, "current_block_reward")?,
+            &token.formatted_block_reward,
+            &to_i64(token.current_block_height, "current_block_height")?,
+        ],
+    ).await?;
+    Ok(())
+}
+
+async fn upsert_ledger(client: &impl GenericClient, ledger: &LedgerInfo) -> Result<()> {
+    client.execute(
+        "INSERT INTO ledger_info (canister_id, name, symbol, decimals, fee, total_supply, logo, supported_standards, metadata, last_updated)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         ON CONFLICT (canister_id) DO UPDATE SET
+         name = EXCLUDED.name,
+         symbol = EXCLUDED.symbol,
+         decimals = EXCLUDED.decimals,
+         fee = EXCLUDED.fee,
+         total_supply = EXCLUDED.total_supply,
+         logo = EXCLUDED.logo,
+         supported_standards = EXCLUDED.supported_standards,
+         metadata = EXCLUDED.metadata,
+         last_updated = EXCLUDED.last_updated",
+        &[
+            &ledger.canister_id,
+            &ledger.name,
+            &ledger.symbol,
+            &(ledger.decimals as i16),
+            &ledger.fee,
+            &ledger.total_supply,
+            &ledger.logo,
+            &ledger.supported_standards,
+            &ledger.metadata,
+            &ledger.last_updated,
+        ],
+    ).await?;
+    Ok(())
+}
+
+async fn upsert_miner(client: &impl GenericClient, miner: &MinerInfo) -> Result<()> {
+    client.execute(
+        "INSERT INTO miner_info (canister_id, miner_type, is_mining, current_token, speed_percentage, chunks_per_refresh, last_updated, raw_info)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         ON CONFLICT (canister_id) DO UPDATE SET
+         miner_type = EXCLUDED.miner_type,
+         is_mining = EXCLUDED.is_mining,
+         current_token = EXCLUDED.current_token,
+         speed_percentage = EXCLUDED.speed_percentage,
+         chunks_per_refresh = EXCLUDED.chunks_per_refresh,
+         last_updated = EXCLUDED.last_updated,
+         raw_info = EXCLUDED.raw_info",
+        &[
+            &miner.canister_id,
+            &miner.miner_type.to_string(),
+            &miner.is_mining,
+            &miner.current_token,
+            &(miner.speed_percentage as i16),
+            &to_i64(miner.chunks_per_refresh, "chunks_per_refresh")?,
+            &miner.last_updated,
+            &miner.raw_info,
+        ],
+    ).await?;
+    Ok(())
+}
+
+async fn upsert_stats(client: &impl GenericClient, stats: &MiningStats) -> Result<()> {
+    client.execute(
+        "INSERT INTO mining_stats (canister_id, total_hashes, blocks_mined, chunks_since_refresh, total_rewards, last_hash_rate, start_time, last_updated)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         ON CONFLICT (canister_id) DO UPDATE SET
+         total_hashes = EXCLUDED.total_hashes,
+         blocks_mined = EXCLUDED.blocks_mined,
+         chunks_since_refresh = EXCLUDED.chunks_since_refresh,
+         total_rewards = EXCLUDED.total_rewards,
+         last_hash_rate = EXCLUDED.last_hash_rate,
+         start_time = EXCLUDED.start_time,
+         last_updated = EXCLUDED.last_updated",
+        &[
+            &stats.canister_id,
+            &to_i64(stats.total_hashes, "total_hashes")?,
+            &to_i64(stats.blocks_mined, "blocks_mined")?,
+            &to_i64(stats.chunks_since_refresh, "chunks_since_refresh")?,
+            &to_i64(stats.total_rewards, "total_rewards")?,
+            &stats.last_hash_rate,
+            &to_i64(stats.start_time, "start_time")?,
+            &stats.last_updated,
+        ],
+    ).await?;
+    Ok(())
+}
+
+async fn upsert_hash(client: &impl GenericClient, hash: &VerifiedModuleHash) -> Result<()> {
+    client.execute(
+        "INSERT INTO verified_module_hashes (id, hash, description, canister_type, is_active, created_at, last_updated)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (hash) DO UPDATE SET
+         description = EXCLUDED.description,
+         canister_type = EXCLUDED.canister_type,
+         is_active = EXCLUDED.is_active,
+         last_updated = EXCLUDED.last_updated",
+        &[
+            &hash.id,
+            &hash.hash,
+            &hash.description,
+            &hash.canister_type,
+            &hash.is_active,
+            &hash.created_at,
+            &hash.last_updated,
+        ],
+    ).await?;
+    Ok(())
+}
+
+#[async_trait]
+impl CanisterRepository for PostgresRepository {
+    async fn save(&self, canister: &Canister) -> Result<()> {
+        upsert_canister(&self.client().await?, canister).await
+    }
+
+    async fn save_all(&self, canisters: &[Canister]) -> Result<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        for canister in canisters {
+            upsert_canister(&tx, canister).await?;
+        }
+        Ok(tx.commit().await?)
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Canister>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM canisters WHERE id = $1", CANISTER_COLUMNS),
+            &[&id],
+        ).await?;
+        row.as_ref().map(canister_from_row).transpose()
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<Canister>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM canisters WHERE canister_id = $1 AND deleted_at IS NULL", CANISTER_COLUMNS),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(canister_from_row).transpose()
+    }
+
+    async fn find_any_by_canister_id(&self, canister_id: &str) -> Result<Option<Canister>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM canisters WHERE canister_id = $1", CANISTER_COLUMNS),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(canister_from_row).transpose()
+    }
+
+    async fn find_all(&self) -> Result<Vec<Canister>> {
+        let rows = self.client().await?.query(
+            &format!("SELECT {} FROM canisters WHERE deleted_at IS NULL ORDER BY last_updated DESC", CANISTER_COLUMNS),
+            &[],
+        ).await?;
+        map_rows(rows, canister_from_row)
+    }
+
+    async fn find_by_type(&self, canister_type: &CanisterType) -> Result<Vec<Canister>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM canisters WHERE type = $1 AND deleted_at IS NULL ORDER BY last_updated DESC",
+                CANISTER_COLUMNS
+            ),
+            &[&canister_type.to_string()],
+        ).await?;
+        map_rows(rows, canister_from_row)
+    }
+
+    async fn find_by_principal(&self, principal: &str) -> Result<Vec<Canister>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM canisters WHERE principal = $1 AND deleted_at IS NULL ORDER BY created_at ASC",
+                CANISTER_COLUMNS
+            ),
+            &[&principal],
+        ).await?;
+        map_rows(rows, canister_from_row)
+    }
+
+    async fn find_deleted(&self) -> Result<Vec<Canister>> {
+        let rows = self.client().await?.query(
+            &format!("SELECT {} FROM canisters WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC", CANISTER_COLUMNS),
+            &[],
+        ).await?;
+        map_rows(rows, canister_from_row)
+    }
+
+    async fn soft_delete(&self, canister_id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "UPDATE canisters SET deleted_at = $2 WHERE canister_id = $1 AND deleted_at IS NULL",
+            &[&canister_id, &Utc::now().timestamp()],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn restore(&self, canister_id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "UPDATE canisters SET deleted_at = NULL WHERE canister_id = $1 AND deleted_at IS NOT NULL",
+            &[&canister_id],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        for table in [
+            "mining_stats", "miner_info", "token_info", "ledger_info",
+            "ledger_transactions", "ledger_index_state", "ledger_balances", "canister_interfaces",
+        ] {
+            tx.execute(&format!("DELETE FROM {} WHERE canister_id = $1", table), &[&canister_id]).await?;
+        }
+        let rows_affected = tx.execute("DELETE FROM canisters WHERE canister_id = $1", &[&canister_id]).await?;
+        tx.commit().await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl TokenInfoRepository for PostgresRepository {
+    async fn save(&self, token: &TokenInfo) -> Result<()> {
+        upsert_token(&self.client().await?, token).await
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<TokenInfo>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM token_info t WHERE t.canister_id = $1 AND t.{}", TOKEN_COLUMNS, NOT_DELETED),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(token_from_row).transpose()
+    }
+
+    async fn find_all(&self) -> Result<Vec<TokenInfo>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM token_info t
+                 JOIN canisters c ON t.canister_id = c.canister_id
+                 WHERE c.deleted_at IS NULL
+                 ORDER BY t.last_updated DESC",
+                TOKEN_COLUMNS
+            ),
+            &[],
+        ).await?;
+        map_rows(rows, token_from_row)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "DELETE FROM token_info WHERE canister_id = $1",
+            &[&canister_id],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl CanisterInterfaceRepository for PostgresRepository {
+    async fn save(&self, interface: &CanisterInterface) -> Result<()> {
+        self.client().await?.execute(
+            "INSERT INTO canister_interfaces (canister_id, candid_service, last_updated)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (canister_id) DO UPDATE SET
+             candid_service = EXCLUDED.candid_service,
+             last_updated = EXCLUDED.last_updated",
+            &[&interface.canister_id, &interface.candid_service, &interface.last_updated],
+        ).await?;
+        Ok(())
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<CanisterInterface>> {
+        let row = self.client().await?.query_opt(
+            &format!(
+                "SELECT canister_id, candid_service, last_updated FROM canister_interfaces
+                 WHERE canister_id = $1 AND {}",
+                NOT_DELETED
+            ),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(interface_from_row).transpose()
+    }
+}
+
+#[async_trait]
+impl LedgerInfoRepository for PostgresRepository {
+    async fn save(&self, ledger: &LedgerInfo) -> Result<()> {
+        upsert_ledger(&self.client().await?, ledger).await
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<LedgerInfo>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM ledger_info l WHERE l.canister_id = $1 AND l.{}", LEDGER_COLUMNS, NOT_DELETED),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(ledger_from_row).transpose()
+    }
+
+    async fn find_all(&self) -> Result<Vec<LedgerInfo>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM ledger_info l
+                 JOIN canisters c ON l.canister_id = c.canister_id
+                 WHERE c.deleted_at IS NULL
+                 ORDER BY l.last_updated DESC",
+                LEDGER_COLUMNS
+            ),
+            &[],
+        ).await?;
+        map_rows(rows, ledger_from_row)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "DELETE FROM ledger_info WHERE canister_id = $1",
+            &[&canister_id],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl LedgerTransactionRepository for PostgresRepository {
+    async fn save_page(
+        &self,
+        transactions: &[LedgerTransaction],
+        balances: &[AccountBalance],
+        state: &LedgerIndexState,
+    ) -> Result<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        for transaction in transactions {
+            // Blocks never change, so an already indexed block is left as is
+            tx.execute(
+                "INSERT INTO ledger_transactions (canister_id, block_index, kind, from_owner, from_subaccount, to_owner, to_subaccount,
+                 spender, amount, fee, memo, timestamp)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                 ON CONFLICT DO NOTHING",
+                &[
+                    &transaction.canister_id,
+                    &to_i64(transaction.block_index, "block_index")?,
+                    &transaction.kind.to_string(),
+                    &transaction.from_owner,
+                    &transaction.from_subaccount,
+                    &transaction.to_owner,
+                    &transaction.to_subaccount,
+                    &transaction.spender,
+                    &transaction.amount,
+                    &transaction.fee,
+                    &transaction.memo,
+                    &to_i64(transaction.timestamp, "timestamp")?,
+                ],
+            ).await?;
+        }
+        for balance in balances {
+            let subaccount = balance.subaccount.as_deref().unwrap_or("");
+            // Empty accounts are removed, holders are the stored accounts
+            if balance.balance == "0" {
+                tx.execute(
+                    "DELETE FROM ledger_balances WHERE canister_id = $1 AND owner = $2 AND subaccount = $3",
+                    &[&balance.canister_id, &balance.owner, &subaccount],
+                ).await?;
+            } else {
+                tx.execute(
+                    "INSERT INTO ledger_balances (canister_id, owner, subaccount, balance)
+                     VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (canister_id, owner, subaccount) DO UPDATE SET
+                     balance = EXCLUDED.balance",
+                    &[&balance.canister_id, &balance.owner, &subaccount, &balance.balance],
+                ).await?;
+            }
+        }
+        tx.execute(
+            "INSERT INTO ledger_index_state (canister_id, next_block, log_length, last_updated)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (canister_id) DO UPDATE SET
+             next_block = EXCLUDED.next_block,
+             log_length = EXCLUDED.log_length,
+             last_updated = EXCLUDED.last_updated",
+            &[
+                &state.canister_id,
+                &to_i64(state.next_block, "next_block")?,
+                &to_i64(state.log_length, "log_length")?,
+                &state.last_updated,
+            ],
+        ).await?;
+        Ok(tx.commit().await?)
+    }
+
+    async fn index_state(&self, canister_id: &str) -> Result<Option<LedgerIndexState>> {
+        let row = self.client().await?.query_opt(
+            "SELECT canister_id, next_block, log_length, last_updated FROM ledger_index_state WHERE canister_id = $1",
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(index_state_from_row).transpose()
+    }
+
+    async fn find_by_account(
+        &self,
+        canister_id: &str,
+        owner: &str,
+        subaccount: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LedgerTransaction>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM ledger_transactions WHERE {} AND {} ORDER BY block_index DESC LIMIT $4 OFFSET $5",
+                TRANSACTION_COLUMNS, ACCOUNT_FILTER, NOT_DELETED
+            ),
+            &[&canister_id, &owner, &subaccount, &limit, &offset],
+        ).await?;
+        map_rows(rows, transaction_from_row)
+    }
+
+    async fn count_by_account(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<i64> {
+        let row = self.client().await?.query_one(
+            &format!("SELECT COUNT(*) FROM ledger_transactions WHERE {} AND {}", ACCOUNT_FILTER, NOT_DELETED),
+            &[&canister_id, &owner, &subaccount],
+        ).await?;
+        Ok(row.try_get(0)?)
+    }
+
+    async fn find_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<Option<AccountBalance>> {
+        let row = self.client().await?.query_opt(
+            "SELECT canister_id, owner, subaccount, balance FROM ledger_balances
+             WHERE canister_id = $1 AND owner = $2 AND subaccount = $3",
+            &[&canister_id, &owner, &subaccount.unwrap_or("")],
+        ).await?;
+        row.as_ref().map(balance_from_row).transpose()
+    }
+
+    async fn top_holders(&self, canister_id: &str, limit: i64) -> Result<Vec<AccountBalance>> {
+        // Decimal strings are ordered by length first
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT canister_id, owner, subaccount, balance FROM ledger_balances
+                 WHERE canister_id = $1 AND {}
+                 ORDER BY LENGTH(balance) DESC, balance DESC
+                 LIMIT $2",
+                NOT_DELETED
+            ),
+            &[&canister_id, &limit],
+        ).await?;
+        map_rows(rows, balance_from_row)
+    }
+
+    async fn count_holders(&self, canister_id: &str) -> Result<i64> {
+        let row = self.client().await?.query_one(
+            &format!("SELECT COUNT(*) FROM ledger_balances WHERE canister_id = $1 AND {}", NOT_DELETED),
+            &[&canister_id],
+        ).await?;
+        Ok(row.try_get(0)?)
+    }
+}
+
+#[async_trait]
+impl MinerInfoRepository for PostgresRepository {
+    async fn save(&self, miner: &MinerInfo) -> Result<()> {
+        upsert_miner(&self.client().await?, miner).await
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<MinerInfo>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM miner_info m WHERE m.canister_id = $1 AND m.{}", MINER_COLUMNS, NOT_DELETED),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(miner_from_row).transpose()
+    }
+
+    async fn find_all(&self) -> Result<Vec<MinerInfo>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM miner_info m
+                 JOIN canisters c ON m.canister_id = c.canister_id
+                 WHERE c.deleted_at IS NULL
+                 ORDER BY m.last_updated DESC",
+                MINER_COLUMNS
+            ),
+            &[],
+        ).await?;
+        map_rows(rows, miner_from_row)
+    }
+
+    async fn find_by_token(&self, token_canister_id: &str) -> Result<Vec<MinerInfo>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM miner_info m
+                 JOIN canisters c ON m.canister_id = c.canister_id
+                 WHERE m.current_token = $1 AND c.deleted_at IS NULL
+                 ORDER BY m.last_updated DESC",
+                MINER_COLUMNS
+            ),
+            &[&token_canister_id],
+        ).await?;
+        map_rows(rows, miner_from_row)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "DELETE FROM miner_info WHERE canister_id = $1",
+            &[&canister_id],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl MiningStatsRepository for PostgresRepository {
+    async fn save(&self, stats: &MiningStats) -> Result<()> {
+        upsert_stats(&self.client().await?, stats).await
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<MiningStats>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM mining_stats ms WHERE ms.canister_id = $1 AND ms.{}", STATS_COLUMNS, NOT_DELETED),
+            &[&canister_id],
+        ).await?;
+        row.as_ref().map(stats_from_row).transpose()
+    }
+
+    async fn find_all(&self) -> Result<Vec<MiningStats>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM mining_stats ms
+                 JOIN canisters c ON ms.canister_id = c.canister_id
+                 WHERE c.deleted_at IS NULL
+                 ORDER BY ms.last_updated DESC",
+                STATS_COLUMNS
+            ),
+            &[],
+        ).await?;
+        map_rows(rows, stats_from_row)
+    }
+
+    async fn total_hash_rate_for_token(&self, token_canister_id: &str) -> Result<f64> {
+        let row = self.client().await?.query_one(
+            "SELECT SUM(ms.last_hash_rate)
+             FROM mining_stats ms
+             JOIN miner_info m ON ms.canister_id = m.canister_id
+             JOIN canisters c ON ms.canister_id = c.canister_id
+             WHERE m.current_token = $1 AND m.is_mining AND c.deleted_at IS NULL",
+            &[&token_canister_id],
+        ).await?;
+        Ok(row.try_get::<_, Option<f64>>(0)?.unwrap_or(0.0))
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "DELETE FROM mining_stats WHERE canister_id = $1",
+            &[&canister_id],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl MinerCommandRepository for PostgresRepository {
+    async fn save(&self, command: &MinerCommand) -> Result<()> {
+        self.client().await?.execute(
+            "INSERT INTO miner_commands (
+                id, canister_id, command, arguments, status, reply, error, admin_id, admin_name, created_at, completed_at
+             )
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             ON CONFLICT (id) DO UPDATE SET
+             status = EXCLUDED.status,
+             reply = EXCLUDED.reply,
+             error = EXCLUDED.error,
+             completed_at = EXCLUDED.completed_at",
+            &[
+                &command.id,
+                &command.canister_id,
+                &command.command.to_string(),
+                &command.arguments.to_string(),
+                &command.status.to_string(),
+                &command.reply.as_ref().map(|reply| reply.to_string()),
+                &command.error,
+                &command.admin_id,
+                &command.admin_name,
+                &command.created_at,
+                &command.completed_at,
+            ],
+        ).await?;
+        Ok(())
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str, limit: i64, offset: i64) -> Result<Vec<MinerCommand>> {
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM miner_commands WHERE canister_id = $1
+                 ORDER BY created_at DESC, seq DESC
+                 LIMIT $2 OFFSET $3",
+                COMMAND_COLUMNS
+            ),
+            &[&canister_id, &limit, &offset],
+        ).await?;
+        map_rows(rows, command_from_row)
+    }
+}
+
+#[async_trait]
+impl ModuleHashRepository for PostgresRepository {
+    async fn save(&self, hash: &VerifiedModuleHash) -> Result<()> {
+        upsert_hash(&self.client().await?, hash).await
+    }
+
+    async fn find_all(&self) -> Result<Vec<VerifiedModuleHash>> {
+        let rows = self.client().await?.query(
+            &format!("SELECT {} FROM verified_module_hashes ORDER BY last_updated DESC", HASH_COLUMNS),
+            &[],
+        ).await?;
+        map_rows(rows, hash_from_row)
+    }
+
+    async fn find_by_hash(&self, hash: &str) -> Result<Option<VerifiedModuleHash>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM verified_module_hashes WHERE hash = $1", HASH_COLUMNS),
+            &[&hash],
+        ).await?;
+        row.as_ref().map(hash_from_row).transpose()
+    }
+
+    async fn is_hash_verified(&self, hash: &str, canister_type: &str) -> Result<bool> {
+        let row = self.client().await?.query_one(
+            "SELECT EXISTS(SELECT 1 FROM verified_module_hashes WHERE hash = $1 AND canister_type = $2 AND is_active)",
+            &[&hash, &canister_type],
+        ).await?;
+        Ok(row.try_get(0)?)
+    }
+
+    async fn delete(&self, hash: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "DELETE FROM verified_module_hashes WHERE hash = $1",
+            &[&hash],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl AdminRepository for PostgresRepository {
+    async fn save(&self, admin: &Admin) -> Result<()> {
+        self.client().await?.execute(
+            "INSERT INTO admins (id, username, password_hash, api_key, api_key_prefix, previous_api_key,
+             previous_api_key_prefix, previous_api_key_expires_at, role, is_active, created_at, last_updated)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+             ON CONFLICT (username) DO UPDATE SET
+             password_hash = EXCLUDED.password_hash,
+             api_key = EXCLUDED.api_key,
+             api_key_prefix = EXCLUDED.api_key_prefix,
+             previous_api_key = EXCLUDED.previous_api_key,
+             previous_api_key_prefix = EXCLUDED.previous_api_key_prefix,
+             previous_api_key_expires_at = EXCLUDED.previous_api_key_expires_at,
+             role = EXCLUDED.role,
+             is_active = EXCLUDED.is_active,
+             last_updated = EXCLUDED.last_updated",
+            &[
+                &admin.id,
+                &admin.username,
+                &admin.password_hash,
+                &admin.api_key_hash,
+                &admin.api_key_prefix,
+                &admin.previous_api_key_hash,
+                &admin.previous_api_key_prefix,
+                &admin.previous_api_key_expires_at,
+                &admin.role.to_string(),
+                &admin.is_active,
+                &admin.created_at,
+                &admin.last_updated,
+            ],
+        ).await?;
+        Ok(())
+    }
+
+    async fn insert_if_none_exists(&self, admin: &Admin) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "INSERT INTO admins (id, username, password_hash, api_key, api_key_prefix, role, is_active, created_at, last_updated)
+             SELECT $1::TEXT, $2::TEXT, $3::TEXT, $4::TEXT, $5::TEXT, $6::TEXT, $7::BOOLEAN, $8::BIGINT, $9::BIGINT
+             WHERE NOT EXISTS (SELECT 1 FROM admins)
+             ON CONFLICT DO NOTHING",
+            &[
+                &admin.id,
+                &admin.username,
+                &admin.password_hash,
+                &admin.api_key_hash,
+                &admin.api_key_prefix,
+                &admin.role.to_string(),
+                &admin.is_active,
+                &admin.created_at,
+                &admin.last_updated,
+            ],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Admin>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM admins WHERE id = $1", ADMIN_COLUMNS),
+            &[&id],
+        ).await?;
+        row.as_ref().map(admin_from_row).transpose()
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<Admin>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM admins WHERE username = $1", ADMIN_COLUMNS),
+            &[&username],
+        ).await?;
+        row.as_ref().map(admin_from_row).transpose()
+    }
+
+    async fn find_by_api_key(&self, api_key: &str) -> Result<Option<Admin>> {
+        let now = Utc::now().timestamp();
+        let rows = self.client().await?.query(
+            &format!(
+                "SELECT {} FROM admins
+                 WHERE is_active
+                 AND (api_key_prefix = $1 OR (previous_api_key_prefix = $1 AND previous_api_key_expires_at > $2))",
+                ADMIN_COLUMNS
+            ),
+            &[&api_key_prefix(api_key), &now],
+        ).await?;
+
+        for row in &rows {
+            let admin = admin_from_row(row)?;
+            if admin.matches_api_key(api_key, now) {
+                return Ok(Some(admin));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Admin>> {
+        let rows = self.client().await?.query(
+            &format!("SELECT {} FROM admins ORDER BY created_at ASC", ADMIN_COLUMNS),
+            &[],
+        ).await?;
+        map_rows(rows, admin_from_row)
+    }
+
+    async fn count_active_superadmins(&self) -> Result<i64> {
+        let row = self.client().await?.query_one(
+            "SELECT COUNT(*) FROM admins WHERE is_active AND role = 'superadmin'",
+            &[],
+        ).await?;
+        Ok(row.try_get(0)?)
+    }
+
+    async fn touch_last_used(&self, id: &str) -> Result<()> {
+        self.client().await?.execute(
+            "UPDATE admins SET last_used_at = $2 WHERE id = $1",
+            &[&id, &Utc::now().timestamp()],
+        ).await?;
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, id: &str, max_attempts: i64, lockout_secs: i64) -> Result<()> {
+        self.client().await?.execute(
+            "UPDATE admins SET
+             failed_login_attempts = failed_login_attempts + 1,
+             locked_until = CASE WHEN failed_login_attempts + 1 >= $2 THEN $3 ELSE locked_until END
+             WHERE id = $1",
+            &[&id, &max_attempts, &(Utc::now().timestamp() + lockout_secs)],
+        ).await?;
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, id: &str) -> Result<()> {
+        self.client().await?.execute(
+            "UPDATE admins SET failed_login_attempts = 0, locked_until = NULL WHERE id = $1",
+            &[&id],
+        ).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AdminSessionRepository for PostgresRepository {
+    async fn save(&self, session: &AdminSession) -> Result<()> {
+        self.client().await?.execute(
+            "INSERT INTO admin_sessions (id, admin_id, refresh_token_id, created_at, expires_at, revoked_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (id) DO UPDATE SET
+             refresh_token_id = EXCLUDED.refresh_token_id,
+             expires_at = EXCLUDED.expires_at,
+             revoked_at = EXCLUDED.revoked_at",
+            &[
+                &session.id,
+                &session.admin_id,
+                &session.refresh_token_id,
+                &session.created_at,
+                &session.expires_at,
+                &session.revoked_at,
+            ],
+        ).await?;
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<AdminSession>> {
+        let row = self.client().await?.query_opt(
+            &format!("SELECT {} FROM admin_sessions WHERE id = $1", SESSION_COLUMNS),
+            &[&id],
+        ).await?;
+        row.as_ref().map(session_from_row).transpose()
+    }
+
+    async fn revoke(&self, id: &str) -> Result<bool> {
+        let rows_affected = self.client().await?.execute(
+            "UPDATE admin_sessions SET revoked_at = $2 WHERE id = $1 AND revoked_at IS NULL",
+            &[&id, &Utc::now().timestamp()],
+        ).await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn revoke_all_for_admin(&self, admin_id: &str) -> Result<usize> {
+        let rows_affected = self.client().await?.execute(
+            "UPDATE admin_sessions SET revoked_at = $2 WHERE admin_id = $1 AND revoked_at IS NULL",
+            &[&admin_id, &Utc::now().timestamp()],
+        ).await?;
+        Ok(rows_affected as usize)
+    }
+
+    async fn delete_expired(&self) -> Result<usize> {
+        let rows_affected = self.client().await?.execute(
+            "DELETE FROM admin_sessions WHERE expires_at < $1 OR revoked_at IS NOT NULL",
+            &[&Utc::now().timestamp()],
+        ).await?;
+        Ok(rows_affected as usize)
+    }
+}
+
+#[async_trait]
+impl AuditLogRepository for PostgresRepository {
+    async fn save(&self, entry: &AuditLogEntry) -> Result<()> {
+        let (before, after) = entry.changed_states();
+        self.client().await?.execute(
+            "INSERT INTO audit_log (id, actor_type, actor_id, actor_name, action, target_type, target_id,
+             before_state, after_state, ip_address, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            &[
+                &entry.id,
+                &entry.actor_type,
+                &entry.actor_id,
+                &entry.actor_name,
+                &entry.action,
+                &entry.target_type,
+                &entry.target_id,
+                &before,
+                &after,
+                &entry.ip_address,
+                &entry.created_at,
+            ],
+        ).await?;
+        Ok(())
+    }
+
+    async fn find(&self, filter: &AuditLogFilter, limit: i64, offset: i64) -> Result<Vec<AuditLogEntry>> {
+        let (where_clause, mut values) = audit_filter(filter);
+        values.push(Box::new(limit));
+        values.push(Box::new(offset));
+        let query = format!(
+            "SELECT {} FROM audit_log {} ORDER BY created_at DESC, seq DESC LIMIT ${} OFFSET ${}",
+            AUDIT_COLUMNS,
+            where_clause,
+            values.len() - 1,
+            values.len()
+        );
+        let rows = self.client().await?.query(&query, &param_refs(&values)).await?;
+        map_rows(rows, audit_from_row)
+    }
+
+    async fn count(&self, filter: &AuditLogFilter) -> Result<i64> {
+        let (where_clause, values) = audit_filter(filter);
+        let row = self.client().await?.query_one(
+            &format!("SELECT COUNT(*) FROM audit_log {}", where_clause),
+            &param_refs(&values),
+        ).await?;
+        Ok(row.try_get(0)?)
+    }
+}
+
+#[async_trait]
+impl RegistryRepository for PostgresRepository {
+    async fn ping(&self) -> Result<()> {
+        self.client().await?.query_one("SELECT 1", &[]).await?;
+        Ok(())
+    }
+
+    async fn stats(&self) -> Result<RegistryStats> {
+        let client = self.client().await?;
+        let mut stats = RegistryStats::default();
+
+        let rows = client.query(
+            "SELECT type, COUNT(*) FROM canisters WHERE deleted_at IS NULL GROUP BY type",
+            &[],
+        ).await?;
+        for row in rows {
+            stats.canisters_by_type.insert(row.try_get(0)?, row.try_get(1)?);
+        }
+
+        // SUM over BIGINT yields NUMERIC, cast back to fit i64
+        let row = client.query_one(
+            &format!(
+                "SELECT
+                 (SELECT COUNT(*) FROM token_info WHERE {0}),
+                 (SELECT COUNT(*) FROM ledger_info WHERE {0}),
+                 (SELECT COUNT(*) FROM miner_info WHERE {0}),
+                 (SELECT COUNT(*) FROM miner_info WHERE is_mining AND {0}),
+                 (SELECT SUM(ms.last_hash_rate)
+                  FROM mining_stats ms
+                  JOIN miner_info m ON ms.canister_id = m.canister_id
+                  JOIN canisters c ON ms.canister_id = c.canister_id
+                  WHERE m.is_mining AND c.deleted_at IS NULL),
+                 (SELECT SUM(blocks_mined)::BIGINT FROM mining_stats WHERE {0}),
+                 (SELECT SUM(total_rewards)::BIGINT FROM mining_stats WHERE {0})",
+                NOT_DELETED
+            ),
+            &[],
+        ).await?;
+        stats.tokens = row.try_get(0)?;
+        stats.ledgers = row.try_get(1)?;
+        stats.miners = row.try_get(2)?;
+        stats.active_miners = row.try_get(3)?;
+        stats.network_hash_rate = row.try_get::<_, Option<f64>>(4)?.unwrap_or(0.0);
+        stats.blocks_mined = row.try_get::<_, Option<i64>>(5)?.unwrap_or(0);
+        stats.total_rewards = row.try_get::<_, Option<i64>>(6)?.unwrap_or(0);
+
+        Ok(stats)
+    }
+
+    async fn keys(&self) -> Result<RegistryKeys> {
+        let client = self.client().await?;
+        let mut keys = RegistryKeys::new();
+        for table in RegistryTable::ALL {
+            let rows = client.query(&format!("SELECT {} FROM {}", table.key_column(), table), &[]).await?;
+            let table_keys = rows.iter().map(|row| row.try_get(0)).collect::<Result<HashSet<String>, _>>()?;
+            keys.insert(table, table_keys);
+        }
+        Ok(keys)
+    }
+
+    async fn save(&self, rows: &RegistryExport) -> Result<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        for canister in &rows.canisters {
+            upsert_canister(&tx, canister).await?;
+        }
+        for token in &rows.token_info {
+            upsert_token(&tx, token).await?;
+        }
+        for ledger in &rows.ledger_info {
+            upsert_ledger(&tx, ledger).await?;
+        }
+        for miner in &rows.miner_info {
+            upsert_miner(&tx, miner).await?;
+        }
+        for stats in &rows.mining_stats {
+            upsert_stats(&tx, stats).await?;
+        }
+        for hash in &rows.verified_module_hashes {
+            upsert_hash(&tx, hash).await?;
+        }
+        Ok(tx.commit().await?)
+    }
+}
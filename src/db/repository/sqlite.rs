@@ -0,0 +1,549 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::db::export::{RegistryExport, RegistryTable};
+use crate::db::models::admin::Admin;
+use crate::db::models::admin_session::AdminSession;
+use crate::db::models::audit_log::{AuditLogEntry, AuditLogFilter};
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::canister_interface::CanisterInterface;
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::ledger_transaction::{AccountBalance, LedgerIndexState, LedgerTransaction};
+use crate::db::models::miner_command::MinerCommand;
+use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::db::models::verified_module_hash::VerifiedModuleHash;
+use crate::db::pool::{init_memory_pool, init_pool, DbPool};
+use crate::db::repository::*;
+
+/// Repository backed by a SQLite file, the single-instance default,
+/// or by an in-memory SQLite database for development and tests
+pub struct SqliteRepository {
+    pool: DbPool,
+    // None for an in-memory database
+    path: Option<PathBuf>,
+}
+
+impl SqliteRepository {
+    /// Open the database file, creating it and its schema if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        let pool = init_pool(path).context("Failed to initialize SQLite connection pool")?;
+        Ok(Self { pool, path: Some(path.to_path_buf()) })
+    }
+
+    /// Create an empty in-memory database, its data is lost when the repository is dropped
+    pub fn open_in_memory() -> Result<Self> {
+        let pool = init_memory_pool().context("Failed to initialize in-memory SQLite connection pool")?;
+        Ok(Self { pool, path: None })
+    }
+
+    fn conn(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
+        self.pool.get().context("Failed to get database connection")
+    }
+}
+
+impl Repository for SqliteRepository {
+    fn canisters(&self) -> &dyn CanisterRepository { self }
+    fn tokens(&self) -> &dyn TokenInfoRepository { self }
+    fn interfaces(&self) -> &dyn CanisterInterfaceRepository { self }
+    fn ledgers(&self) -> &dyn LedgerInfoRepository { self }
+    fn ledger_transactions(&self) -> &dyn LedgerTransactionRepository { self }
+    fn miners(&self) -> &dyn MinerInfoRepository { self }
+    fn mining_stats(&self) -> &dyn MiningStatsRepository { self }
+    fn miner_commands(&self) -> &dyn MinerCommandRepository { self }
+    fn module_hashes(&self) -> &dyn ModuleHashRepository { self }
+    fn admins(&self) -> &dyn AdminRepository { self }
+    fn sessions(&self) -> &dyn AdminSessionRepository { self }
+    fn audit_log(&self) -> &dyn AuditLogRepository { self }
+    fn registry(&self) -> &dyn RegistryRepository { self }
+
+    fn backend(&self) -> &'static str {
+        if self.path.is_some() { "sqlite" } else { "memory" }
+    }
+
+    fn file_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        let state = self.pool.state();
+        PoolStatus {
+            connections: state.connections as usize,
+            idle_connections: state.idle_connections as usize,
+            max_size: self.pool.max_size() as usize,
+        }
+    }
+}
+
+#[async_trait]
+impl CanisterRepository for SqliteRepository {
+    async fn save(&self, canister: &Canister) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(canister.save(&conn)?)
+    }
+
+    async fn save_all(&self, canisters: &[Canister]) -> Result<()> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()?;
+        for canister in canisters {
+            canister.save(&tx)?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_by_id(&conn, id)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_any_by_canister_id(&self, canister_id: &str) -> Result<Option<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_any_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_all(&conn)?)
+    }
+
+    async fn find_by_type(&self, canister_type: &CanisterType) -> Result<Vec<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_by_type(&conn, canister_type)?)
+    }
+
+    async fn find_by_principal(&self, principal: &str) -> Result<Vec<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_by_principal(&conn, principal)?)
+    }
+
+    async fn find_deleted(&self) -> Result<Vec<Canister>> {
+        let conn = self.conn()?;
+        Ok(Canister::find_deleted(&conn)?)
+    }
+
+    async fn soft_delete(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(Canister::soft_delete(&conn, canister_id)?)
+    }
+
+    async fn restore(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(Canister::restore(&conn, canister_id)?)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(Canister::delete(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl TokenInfoRepository for SqliteRepository {
+    async fn save(&self, token: &TokenInfo) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(token.save(&conn)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<TokenInfo>> {
+        let conn = self.conn()?;
+        Ok(TokenInfo::find_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<TokenInfo>> {
+        let conn = self.conn()?;
+        Ok(TokenInfo::find_all(&conn)?)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(TokenInfo::delete(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl CanisterInterfaceRepository for SqliteRepository {
+    async fn save(&self, interface: &CanisterInterface) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(interface.save(&conn)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<CanisterInterface>> {
+        let conn = self.conn()?;
+        Ok(CanisterInterface::find_by_canister_id(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl LedgerInfoRepository for SqliteRepository {
+    async fn save(&self, ledger: &LedgerInfo) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(ledger.save(&conn)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<LedgerInfo>> {
+        let conn = self.conn()?;
+        Ok(LedgerInfo::find_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<LedgerInfo>> {
+        let conn = self.conn()?;
+        Ok(LedgerInfo::find_all(&conn)?)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(LedgerInfo::delete(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl LedgerTransactionRepository for SqliteRepository {
+    async fn save_page(
+        &self,
+        transactions: &[LedgerTransaction],
+        balances: &[AccountBalance],
+        state: &LedgerIndexState,
+    ) -> Result<()> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()?;
+        for transaction in transactions {
+            transaction.save(&tx)?;
+        }
+        for balance in balances {
+            balance.save(&tx)?;
+        }
+        state.save(&tx)?;
+        Ok(tx.commit()?)
+    }
+
+    async fn index_state(&self, canister_id: &str) -> Result<Option<LedgerIndexState>> {
+        let conn = self.conn()?;
+        Ok(LedgerIndexState::find_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_by_account(
+        &self,
+        canister_id: &str,
+        owner: &str,
+        subaccount: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LedgerTransaction>> {
+        let conn = self.conn()?;
+        Ok(LedgerTransaction::find_by_account(&conn, canister_id, owner, subaccount, limit, offset)?)
+    }
+
+    async fn count_by_account(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<i64> {
+        let conn = self.conn()?;
+        Ok(LedgerTransaction::count_by_account(&conn, canister_id, owner, subaccount)?)
+    }
+
+    async fn find_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<Option<AccountBalance>> {
+        let conn = self.conn()?;
+        Ok(AccountBalance::find(&conn, canister_id, owner, subaccount)?)
+    }
+
+    async fn top_holders(&self, canister_id: &str, limit: i64) -> Result<Vec<AccountBalance>> {
+        let conn = self.conn()?;
+        Ok(AccountBalance::find_top(&conn, canister_id, limit)?)
+    }
+
+    async fn count_holders(&self, canister_id: &str) -> Result<i64> {
+        let conn = self.conn()?;
+        Ok(AccountBalance::count(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl MinerInfoRepository for SqliteRepository {
+    async fn save(&self, miner: &MinerInfo) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(miner.save(&conn)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<MinerInfo>> {
+        let conn = self.conn()?;
+        Ok(MinerInfo::find_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<MinerInfo>> {
+        let conn = self.conn()?;
+        Ok(MinerInfo::find_all(&conn)?)
+    }
+
+    async fn find_by_token(&self, token_canister_id: &str) -> Result<Vec<MinerInfo>> {
+        let conn = self.conn()?;
+        Ok(MinerInfo::find_by_token(&conn, token_canister_id)?)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(MinerInfo::delete(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl MiningStatsRepository for SqliteRepository {
+    async fn save(&self, stats: &MiningStats) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(stats.save(&conn)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str) -> Result<Option<MiningStats>> {
+        let conn = self.conn()?;
+        Ok(MiningStats::find_by_canister_id(&conn, canister_id)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<MiningStats>> {
+        let conn = self.conn()?;
+        Ok(MiningStats::find_all(&conn)?)
+    }
+
+    async fn total_hash_rate_for_token(&self, token_canister_id: &str) -> Result<f64> {
+        let conn = self.conn()?;
+        Ok(MiningStats::total_hash_rate_for_token(&conn, token_canister_id)?)
+    }
+
+    async fn delete(&self, canister_id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(MiningStats::delete(&conn, canister_id)?)
+    }
+}
+
+#[async_trait]
+impl MinerCommandRepository for SqliteRepository {
+    async fn save(&self, command: &MinerCommand) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(command.save(&conn)?)
+    }
+
+    async fn find_by_canister_id(&self, canister_id: &str, limit: i64, offset: i64) -> Result<Vec<MinerCommand>> {
+        let conn = self.conn()?;
+        Ok(MinerCommand::find_by_canister_id(&conn, canister_id, limit, offset)?)
+    }
+}
+
+#[async_trait]
+impl ModuleHashRepository for SqliteRepository {
+    async fn save(&self, hash: &VerifiedModuleHash) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(hash.save(&conn)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<VerifiedModuleHash>> {
+        let conn = self.conn()?;
+        Ok(VerifiedModuleHash::find_all(&conn)?)
+    }
+
+    async fn find_by_hash(&self, hash: &str) -> Result<Option<VerifiedModuleHash>> {
+        let conn = self.conn()?;
+        Ok(VerifiedModuleHash::find_by_hash(&conn, hash)?)
+    }
+
+    async fn is_hash_verified(&self, hash: &str, canister_type: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(VerifiedModuleHash::is_hash_verified(&conn, hash, canister_type)?)
+    }
+
+    async fn delete(&self, hash: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(VerifiedModuleHash::delete(&conn, hash)?)
+    }
+}
+
+#[async_trait]
+impl AdminRepository for SqliteRepository {
+    async fn save(&self, admin: &Admin) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(admin.save(&conn)?)
+    }
+
+    async fn insert_if_none_exists(&self, admin: &Admin) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(admin.insert_if_none_exists(&conn)?)
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Admin>> {
+        let conn = self.conn()?;
+        Ok(Admin::find_by_id(&conn, id)?)
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<Admin>> {
+        let conn = self.conn()?;
+        Ok(Admin::find_by_username(&conn, username)?)
+    }
+
+    async fn find_by_api_key(&self, api_key: &str) -> Result<Option<Admin>> {
+        let conn = self.conn()?;
+        Ok(Admin::find_by_api_key(&conn, api_key)?)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Admin>> {
+        let conn = self.conn()?;
+        Ok(Admin::find_all(&conn)?)
+    }
+
+    async fn count_active_superadmins(&self) -> Result<i64> {
+        let conn = self.conn()?;
+        Ok(Admin::count_active_superadmins(&conn)?)
+    }
+
+    async fn touch_last_used(&self, id: &str) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(Admin::touch_last_used(&conn, id)?)
+    }
+
+    async fn record_login_failure(&self, id: &str, max_attempts: i64, lockout_secs: i64) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(Admin::record_login_failure(&conn, id, max_attempts, lockout_secs)?)
+    }
+
+    async fn reset_login_failures(&self, id: &str) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(Admin::reset_login_failures(&conn, id)?)
+    }
+}
+
+#[async_trait]
+impl AdminSessionRepository for SqliteRepository {
+    async fn save(&self, session: &AdminSession) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(session.save(&conn)?)
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<AdminSession>> {
+        let conn = self.conn()?;
+        Ok(AdminSession::find_by_id(&conn, id)?)
+    }
+
+    async fn revoke(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        Ok(AdminSession::revoke(&conn, id)?)
+    }
+
+    async fn revoke_all_for_admin(&self, admin_id: &str) -> Result<usize> {
+        let conn = self.conn()?;
+        Ok(AdminSession::revoke_all_for_admin(&conn, admin_id)?)
+    }
+
+    async fn delete_expired(&self) -> Result<usize> {
+        let conn = self.conn()?;
+        Ok(AdminSession::delete_expired(&conn)?)
+    }
+}
+
+#[async_trait]
+impl AuditLogRepository for SqliteRepository {
+    async fn save(&self, entry: &AuditLogEntry) -> Result<()> {
+        let conn = self.conn()?;
+        Ok(entry.save(&conn)?)
+    }
+
+    async fn find(&self, filter: &AuditLogFilter, limit: i64, offset: i64) -> Result<Vec<AuditLogEntry>> {
+        let conn = self.conn()?;
+        Ok(AuditLogEntry::find(&conn, filter, limit, offset)?)
+    }
+
+    async fn count(&self, filter: &AuditLogFilter) -> Result<i64> {
+        let conn = self.conn()?;
+        Ok(AuditLogEntry::count(&conn, filter)?)
+    }
+}
+
+#[async_trait]
+impl RegistryRepository for SqliteRepository {
+    async fn ping(&self) -> Result<()> {
+        self.conn()?.query_row("SELECT 1", [], |row| row.get::<_, i64>(0))?;
+        Ok(())
+    }
+
+    async fn stats(&self) -> Result<RegistryStats> {
+        let conn = self.conn()?;
+        let mut stats = RegistryStats::default();
+
+        let mut stmt = conn.prepare("SELECT type, COUNT(*) FROM canisters WHERE deleted_at IS NULL GROUP BY type")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (canister_type, count) = row?;
+            stats.canisters_by_type.insert(canister_type, count);
+        }
+
+        (stats.tokens, stats.ledgers, stats.miners, stats.active_miners) = conn.query_row(
+            "SELECT
+             (SELECT COUNT(*) FROM token_info WHERE canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)),
+             (SELECT COUNT(*) FROM ledger_info WHERE canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)),
+             (SELECT COUNT(*) FROM miner_info WHERE canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)),
+             (SELECT COUNT(*) FROM miner_info
+              WHERE is_mining = 1 AND canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL))",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+        let network_hash_rate: Option<f64> = conn.query_row(
+            "SELECT SUM(ms.last_hash_rate)
+             FROM mining_stats ms
+             JOIN miner_info m ON ms.canister_id = m.canister_id
+             JOIN canisters c ON ms.canister_id = c.canister_id
+             WHERE m.is_mining = 1 AND c.deleted_at IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        stats.network_hash_rate = network_hash_rate.unwrap_or(0.0);
+
+        let (blocks_mined, total_rewards): (Option<i64>, Option<i64>) = conn.query_row(
+            "SELECT SUM(blocks_mined), SUM(total_rewards) FROM mining_stats
+             WHERE canister_id IN (SELECT canister_id FROM canisters WHERE deleted_at IS NULL)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        stats.blocks_mined = blocks_mined.unwrap_or(0);
+        stats.total_rewards = total_rewards.unwrap_or(0);
+
+        Ok(stats)
+    }
+
+    async fn keys(&self) -> Result<RegistryKeys> {
+        let conn = self.conn()?;
+        let mut keys = RegistryKeys::new();
+        for table in RegistryTable::ALL {
+            let mut stmt = conn.prepare(&format!("SELECT {} FROM {}", table.key_column(), table))?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            keys.insert(table, rows.collect::<rusqlite::Result<HashSet<_>>>()?);
+        }
+        Ok(keys)
+    }
+
+    async fn save(&self, rows: &RegistryExport) -> Result<()> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()?;
+        for canister in &rows.canisters {
+            canister.save(&tx)?;
+        }
+        for token in &rows.token_info {
+            token.save(&tx)?;
+        }
+        for ledger in &rows.ledger_info {
+            ledger.save(&tx)?;
+        }
+        for miner in &rows.miner_info {
+            miner.save(&tx)?;
+        }
+        for stats in &rows.mining_stats {
+            stats.save(&tx)?;
+        }
+        for hash in &rows.verified_module_hashes {
+            hash.save(&tx)?;
+        }
+        Ok(tx.commit()?)
+    }
+}
@@ -1,6 +1,8 @@
 use rusqlite::{Connection, Result};
 use log::info;
 
+use crate::db::models::admin::Admin;
+
 /// Initialize the database schema
 pub fn init_db(conn: &Connection) -> Result<()> {
     info!("Initializing database schema");
@@ -19,6 +21,9 @@ pub fn init_db(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Canisters moved to the trash keep their rows until purged
+    add_column_if_missing(conn, "canisters", "deleted_at", "INTEGER")?;
+
     // Create token_info table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS token_info (
@@ -44,6 +49,81 @@ pub fn init_db(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Create canister_interfaces table, the candid:service metadata read at registration
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS canister_interfaces (
+            canister_id TEXT PRIMARY KEY,
+            candid_service TEXT NOT NULL,
+            last_updated INTEGER NOT NULL,
+            FOREIGN KEY (canister_id) REFERENCES canisters (canister_id)
+        )",
+        [],
+    )?;
+
+    // Create ledger_info table for ICRC-1 ledgers
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledger_info (
+            canister_id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            symbol TEXT NOT NULL,
+            decimals INTEGER NOT NULL,
+            fee TEXT NOT NULL,
+            total_supply TEXT NOT NULL,
+            logo TEXT,
+            supported_standards TEXT NOT NULL,
+            metadata TEXT NOT NULL,
+            last_updated INTEGER NOT NULL,
+            FOREIGN KEY (canister_id) REFERENCES canisters (canister_id)
+        )",
+        [],
+    )?;
+
+    // Create ledger_transactions table, filled by the ledger indexer
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledger_transactions (
+            canister_id TEXT NOT NULL,
+            block_index INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            from_owner TEXT,
+            from_subaccount TEXT,
+            to_owner TEXT,
+            to_subaccount TEXT,
+            spender TEXT,
+            amount TEXT NOT NULL,
+            fee TEXT,
+            memo TEXT,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (canister_id, block_index),
+            FOREIGN KEY (canister_id) REFERENCES canisters (canister_id)
+        )",
+        [],
+    )?;
+
+    // Create ledger_index_state table, the indexer's checkpoint per ledger
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledger_index_state (
+            canister_id TEXT PRIMARY KEY,
+            next_block INTEGER NOT NULL,
+            log_length INTEGER NOT NULL,
+            last_updated INTEGER NOT NULL,
+            FOREIGN KEY (canister_id) REFERENCES canisters (canister_id)
+        )",
+        [],
+    )?;
+
+    // Create ledger_balances table, account balances computed by the ledger indexer
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledger_balances (
+            canister_id TEXT NOT NULL,
+            owner TEXT NOT NULL,
+            subaccount TEXT NOT NULL DEFAULT '',
+            balance TEXT NOT NULL,
+            PRIMARY KEY (canister_id, owner, subaccount),
+            FOREIGN KEY (canister_id) REFERENCES canisters (canister_id)
+        )",
+        [],
+    )?;
+
     // Create miner_info table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS miner_info (
@@ -104,11 +184,83 @@ pub fn init_db(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Columns added to admins after the initial release
+    add_column_if_missing(conn, "admins", "previous_api_key", "TEXT")?;
+    add_column_if_missing(conn, "admins", "previous_api_key_expires_at", "INTEGER")?;
+    add_column_if_missing(conn, "admins", "last_used_at", "INTEGER")?;
+    add_column_if_missing(conn, "admins", "failed_login_attempts", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "admins", "locked_until", "INTEGER")?;
+    // Admins created before roles existed keep full access
+    add_column_if_missing(conn, "admins", "role", "TEXT NOT NULL DEFAULT 'superadmin'")?;
+    // API keys are stored as a lookup prefix plus a hash in the api_key columns
+    add_column_if_missing(conn, "admins", "api_key_prefix", "TEXT")?;
+    add_column_if_missing(conn, "admins", "previous_api_key_prefix", "TEXT")?;
+    Admin::migrate_plaintext_api_keys(conn)?;
+
+    // Create admin_sessions table for password logins
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS admin_sessions (
+            id TEXT PRIMARY KEY,
+            admin_id TEXT NOT NULL,
+            refresh_token_id TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            revoked_at INTEGER,
+            FOREIGN KEY (admin_id) REFERENCES admins (id)
+        )",
+        [],
+    )?;
+
+    // Create audit_log table recording every registry and admin mutation
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id TEXT PRIMARY KEY,
+            actor_type TEXT NOT NULL,
+            actor_id TEXT,
+            actor_name TEXT,
+            action TEXT NOT NULL,
+            target_type TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            before_state TEXT,
+            after_state TEXT,
+            ip_address TEXT,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create miner_commands table, control commands sent to miners with update calls
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS miner_commands (
+            id TEXT PRIMARY KEY,
+            canister_id TEXT NOT NULL,
+            command TEXT NOT NULL,
+            arguments TEXT NOT NULL,
+            status TEXT NOT NULL,
+            reply TEXT,
+            error TEXT,
+            admin_id TEXT NOT NULL,
+            admin_name TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            completed_at INTEGER
+        )",
+        [],
+    )?;
+
     // Create indices for faster lookups
     conn.execute("CREATE INDEX IF NOT EXISTS idx_canisters_type ON canisters (type)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_canisters_principal ON canisters (principal)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_canisters_deleted_at ON canisters (deleted_at)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ledger_transactions_from ON ledger_transactions (canister_id, from_owner)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ledger_transactions_to ON ledger_transactions (canister_id, to_owner)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_verified_module_hashes_hash ON verified_module_hashes (hash)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_verified_module_hashes_type ON verified_module_hashes (canister_type)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_admin_sessions_admin ON admin_sessions (admin_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_admins_api_key_prefix ON admins (api_key_prefix)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log (created_at)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log (target_type, target_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log (actor_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_miner_commands_canister ON miner_commands (canister_id, created_at)", [])?;
     
     // Insert default verified module hash
     conn.execute(
@@ -120,3 +272,19 @@ pub fn init_db(conn: &Connection) -> Result<()> {
     info!("Database schema initialized successfully");
     Ok(())
 }
+
+/// Add a column to an existing table if it is not there yet
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>("name"))?;
+    
+    for existing in columns {
+        if existing? == column {
+            return Ok(());
+        }
+    }
+    
+    info!("Adding column {}.{}", table, column);
+    conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    Ok(())
+}
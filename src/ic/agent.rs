@@ -1,21 +1,86 @@
 use ic_agent::Agent;
 use ic_agent::agent::http_transport::reqwest_transport::ReqwestHttpReplicaV2Transport;
-use ic_agent::identity::{AnonymousIdentity, Identity, Secp256k1Identity};
+use ic_agent::identity::{AnonymousIdentity, BasicIdentity, Identity, Secp256k1Identity};
 use anyhow::{Result, Context, anyhow};
+use chrono::Utc;
 use log::{info, warn, error};
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::fmt;
 use std::path::{Path, PathBuf};
-use std::fs::{File, create_dir_all};
-use std::io::{Read, Write};
-use std::sync::OnceLock;
-use ring::signature::Ed25519KeyPair;
+use std::fs::{self, create_dir_all, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use crate::ic::keystore::{self, LockedIdentityError};
 
 // Default identity directory and file path
 const DEFAULT_IDENTITY_DIR: &str = "data/identity";
 const DEFAULT_IDENTITY_FILE: &str = "identity.pem";
+// Directory next to the identity file that keeps the keys replaced by rotations
+const RETIRED_IDENTITY_DIR: &str = "retired";
+// Principal of the anonymous identity
+const ANONYMOUS_PRINCIPAL: &str = "2vxsx-fae";
 
 // Global identity file path
 static IDENTITY_FILE_PATH: OnceLock<String> = OnceLock::new();
 
+// The identity loaded from the identity file, replaced on rotation
+static SERVER_IDENTITY: RwLock<Option<IdentityInfo>> = RwLock::new(None);
+// Held for a whole rotation, so rotations do not interleave
+static ROTATION: Mutex<()> = Mutex::new(());
+
+/// Key type of an identity file
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+    /// PKCS#8 Ed25519 key, as generated by the server
+    Ed25519,
+    /// SEC1 or PKCS#8 Secp256k1 key, as generated by dfx
+    Secp256k1,
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyType::Ed25519 => write!(f, "Ed25519"),
+            KeyType::Secp256k1 => write!(f, "Secp256k1"),
+        }
+    }
+}
+
+/// An identity file and the principal it signs calls as
+#[derive(Debug, Serialize, Clone)]
+pub struct IdentityInfo {
+    pub principal: String,
+    pub key_type: KeyType,
+    pub path: String,
+    /// Whether the file is encrypted with the identity passphrase
+    pub encrypted: bool,
+}
+
+/// An identity replaced by a rotation, its key is kept so the principal can still be used
+///
+/// Listed from the file name, so encrypted keys are not decrypted just to show them.
+#[derive(Debug, Serialize, Clone)]
+pub struct RetiredIdentity {
+    pub principal: String,
+    pub path: String,
+    pub encrypted: bool,
+    pub retired_at: i64,
+}
+
+/// What `encrypt_identity_file` encrypted
+#[derive(Debug)]
+pub struct EncryptedIdentities {
+    /// The identity file, `None` if it was already encrypted
+    pub identity: Option<IdentityInfo>,
+    /// Retired key files that were still in plaintext
+    pub retired: Vec<String>,
+}
+
 /// Set the global identity file path
 pub fn set_identity_file_path(path: String) {
     let _ = IDENTITY_FILE_PATH.set(path);
@@ -26,6 +91,11 @@ pub fn get_identity_file_path() -> Option<&'static String> {
     IDENTITY_FILE_PATH.get()
 }
 
+/// The identity the server signs calls with, `None` if no identity file could be loaded
+pub fn server_identity() -> Option<IdentityInfo> {
+    SERVER_IDENTITY.read().unwrap().clone()
+}
+
 /// Initialize the identity system, ensuring a valid identity exists
 pub fn init_identity() -> Result<String> {
     // Check if identity directory exists, create if not
@@ -46,16 +116,35 @@ pub fn init_identity() -> Result<String> {
     } else {
         info!("Using existing identity file: {}", identity_path.display());
         // Validate the identity file
-        match validate_identity_file(&identity_path_str) {
+        match load_identity_info(&identity_path_str) {
             Ok(_) => info!("Identity file validated successfully"),
+            // Never replace a key that only lacks the right passphrase
+            Err(e) if e.downcast_ref::<LockedIdentityError>().is_some() => return Err(e),
             Err(e) => {
-                warn!("Invalid identity file: {}", e);
-                warn!("Creating new identity file");
+                // Keep the unreadable file, it may hold a key that controls canisters
+                let retired_dir = retired_identity_dir(&identity_path_str);
+                create_dir_all(&retired_dir).context("Failed to create retired identity directory")?;
+                let kept_path = retired_dir.join(format!("unreadable-{}.pem", Utc::now().timestamp()));
+                fs::rename(&identity_path, &kept_path).context("Failed to move the unreadable identity file aside")?;
+                error!("Identity file {} could not be loaded: {:#}", identity_path.display(), e);
+                error!("Moved it to {} and creating a new identity, the server principal changes", kept_path.display());
                 create_new_identity_file(&identity_path_str).context("Failed to create new identity file")?;
             }
         }
     }
     
+    let identity = load_identity_info(&identity_path_str)?;
+    info!("Server identity in effect: {} principal {} from {} ({})", identity.key_type, identity.principal, identity.path,
+        if identity.encrypted { "encrypted" } else { "plaintext" });
+    match plaintext_retired_identities(&identity_path_str) {
+        Ok(plaintext) if identity.encrypted && !plaintext.is_empty() => warn!(
+            "{} retired identity files in {} are not encrypted, run `encrypt-identity` to encrypt them",
+            plaintext.len(), retired_identity_dir(&identity_path_str).display()),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to check retired identities: {:#}", e),
+    }
+    *SERVER_IDENTITY.write().unwrap() = Some(identity);
+    
     // Set the global identity file path
     set_identity_file_path(identity_path_str.clone());
     
@@ -69,11 +158,14 @@ fn create_new_identity_file(path: &str) -> Result<()> {
     let pkcs8_bytes = ring::signature::Ed25519KeyPair::generate_pkcs8(&rng)
         .context("Failed to generate Ed25519 key pair")?;
     
-    // Encode as PEM
-    let pem = pem::encode(&pem::Pem {
+    // Encode as PEM, encrypted when a passphrase is configured
+    let mut pem = pem::encode(&pem::Pem {
         tag: "PRIVATE KEY".to_string(),
         contents: pkcs8_bytes.as_ref().to_vec(),
     });
+    if let Some(passphrase) = keystore::passphrase()? {
+        pem = keystore::encrypt(pem.as_bytes(), &passphrase)?;
+    }
     
     // Ensure the directory exists
     if let Some(parent) = Path::new(path).parent() {
@@ -83,42 +175,209 @@ fn create_new_identity_file(path: &str) -> Result<()> {
     }
     
     // Write the PEM to file
-    let mut file = File::create(path).context("Failed to create identity file")?;
-    file.write_all(pem.as_bytes()).context("Failed to write identity to file")?;
+    write_key_file(Path::new(path), pem.as_bytes()).context("Failed to write identity to file")?;
     
     info!("Created new identity file at: {}", path);
     Ok(())
 }
 
-/// Validate an identity file to ensure it's a valid PEM file
-fn validate_identity_file(path: &str) -> Result<()> {
-    // Try to read the file
-    let pem_path = Path::new(path);
-    let mut pem_file = File::open(pem_path).context("Failed to open PEM file")?;
-    let mut pem_content = Vec::new();
-    pem_file.read_to_end(&mut pem_content).context("Failed to read PEM file")?;
+/// Load an Ed25519 or Secp256k1 identity from a PEM file, decrypting it with the identity passphrase if needed
+pub fn load_identity(path: &str) -> Result<(Box<dyn Identity>, KeyType)> {
+    let pem_content = fs::read(path).context("Failed to read PEM file")?;
+    decode_identity(pem_content)
+}
+
+// Decode the contents of an identity file, decrypting them with the identity passphrase if needed
+fn decode_identity(mut pem_content: Vec<u8>) -> Result<(Box<dyn Identity>, KeyType)> {
+    if keystore::is_encrypted(&pem_content) {
+        let passphrase = keystore::passphrase()?
+            .ok_or_else(|| LockedIdentityError("set IDENTITY_PASSPHRASE or IDENTITY_PASSPHRASE_FILE".to_string()))?;
+        pem_content = keystore::decrypt(&pem_content, &passphrase)
+            .map_err(|e| LockedIdentityError(format!("{:#}", e)))?;
+    }
     
-    // Try to parse it as a Secp256k1Identity
+    if let Ok(identity) = BasicIdentity::from_pem(&*pem_content) {
+        return Ok((Box::new(identity), KeyType::Ed25519));
+    }
     match Secp256k1Identity::from_pem(&*pem_content) {
-        Ok(_) => return Ok(()),
-        Err(_) => {}
+        Ok(identity) => Ok((Box::new(identity), KeyType::Secp256k1)),
+        Err(e) => Err(anyhow!("Not an Ed25519 or Secp256k1 private key: {}", e)),
     }
+}
+
+// Load an identity file and derive its principal
+// Write a key file readable only by the owner
+fn write_key_file(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)?;
+    Ok(())
+}
+
+fn load_identity_info(path: &str) -> Result<IdentityInfo> {
+    let pem_content = fs::read(path).context("Failed to read PEM file")?;
+    let encrypted = keystore::is_encrypted(&pem_content);
+    let (identity, key_type) = decode_identity(pem_content)?;
+    let principal = identity.sender().map_err(|e| anyhow!("Failed to derive principal: {}", e))?;
+    Ok(IdentityInfo { principal: principal.to_text(), key_type, path: path.to_string(), encrypted })
+}
+
+/// Encrypt a plaintext identity file and the plaintext keys retired next to it in place with the identity passphrase
+///
+/// Defaults to the server identity file. The encrypted file is checked to load
+/// as the same principal before it replaces the plaintext one. Retired files,
+/// including unreadable ones kept at startup, are checked to decrypt to the same
+/// contents.
+pub fn encrypt_identity_file(path: Option<&str>) -> Result<EncryptedIdentities> {
+    let default_path = PathBuf::from(DEFAULT_IDENTITY_DIR).join(DEFAULT_IDENTITY_FILE).to_string_lossy().to_string();
+    let path = path.unwrap_or(&default_path);
+    let passphrase = keystore::passphrase()?
+        .ok_or_else(|| anyhow!("Set IDENTITY_PASSPHRASE or IDENTITY_PASSPHRASE_FILE to encrypt the identity"))?;
     
-    // If it fails as Secp256k1, try as Ed25519KeyPair
-    let pem_str = String::from_utf8(pem_content.clone())
-        .context("Failed to convert PEM content to string")?;
+    let pem_content = fs::read(path).with_context(|| format!("Failed to read identity file {}", path))?;
+    let plaintext_retired = plaintext_retired_identities(path)?;
+    if keystore::is_encrypted(&pem_content) && plaintext_retired.is_empty() {
+        return Err(anyhow!("Identity file {} and its retired keys are already encrypted", path));
+    }
     
-    // Try to parse the PEM
-    match pem::parse(pem_str) {
-        Ok(parsed_pem) => {
-            // Try to create an Ed25519KeyPair from the parsed PEM
-            match Ed25519KeyPair::from_pkcs8(&parsed_pem.contents) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(anyhow!("Invalid Ed25519 key pair: {:?}", e))
-            }
-        },
-        Err(e) => Err(anyhow!("Invalid PEM format: {}", e))
+    let identity = if keystore::is_encrypted(&pem_content) {
+        None
+    } else {
+        let plaintext = load_identity_info(path)?;
+        let new_path = format!("{}.new", path);
+        write_key_file(Path::new(&new_path), keystore::encrypt(&pem_content, &passphrase)?.as_bytes())
+            .context("Failed to write encrypted identity file")?;
+        let encrypted = load_identity_info(&new_path)?;
+        if encrypted.principal != plaintext.principal {
+            let _ = fs::remove_file(&new_path);
+            return Err(anyhow!("Encrypted identity does not match the plaintext one"));
+        }
+        fs::rename(&new_path, path).context("Failed to replace the plaintext identity file")?;
+        info!("Encrypted identity {} at {}", encrypted.principal, path);
+        Some(load_identity_info(path)?)
+    };
+    
+    let mut retired = Vec::new();
+    for retired_path in plaintext_retired {
+        encrypt_retired_identity(&retired_path, &passphrase)
+            .with_context(|| format!("Failed to encrypt retired identity {}", retired_path.display()))?;
+        info!("Encrypted retired identity at {}", retired_path.display());
+        retired.push(retired_path.to_string_lossy().to_string());
+    }
+    
+    Ok(EncryptedIdentities { identity, retired })
+}
+
+// Encrypt a retired key file in place, which may not hold a loadable key
+fn encrypt_retired_identity(path: &Path, passphrase: &str) -> Result<()> {
+    let pem_content = fs::read(path)?;
+    let encrypted = keystore::encrypt(&pem_content, passphrase)?;
+    if keystore::decrypt(encrypted.as_bytes(), passphrase)? != pem_content {
+        return Err(anyhow!("Encrypted identity does not match the plaintext one"));
     }
+    let new_path = path.with_extension("pem.new");
+    write_key_file(&new_path, encrypted.as_bytes())?;
+    fs::rename(&new_path, path)?;
+    Ok(())
+}
+
+/// Key files in the retired directory next to an identity file that are not encrypted
+pub fn plaintext_retired_identities(identity_path: &str) -> Result<Vec<PathBuf>> {
+    let retired_dir = retired_identity_dir(identity_path);
+    if !retired_dir.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let mut plaintext = Vec::new();
+    for entry in fs::read_dir(&retired_dir).context("Failed to read retired identity directory")? {
+        let file_path = entry?.path();
+        if file_path.extension() == Some(OsStr::new("pem"))
+            && !keystore::is_encrypted(&fs::read(&file_path)?)
+        {
+            plaintext.push(file_path);
+        }
+    }
+    plaintext.sort();
+    Ok(plaintext)
+}
+
+/// Replace the identity file with a new Ed25519 key, returning the previous and the new identity
+///
+/// The previous key is moved to the `retired` directory next to the identity file.
+/// Agents created afterwards sign with the new key. Generating and checking the
+/// key is slow for encrypted identities, so call it off the async workers.
+pub fn rotate_identity() -> Result<(IdentityInfo, IdentityInfo)> {
+    let path = get_identity_file_path().ok_or_else(|| anyhow!("No identity file is loaded"))?;
+    let _rotation = ROTATION.lock().unwrap();
+    let previous = match server_identity() {
+        Some(previous) => previous,
+        None => load_identity_info(path).context("Failed to load the current identity")?,
+    };
+    
+    // Write and check the new key before touching the current one
+    let new_path = format!("{}.new", path);
+    create_new_identity_file(&new_path)?;
+    let mut current = load_identity_info(&new_path).context("Failed to load the new identity")?;
+    
+    let retired_dir = retired_identity_dir(path);
+    create_dir_all(&retired_dir).context("Failed to create retired identity directory")?;
+    let retired_path = retired_dir.join(format!("{}-{}.pem", Utc::now().timestamp(), previous.principal));
+    fs::rename(path, &retired_path).context("Failed to retire the current identity file")?;
+    fs::rename(&new_path, path).context("Failed to move the new identity file in place")?;
+    
+    current.path = path.to_string();
+    info!("Rotated server identity from {} to {}, the previous key was kept at {}",
+        previous.principal, current.principal, retired_path.display());
+    *SERVER_IDENTITY.write().unwrap() = Some(current.clone());
+    
+    Ok((previous, current))
+}
+
+/// Identities replaced by rotations, most recently retired first
+pub fn retired_identities() -> Result<Vec<RetiredIdentity>> {
+    let path = match get_identity_file_path() {
+        Some(path) => path,
+        None => return Ok(Vec::new()),
+    };
+    let retired_dir = retired_identity_dir(path);
+    if !retired_dir.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let mut retired = Vec::new();
+    for entry in fs::read_dir(&retired_dir).context("Failed to read retired identity directory")? {
+        let file_path = entry?.path();
+        if file_path.extension() != Some(OsStr::new("pem")) {
+            continue;
+        }
+        // Files are named `<retired_at>-<principal>.pem`, unreadable identities kept at startup are skipped
+        let parsed = file_path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split_once('-'))
+            .and_then(|(timestamp, principal)| Some((timestamp.parse::<i64>().ok()?, principal.to_string())));
+        let Some((retired_at, principal)) = parsed else { continue };
+        match fs::read(&file_path) {
+            Ok(pem_content) => retired.push(RetiredIdentity {
+                principal,
+                path: file_path.to_string_lossy().to_string(),
+                encrypted: keystore::is_encrypted(&pem_content),
+                retired_at,
+            }),
+            Err(e) => warn!("Skipping unreadable retired identity {}: {}", file_path.display(), e),
+        }
+    }
+    retired.sort_by_key(|identity| std::cmp::Reverse(identity.retired_at));
+    Ok(retired)
+}
+
+fn retired_identity_dir(identity_path: &str) -> PathBuf {
+    Path::new(identity_path).parent().unwrap_or(Path::new(".")).join(RETIRED_IDENTITY_DIR)
 }
 
 /// Create an IC agent for interacting with canisters
@@ -131,9 +390,10 @@ pub async fn create_agent(url: &str) -> Result<Agent> {
         // Initialize identity system if no path is set
         match init_identity() {
             Ok(path) => path,
+            Err(e) if e.downcast_ref::<LockedIdentityError>().is_some() => return Err(e),
             Err(e) => {
-                error!("Failed to initialize identity: {}", e);
-                warn!("Falling back to anonymous identity");
+                error!("Failed to initialize identity: {:#}", e);
+                warn!("Falling back to the anonymous identity ({}), update calls will be rejected", ANONYMOUS_PRINCIPAL);
                 return create_agent_with_anonymous_identity(url).await;
             }
         }
@@ -142,9 +402,10 @@ pub async fn create_agent(url: &str) -> Result<Agent> {
     // Try to create an agent with the identity
     match create_agent_with_identity_from_pem(url, &identity_path).await {
         Ok(agent) => Ok(agent),
+        Err(e) if e.downcast_ref::<LockedIdentityError>().is_some() => Err(e),
         Err(e) => {
-            warn!("Failed to create agent with identity file {}: {}", identity_path, e);
-            warn!("Falling back to anonymous identity");
+            warn!("Failed to create agent with identity file {}: {:#}", identity_path, e);
+            warn!("Falling back to the anonymous identity ({}), update calls will be rejected", ANONYMOUS_PRINCIPAL);
             create_agent_with_anonymous_identity(url).await
         }
     }
@@ -194,20 +455,61 @@ pub async fn create_agent_with_identity(url: &str, identity: Box<dyn Identity>)
     Ok(agent)
 }
 
-/// Create an IC agent with a Secp256k1 identity from a PEM file
+/// Create an IC agent with an Ed25519 or Secp256k1 identity from a PEM file
 pub async fn create_agent_with_identity_from_pem(url: &str, pem_file_path: &str) -> Result<Agent> {
     info!("Creating IC agent with identity from PEM file: {}", pem_file_path);
     
-    // Read the PEM file
-    let pem_path = Path::new(pem_file_path);
-    let mut pem_file = File::open(pem_path).context("Failed to open PEM file")?;
-    let mut pem_content = Vec::new();
-    pem_file.read_to_end(&mut pem_content).context("Failed to read PEM file")?;
-    
     // Create the identity
-    let identity = Secp256k1Identity::from_pem(&*pem_content)
-        .context("Failed to create Secp256k1Identity from PEM file")?;
+    let (identity, key_type) = load_identity(pem_file_path)?;
+    let principal = identity.sender().map_err(|e| anyhow!("Failed to derive principal: {}", e))?;
+    info!("Signing IC calls with {} identity {}", key_type, principal.to_text());
     
     // Create the agent with the identity
-    create_agent_with_identity(url, Box::new(identity)).await
+    create_agent_with_identity(url, identity).await
+}
+
+/// Check that the IC is reachable with a cheap status call
+/// Returns the replica health status reported by the boundary node
+pub async fn check_ic_status(url: &str) -> Result<String> {
+    // Create the transport
+    let transport = ReqwestHttpReplicaV2Transport::create(url)
+        .context("Failed to create transport")?;
+    
+    // The status endpoint is unauthenticated, so no identity or root key is needed
+    let agent = Agent::builder()
+        .with_transport(transport)
+        .with_identity(AnonymousIdentity)
+        .build()
+        .context("Failed to build agent")?;
+    
+    let status = agent.status().await.context("Failed to fetch IC status")?;
+    
+    Ok(status.replica_health_status.unwrap_or_else(|| "unknown".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plaintext_retired_identities_are_encrypted_in_place() {
+        let dir = std::env::temp_dir().join(format!("identity-{}", uuid::Uuid::new_v4()));
+        let identity_path = dir.join(DEFAULT_IDENTITY_FILE).to_string_lossy().to_string();
+        let retired_dir = retired_identity_dir(&identity_path);
+        create_dir_all(&retired_dir).unwrap();
+        // Unreadable files kept at startup are encrypted too, even though they hold no usable key
+        let unreadable = retired_dir.join("unreadable-1700000000.pem");
+        fs::write(&unreadable, "not a key").unwrap();
+
+        assert_eq!(plaintext_retired_identities(&identity_path).unwrap(), vec![unreadable.clone()]);
+        encrypt_retired_identity(&unreadable, "correct passphrase").unwrap();
+        assert!(plaintext_retired_identities(&identity_path).unwrap().is_empty());
+
+        let encrypted = fs::read(&unreadable).unwrap();
+        assert_eq!(keystore::decrypt(&encrypted, "correct passphrase").unwrap(), b"not a key");
+        #[cfg(unix)]
+        assert_eq!(fs::metadata(&unreadable).unwrap().permissions().mode() & 0o777, 0o600);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,180 @@
+use anyhow::{Result, Context};
+use candid::parser::types::FuncMode;
+use candid::types::internal::TypeContainer;
+use candid::types::{Function, Type, TypeInner};
+use candid::{IDLProg, Nat, TypeEnv};
+use std::collections::HashSet;
+
+use crate::db::models::canister::CanisterType;
+
+use crate::ic::candid::ledger::{
+    Account, GetBlocksArgs, GetBlocksResult, GetTransactionsRequest, GetTransactionsResponse, MetadataValue,
+    StandardRecord,
+};
+use crate::ic::candid::miner::{MiningStats, Result as InfoResult};
+use crate::ic::candid::token::AllInfoResult;
+
+// Interface of the Claude API canister, which is written in Motoko rather than typed here
+const CLAUDE_API_DID: &str = include_str!("../../../static/claude_api.did");
+
+/// Canister interfaces served under `/interfaces`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interface {
+    Token,
+    Miner,
+    Ledger,
+    ClaudeApi,
+}
+
+/// Languages an interface can be rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Candid,
+    TypeScript,
+    Motoko,
+}
+
+impl Binding {
+    pub const ALL: [Binding; 3] = [Binding::Candid, Binding::TypeScript, Binding::Motoko];
+
+    /// File extension, matching the files `dfx generate` writes
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Binding::Candid => ".did",
+            Binding::TypeScript => ".did.d.ts",
+            Binding::Motoko => ".mo",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Binding::Candid => "text/plain; charset=utf-8",
+            Binding::TypeScript => "application/typescript; charset=utf-8",
+            Binding::Motoko => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+impl Interface {
+    pub const ALL: [Interface; 4] = [Interface::Token, Interface::Miner, Interface::Ledger, Interface::ClaudeApi];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interface::Token => "token",
+            Interface::Miner => "miner",
+            Interface::Ledger => "ledger",
+            Interface::ClaudeApi => "claude_api",
+        }
+    }
+
+    /// Canister type of the canisters implementing the interface
+    pub fn canister_type(&self) -> Option<CanisterType> {
+        match self {
+            Interface::Token => Some(CanisterType::Token),
+            Interface::Miner => Some(CanisterType::Miner),
+            Interface::Ledger => Some(CanisterType::Ledger),
+            Interface::ClaudeApi => None,
+        }
+    }
+
+    // Methods the registry calls, a canister must have all of them to be detected as this interface
+    fn required_methods(&self) -> &'static [&'static str] {
+        match self {
+            Interface::Token => &["get_all_info"],
+            Interface::Miner => &["get_info", "get_mining_stats"],
+            Interface::Ledger => &[
+                "icrc1_metadata", "icrc1_name", "icrc1_symbol", "icrc1_decimals", "icrc1_fee",
+                "icrc1_total_supply", "icrc1_supported_standards", "icrc1_balance_of",
+            ],
+            Interface::ClaudeApi => &[],
+        }
+    }
+
+    /// Interfaces implemented by a canister with the given Candid service definition, e.g.
+    /// its `candid:service` metadata
+    ///
+    /// A canister implements an interface if it has all the methods the registry calls. The
+    /// result is ordered like `ALL`, so a token is preferred over a miner and a miner over a ledger.
+    pub fn implemented_by(candid_service: &str) -> Result<Vec<Interface>> {
+        let prog: IDLProg = candid_service.parse().context("Failed to parse Candid service definition")?;
+        let mut env = TypeEnv::new();
+        let actor = candid::check_prog(&mut env, &prog)
+            .context("Failed to check Candid service definition")?
+            .context("Candid definition has no service")?;
+        let methods: HashSet<&str> = env.as_service(&actor)
+            .context("Candid definition has no service")?
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        Ok(Interface::ALL.into_iter().filter(|interface| {
+            let required = interface.required_methods();
+            !required.is_empty() && required.iter().all(|method| methods.contains(method))
+        }).collect())
+    }
+
+    /// Find the interface and binding named by a file such as `ledger.did.d.ts`
+    pub fn from_file_name(file_name: &str) -> Option<(Interface, Binding)> {
+        Interface::ALL.iter().find_map(|interface| {
+            let binding = Binding::ALL.iter().find(|binding| {
+                file_name.strip_prefix(interface.name()) == Some(binding.extension())
+            })?;
+            Some((*interface, *binding))
+        })
+    }
+
+    /// Render the interface in a binding language
+    pub fn render(&self, binding: Binding) -> Result<String> {
+        let (env, service) = self.service()?;
+        let actor = Some(service);
+        Ok(match binding {
+            Binding::Candid => candid::bindings::candid::compile(&env, &actor),
+            Binding::TypeScript => candid::bindings::typescript::compile(&env, &actor),
+            Binding::Motoko => candid::bindings::motoko::compile(&env, &actor),
+        })
+    }
+
+    // Type environment and service type, built from the types used to call the canisters
+    fn service(&self) -> Result<(TypeEnv, Type)> {
+        let mut types = TypeContainer::new();
+        let methods = match self {
+            Interface::Token => vec![
+                query("get_all_info", vec![], vec![types.add::<AllInfoResult>()]),
+            ],
+            Interface::Miner => vec![
+                query("get_info", vec![], vec![types.add::<InfoResult>()]),
+                query("get_mining_stats", vec![], vec![types.add::<Option<MiningStats>>()]),
+            ],
+            Interface::Ledger => vec![
+                query("icrc1_metadata", vec![], vec![types.add::<Vec<(String, MetadataValue)>>()]),
+                query("icrc1_name", vec![], vec![types.add::<String>()]),
+                query("icrc1_symbol", vec![], vec![types.add::<String>()]),
+                query("icrc1_decimals", vec![], vec![types.add::<u8>()]),
+                query("icrc1_fee", vec![], vec![types.add::<Nat>()]),
+                query("icrc1_total_supply", vec![], vec![types.add::<Nat>()]),
+                query("icrc1_supported_standards", vec![], vec![types.add::<Vec<StandardRecord>>()]),
+                query("icrc1_balance_of", vec![types.add::<Account>()], vec![types.add::<Nat>()]),
+                query("icrc3_get_blocks", vec![types.add::<Vec<GetBlocksArgs>>()], vec![types.add::<GetBlocksResult>()]),
+                query(
+                    "get_transactions",
+                    vec![types.add::<GetTransactionsRequest>()],
+                    vec![types.add::<GetTransactionsResponse>()],
+                ),
+            ],
+            Interface::ClaudeApi => {
+                let prog: IDLProg = CLAUDE_API_DID.parse().context("Failed to parse claude_api.did")?;
+                let mut env = TypeEnv::new();
+                let service = candid::check_prog(&mut env, &prog)
+                    .context("Failed to check claude_api.did")?
+                    .context("claude_api.did has no service")?;
+                return Ok((env, service));
+            }
+        };
+        Ok((types.env, TypeInner::Service(methods).into()))
+    }
+}
+
+// A query method of a service
+fn query(name: &str, args: Vec<Type>, rets: Vec<Type>) -> (String, Type) {
+    let method = Function { modes: vec![FuncMode::Query], args, rets };
+    (name.to_string(), TypeInner::Func(method).into())
+}
@@ -0,0 +1,199 @@
+use anyhow::{anyhow, bail, Context, Result};
+use candid::parser::types::FuncMode;
+use candid::types::value::{IDLField, VariantValue};
+use candid::types::{Function, Label, Type, TypeInner};
+use candid::{IDLArgs, IDLProg, IDLValue, Int, Nat, Principal, TypeEnv};
+use serde_json::{Map, Number, Value};
+
+/// A method of a canister's Candid service, called with JSON values
+///
+/// Arguments are converted to Candid following the method's argument types, so
+/// JSON numbers and strings can stand for any number type and `null` for `opt`.
+/// `nat`, `int`, `nat64` and `int64` results are returned as decimal strings,
+/// records as objects and variants as objects with a single key.
+pub struct CandidMethod {
+    env: TypeEnv,
+    function: Function,
+}
+
+impl CandidMethod {
+    /// Find `method` in a Candid service definition, e.g. a canister's `candid:service` metadata
+    pub fn from_service(candid_service: &str, method: &str) -> Result<Self> {
+        let prog: IDLProg = candid_service.parse().context("Failed to parse Candid service definition")?;
+        let mut env = TypeEnv::new();
+        let actor = candid::check_prog(&mut env, &prog)
+            .context("Failed to check Candid service definition")?
+            .context("Candid definition has no service")?;
+        let function = env.get_method(&actor, method)
+            .map_err(|_| anyhow!("The canister has no method {}", method))?
+            .clone();
+        Ok(Self { env, function })
+    }
+
+    /// Whether the method is annotated `query` or `composite_query`
+    pub fn is_query(&self) -> bool {
+        self.function.modes.iter().any(|mode| matches!(mode, FuncMode::Query | FuncMode::CompositeQuery))
+    }
+
+    /// Encode JSON arguments, trailing `opt` arguments may be left out
+    pub fn encode_args(&self, args: &[Value]) -> Result<Vec<u8>> {
+        if args.len() > self.function.args.len() {
+            bail!("Expected at most {} arguments, got {}", self.function.args.len(), args.len());
+        }
+        let values = self.function.args.iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                to_idl(&self.env, ty, args.get(i).unwrap_or(&Value::Null))
+                    .with_context(|| format!("Invalid argument {}", i))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        IDLArgs::new(&values)
+            .to_bytes_with_types(&self.env, &self.function.args)
+            .context("Failed to encode arguments")
+    }
+
+    /// Decode a reply as JSON: the value for methods with one result, an array otherwise
+    pub fn decode_reply(&self, bytes: &[u8]) -> Result<Value> {
+        let reply = IDLArgs::from_bytes_with_types(bytes, &self.env, &self.function.rets)
+            .context("Failed to decode reply")?;
+        let mut values: Vec<Value> = reply.args.iter().map(to_json).collect();
+        Ok(if values.len() == 1 { values.remove(0) } else { Value::Array(values) })
+    }
+}
+
+// Convert a JSON value to a Candid value of type `ty`
+fn to_idl(env: &TypeEnv, ty: &Type, json: &Value) -> Result<IDLValue> {
+    let ty = env.trace_type(ty)?;
+    let value = match (ty.as_ref(), json) {
+        (TypeInner::Null, _) => IDLValue::Null,
+        (TypeInner::Reserved, _) => IDLValue::Reserved,
+        (TypeInner::Bool, Value::Bool(b)) => IDLValue::Bool(*b),
+        (TypeInner::Text, Value::String(s)) => IDLValue::Text(s.clone()),
+        (TypeInner::Principal, Value::String(s)) => IDLValue::Principal(principal(s)?),
+        (TypeInner::Service(_), Value::String(s)) => IDLValue::Service(principal(s)?),
+        (TypeInner::Nat, _) => IDLValue::Nat(match json {
+            Value::Number(n) => Nat::from(n.as_u64().ok_or_else(|| anyhow!("Expected a natural number"))?),
+            _ => number_text(json)?.parse::<Nat>().map_err(|_| anyhow!("Expected a natural number"))?,
+        }),
+        (TypeInner::Int, _) => IDLValue::Int(match json {
+            Value::Number(n) => Int::from(n.as_i64().ok_or_else(|| anyhow!("Expected an integer"))?),
+            _ => number_text(json)?.parse::<Int>().map_err(|_| anyhow!("Expected an integer"))?,
+        }),
+        (TypeInner::Nat8, _) => IDLValue::Nat8(number_text(json)?.parse()?),
+        (TypeInner::Nat16, _) => IDLValue::Nat16(number_text(json)?.parse()?),
+        (TypeInner::Nat32, _) => IDLValue::Nat32(number_text(json)?.parse()?),
+        (TypeInner::Nat64, _) => IDLValue::Nat64(number_text(json)?.parse()?),
+        (TypeInner::Int8, _) => IDLValue::Int8(number_text(json)?.parse()?),
+        (TypeInner::Int16, _) => IDLValue::Int16(number_text(json)?.parse()?),
+        (TypeInner::Int32, _) => IDLValue::Int32(number_text(json)?.parse()?),
+        (TypeInner::Int64, _) => IDLValue::Int64(number_text(json)?.parse()?),
+        (TypeInner::Float32, _) => IDLValue::Float32(number_text(json)?.parse()?),
+        (TypeInner::Float64, _) => IDLValue::Float64(number_text(json)?.parse()?),
+        (TypeInner::Opt(_), Value::Null) => IDLValue::None,
+        (TypeInner::Opt(inner), _) => IDLValue::Opt(Box::new(to_idl(env, inner, json)?)),
+        (TypeInner::Vec(inner), Value::Array(items)) => IDLValue::Vec(
+            items.iter().map(|item| to_idl(env, inner, item)).collect::<Result<_>>()?
+        ),
+        (TypeInner::Record(fields), Value::Object(_) | Value::Array(_)) => IDLValue::Record(
+            fields.iter()
+                .map(|field| {
+                    let label: &Label = &field.id;
+                    let item = field_value(json, label).unwrap_or(&Value::Null);
+                    let val = to_idl(env, &field.ty, item)
+                        .with_context(|| format!("Invalid field {}", label_name(label)))?;
+                    Ok(IDLField { id: label.clone(), val })
+                })
+                .collect::<Result<_>>()?
+        ),
+        (TypeInner::Variant(fields), _) => {
+            // `{"Name": value}`, or just `"Name"` for a case without a value
+            let (name, item) = match json {
+                Value::String(name) => (name.as_str(), &Value::Null),
+                Value::Object(object) if object.len() == 1 => {
+                    let (name, item) = object.iter().next().unwrap();
+                    (name.as_str(), item)
+                }
+                _ => bail!("Expected a variant, an object with a single key"),
+            };
+            let (index, field) = fields.iter()
+                .enumerate()
+                .find(|(_, field)| label_name(&field.id) == name)
+                .ok_or_else(|| anyhow!("Unknown variant case {}", name))?;
+            let val = to_idl(env, &field.ty, item)
+                .with_context(|| format!("Invalid variant case {}", name))?;
+            let label: &Label = &field.id;
+            IDLValue::Variant(VariantValue(Box::new(IDLField { id: label.clone(), val }), index as u64))
+        }
+        (TypeInner::Bool | TypeInner::Text | TypeInner::Principal | TypeInner::Service(_)
+            | TypeInner::Vec(_) | TypeInner::Record(_), _) => bail!("Expected a {} value, got {}", ty, json),
+        _ => bail!("Arguments of type {} are not supported", ty),
+    };
+    Ok(value)
+}
+
+// Numbers may be given as JSON numbers or as strings, e.g. for values above 2^53
+fn number_text(json: &Value) -> Result<String> {
+    match json {
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.replace('_', "")),
+        _ => bail!("Expected a number, got {}", json),
+    }
+}
+
+fn principal(text: &str) -> Result<Principal> {
+    Principal::from_text(text).map_err(|e| anyhow!("Invalid principal {}: {}", text, e))
+}
+
+// The JSON value of a record field, by name or, for tuples, by position
+fn field_value<'a>(json: &'a Value, label: &Label) -> Option<&'a Value> {
+    match (json, label) {
+        (Value::Array(items), Label::Unnamed(i)) => items.get(*i as usize),
+        (Value::Object(object), label) => object.get(&label_name(label)),
+        _ => None,
+    }
+}
+
+fn label_name(label: &Label) -> String {
+    match label {
+        Label::Named(name) => name.clone(),
+        Label::Id(id) | Label::Unnamed(id) => id.to_string(),
+    }
+}
+
+// Convert a decoded Candid value to JSON
+fn to_json(value: &IDLValue) -> Value {
+    match value {
+        IDLValue::Null | IDLValue::None | IDLValue::Reserved => Value::Null,
+        IDLValue::Bool(b) => Value::Bool(*b),
+        IDLValue::Text(s) => Value::String(s.clone()),
+        IDLValue::Principal(p) | IDLValue::Service(p) => Value::String(p.to_text()),
+        IDLValue::Nat(n) => Value::String(n.0.to_string()),
+        IDLValue::Int(i) => Value::String(i.0.to_string()),
+        IDLValue::Nat64(n) => Value::String(n.to_string()),
+        IDLValue::Int64(i) => Value::String(i.to_string()),
+        IDLValue::Nat8(n) => Value::from(*n),
+        IDLValue::Nat16(n) => Value::from(*n),
+        IDLValue::Nat32(n) => Value::from(*n),
+        IDLValue::Int8(i) => Value::from(*i),
+        IDLValue::Int16(i) => Value::from(*i),
+        IDLValue::Int32(i) => Value::from(*i),
+        IDLValue::Float32(f) => Number::from_f64(*f as f64).map(Value::Number).unwrap_or(Value::Null),
+        IDLValue::Float64(f) => Number::from_f64(*f).map(Value::Number).unwrap_or(Value::Null),
+        IDLValue::Opt(inner) => to_json(inner),
+        IDLValue::Vec(items) => Value::Array(items.iter().map(to_json).collect()),
+        IDLValue::Record(fields) => {
+            // Tuples become arrays
+            if !fields.is_empty() && fields.iter().all(|field| matches!(field.id, Label::Unnamed(_))) {
+                Value::Array(fields.iter().map(|field| to_json(&field.val)).collect())
+            } else {
+                Value::Object(fields.iter().map(|field| (label_name(&field.id), to_json(&field.val))).collect())
+            }
+        }
+        IDLValue::Variant(VariantValue(field, _)) => {
+            let mut object = Map::new();
+            object.insert(label_name(&field.id), to_json(&field.val));
+            Value::Object(object)
+        }
+        other => Value::String(other.to_string()),
+    }
+}
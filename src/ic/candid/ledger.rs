@@ -0,0 +1,188 @@
+use candid::parser::types::FuncMode;
+use candid::types::{Function, Serializer, Type, TypeInner};
+use candid::{CandidType, Deserialize, Func, Int, Nat, Principal};
+use serde::Serialize;
+
+/// Value of an `icrc1_metadata` entry
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum MetadataValue {
+    Nat(Nat),
+    Int(Int),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Entry of `icrc1_supported_standards`
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct StandardRecord {
+    pub name: String,
+    pub url: String,
+}
+
+/// ICRC-1 account, a `None` subaccount is the default (all-zero) subaccount
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Vec<u8>>,
+}
+
+/// Range of blocks requested from `icrc3_get_blocks`
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct GetBlocksArgs {
+    pub start: Nat,
+    pub length: Nat,
+}
+
+/// Generic block value defined by ICRC-3
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub enum Icrc3Value {
+    Blob(Vec<u8>),
+    Text(String),
+    Nat(Nat),
+    Int(Int),
+    Array(Vec<Icrc3Value>),
+    Map(Vec<(String, Icrc3Value)>),
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct BlockWithId {
+    pub id: Nat,
+    pub block: Icrc3Value,
+}
+
+/// Archive query serving `icrc3_get_blocks` ranges
+///
+/// `Func` alone has the type `func () -> ()`, which a typed callback is not a subtype of.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct GetBlocksCallback(pub Func);
+
+impl CandidType for GetBlocksCallback {
+    fn _ty() -> Type {
+        query_func(vec![Vec::<GetBlocksArgs>::ty()], vec![GetBlocksResult::ty()])
+    }
+
+    fn idl_serialize<S>(&self, serializer: S) -> Result<(), S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.idl_serialize(serializer)
+    }
+}
+
+/// Blocks moved to an archive canister, fetched by calling `callback` with `args`
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchivedBlocks {
+    pub args: Vec<GetBlocksArgs>,
+    pub callback: GetBlocksCallback,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetBlocksResult {
+    pub log_length: Nat,
+    pub blocks: Vec<BlockWithId>,
+    pub archived_blocks: Vec<ArchivedBlocks>,
+}
+
+/// Range of transactions requested from `get_transactions`
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct GetTransactionsRequest {
+    pub start: Nat,
+    pub length: Nat,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Mint {
+    pub to: Account,
+    pub amount: Nat,
+    pub created_at_time: Option<u64>,
+    pub memo: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Burn {
+    pub from: Account,
+    pub amount: Nat,
+    pub spender: Option<Account>,
+    pub created_at_time: Option<u64>,
+    pub memo: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Transfer {
+    pub from: Account,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub spender: Option<Account>,
+    pub created_at_time: Option<u64>,
+    pub memo: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Approve {
+    pub from: Account,
+    pub spender: Account,
+    pub amount: Nat,
+    pub expected_allowance: Option<Nat>,
+    pub expires_at: Option<u64>,
+    pub fee: Option<Nat>,
+    pub created_at_time: Option<u64>,
+    pub memo: Option<Vec<u8>>,
+}
+
+/// Transaction returned by `get_transactions`, `kind` tells which of the records is set
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Transaction {
+    pub kind: String,
+    pub mint: Option<Mint>,
+    pub burn: Option<Burn>,
+    pub transfer: Option<Transfer>,
+    pub approve: Option<Approve>,
+    pub timestamp: u64,
+}
+
+/// Archive query serving `get_transactions` ranges
+#[derive(Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct GetTransactionsCallback(pub Func);
+
+impl CandidType for GetTransactionsCallback {
+    fn _ty() -> Type {
+        query_func(vec![GetTransactionsRequest::ty()], vec![TransactionRange::ty()])
+    }
+
+    fn idl_serialize<S>(&self, serializer: S) -> Result<(), S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.idl_serialize(serializer)
+    }
+}
+
+/// Transactions moved to an archive canister, fetched by calling `callback`
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchivedTransactions {
+    pub start: Nat,
+    pub length: Nat,
+    pub callback: GetTransactionsCallback,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetTransactionsResponse {
+    pub log_length: Nat,
+    pub first_index: Nat,
+    pub transactions: Vec<Transaction>,
+    pub archived_transactions: Vec<ArchivedTransactions>,
+}
+
+/// Response of an archive's `get_transactions` callback
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct TransactionRange {
+    pub transactions: Vec<Transaction>,
+}
+
+// Type of a query method reference
+fn query_func(args: Vec<Type>, rets: Vec<Type>) -> Type {
+    TypeInner::Func(Function { modes: vec![FuncMode::Query], args, rets }).into()
+}
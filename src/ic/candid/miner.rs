@@ -38,30 +38,3 @@ pub enum StatsResult {
     Ok(Option<MiningStats>),
     Err(String),
 }
-
-// Define the interface for the miner canister
-pub fn miner_interface() -> candid::IDLValue {
-    candid::IDLValue::Text(r#"
-    type MinerInfo = record {
-      speed_percentage : nat8;
-      current_token : opt principal;
-      chunks_per_refresh : nat64;
-      miner_type : MinerType;
-      is_mining : bool;
-    };
-    type MinerType = variant { Premium; Lite; Normal };
-    type MiningStats = record {
-      total_hashes : nat64;
-      blocks_mined : nat64;
-      chunks_since_refresh : nat64;
-      total_rewards : nat64;
-      last_hash_rate : float64;
-      start_time : nat64;
-    };
-    type Result = variant { Ok : MinerInfo; Err : text };
-    service : {
-      get_info : () -> (Result) query;
-      get_mining_stats : () -> (opt MiningStats) query;
-    }
-    "#.to_string())
-} 
\ No newline at end of file
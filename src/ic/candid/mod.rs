@@ -1,2 +1,5 @@
 pub mod token;
-pub mod miner; 
\ No newline at end of file
+pub mod miner;
+pub mod ledger;
+pub mod interface;
+pub mod json;
@@ -64,64 +64,3 @@ pub enum AllInfoResult {
     Ok(TokenAllInfo),
     Err(String),
 }
-
-// Define the interface for the token canister
-pub fn token_interface() -> candid::IDLValue {
-    candid::IDLValue::Text(r#"
-    type ArchiveOptions = record {
-      num_blocks_to_archive : nat64;
-      max_transactions_per_response : opt nat64;
-      trigger_threshold : nat64;
-      more_controller_ids : opt vec principal;
-      max_message_size_bytes : opt nat64;
-      cycles_for_archive_creation : opt nat64;
-      node_max_memory_size_bytes : opt nat64;
-      controller_id : principal;
-    };
-    type Result = variant {
-      Ok : TokenInfo;
-      Err : text;
-    };
-    type SocialLink = record {
-      url : text;
-      platform : text;
-    };
-    type TokenInfo = record {
-      decimals : nat8;
-      ticker : text;
-      transfer_fee : nat64;
-      logo : opt text;
-      name : text;
-      ledger_id : opt principal;
-      archive_options : opt ArchiveOptions;
-      total_supply : nat64;
-      social_links : opt vec SocialLink;
-    };
-    type TokenAllInfo = record {
-      name : text;
-      ticker : text;
-      total_supply : nat64;
-      ledger_id : opt principal;
-      logo : opt text;
-      decimals : nat8;
-      transfer_fee : nat64;
-      social_links : opt vec SocialLink;
-      average_block_time : opt float64;
-      formatted_block_time : opt text;
-      block_time_rating : opt text;
-      circulating_supply : nat64;
-      mining_progress_percentage : text;
-      current_block_reward : nat64;
-      formatted_block_reward : text;
-      principal : principal;
-      current_block_height : nat64;
-    };
-    type AllInfoResult = variant {
-      Ok : TokenAllInfo;
-      Err : text;
-    };
-    service : {
-      get_all_info : () -> (AllInfoResult) query;
-    }
-    "#.to_string())
-}
@@ -0,0 +1,109 @@
+use anyhow::{Result, Context};
+use async_trait::async_trait;
+use ic_agent::Agent;
+use tokio::sync::Mutex;
+
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::ic::agent::create_agent;
+use crate::ic::services::{call, ledger, miner, module_hash, token};
+use crate::ic::services::ledger::{BlockMethod, LedgerBlocks};
+
+/// IC mainnet boundary node used for all canister calls
+pub const IC_URL: &str = "https://ic0.app";
+
+/// Canister calls made by the registry
+///
+/// Handlers get it as `web::Data<dyn IcClient>` and jobs as `Arc<dyn IcClient>`,
+/// so tests can stand in a client that does not touch the network.
+#[async_trait]
+pub trait IcClient: Send + Sync {
+    /// Query `get_all_info` on a token canister
+    async fn get_token_all_info(&self, canister_id: &str) -> Result<TokenInfo>;
+    /// Query the `icrc1_*` methods of an ICRC-1 ledger canister
+    async fn get_ledger_info(&self, canister_id: &str) -> Result<LedgerInfo>;
+    /// Read blocks of an ICRC-1 ledger canister, following its archives
+    async fn get_ledger_blocks(&self, canister_id: &str, method: BlockMethod, start: u64, length: u64) -> Result<LedgerBlocks>;
+    /// Query `icrc1_balance_of` on an ICRC-1 ledger canister
+    async fn get_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<String>;
+    /// Query `get_info` and `get_mining_stats` on a miner canister
+    async fn get_miner_info(&self, canister_id: &str) -> Result<(MinerInfo, Option<MiningStats>)>;
+    /// Read the module hash of any canister from the state tree
+    async fn get_module_hash(&self, canister_id: &str) -> Result<String>;
+    /// Read the `candid:service` metadata of any canister from the state tree
+    async fn get_candid_service(&self, canister_id: &str) -> Result<String>;
+    /// Query any method with Candid-encoded arguments, returning the encoded reply
+    async fn query_raw(&self, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>>;
+    /// Make an update call from the server identity with Candid-encoded arguments, returning the encoded reply
+    async fn update_raw(&self, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>>;
+    /// Drop the current agent so the next call signs with the identity file's current key
+    async fn reset_agent(&self);
+}
+
+/// Client calling the IC through an agent with the server identity
+pub struct AgentIcClient {
+    url: String,
+    // Created on first use and kept until the identity changes, creating an agent fetches the root key
+    agent: Mutex<Option<Agent>>,
+}
+
+impl AgentIcClient {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), agent: Mutex::new(None) }
+    }
+
+    async fn agent(&self) -> Result<Agent> {
+        let mut agent = self.agent.lock().await;
+        if let Some(agent) = agent.as_ref() {
+            return Ok(agent.clone());
+        }
+        let created = create_agent(&self.url).await.context("Failed to create IC agent")?;
+        *agent = Some(created.clone());
+        Ok(created)
+    }
+}
+
+#[async_trait]
+impl IcClient for AgentIcClient {
+    async fn get_token_all_info(&self, canister_id: &str) -> Result<TokenInfo> {
+        token::get_token_all_info(&self.agent().await?, canister_id).await
+    }
+
+    async fn get_ledger_info(&self, canister_id: &str) -> Result<LedgerInfo> {
+        ledger::get_ledger_info(&self.agent().await?, canister_id).await
+    }
+
+    async fn get_ledger_blocks(&self, canister_id: &str, method: BlockMethod, start: u64, length: u64) -> Result<LedgerBlocks> {
+        ledger::get_ledger_blocks(&self.agent().await?, canister_id, method, start, length).await
+    }
+
+    async fn get_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<String> {
+        ledger::get_balance(&self.agent().await?, canister_id, owner, subaccount).await
+    }
+
+    async fn get_miner_info(&self, canister_id: &str) -> Result<(MinerInfo, Option<MiningStats>)> {
+        miner::get_miner_info(&self.agent().await?, canister_id).await
+    }
+
+    async fn get_module_hash(&self, canister_id: &str) -> Result<String> {
+        module_hash::get_module_hash(&self.agent().await?, canister_id).await
+    }
+
+    async fn get_candid_service(&self, canister_id: &str) -> Result<String> {
+        module_hash::get_candid_service(&self.agent().await?, canister_id).await
+    }
+
+    async fn query_raw(&self, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>> {
+        call::query_raw(&self.agent().await?, canister_id, method, arg).await
+    }
+
+    async fn update_raw(&self, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>> {
+        call::update_raw(&self.agent().await?, canister_id, method, arg).await
+    }
+
+    async fn reset_agent(&self) {
+        *self.agent.lock().await = None;
+    }
+}
@@ -1,4 +1,5 @@
 pub mod agent;
 pub mod candid;
+pub mod client;
+pub mod keystore;
 pub mod services;
-pub mod utils; 
\ No newline at end of file
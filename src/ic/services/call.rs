@@ -0,0 +1,41 @@
+use ic_agent::Agent;
+use candid::Principal;
+use anyhow::{Result, Context};
+use log::debug;
+use std::time::Instant;
+
+use crate::metrics;
+
+/// Run a query call with Candid-encoded arguments and return the encoded reply
+pub async fn query_raw(agent: &Agent, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>> {
+    debug!("Querying {} on canister {}", method, canister_id);
+    
+    let principal = Principal::from_text(canister_id)
+        .context(format!("Invalid canister ID: {}", canister_id))?;
+    
+    let started = Instant::now();
+    let response = agent.query(&principal, method)
+        .with_arg(arg)
+        .call()
+        .await;
+    metrics::observe_ic_call(method, started, response.is_ok());
+    
+    response.context(format!("Failed to query {} on canister {}", method, canister_id))
+}
+
+/// Make an update call with Candid-encoded arguments from the server identity and wait for the encoded reply
+pub async fn update_raw(agent: &Agent, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>> {
+    debug!("Calling {} on canister {}", method, canister_id);
+    
+    let principal = Principal::from_text(canister_id)
+        .context(format!("Invalid canister ID: {}", canister_id))?;
+    
+    let started = Instant::now();
+    let response = agent.update(&principal, method)
+        .with_arg(arg)
+        .call_and_wait()
+        .await;
+    metrics::observe_ic_call(method, started, response.is_ok());
+    
+    response.context(format!("Failed to call {} on canister {}", method, canister_id))
+}
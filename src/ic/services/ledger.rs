@@ -0,0 +1,412 @@
+use ic_agent::Agent;
+use candid::{CandidType, Decode, Encode, Nat, Principal};
+use anyhow::{Result, Context, anyhow};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use log::info;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::time::Instant;
+
+use crate::ic::candid::ledger::{
+    Account, GetBlocksArgs, GetBlocksResult, GetTransactionsRequest, GetTransactionsResponse, Icrc3Value,
+    MetadataValue, StandardRecord, Transaction, TransactionRange,
+};
+use crate::db::models::ledger_info::LedgerInfo as DbLedgerInfo;
+use crate::db::models::ledger_transaction::{LedgerTransaction, TransactionKind};
+use crate::metrics;
+
+// Metadata key holding the ledger's logo, usually a data URL
+const LOGO_METADATA_KEY: &str = "icrc1:logo";
+
+/// Ledger method used to read blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMethod {
+    /// `icrc3_get_blocks`, for ledgers that support ICRC-3
+    Icrc3GetBlocks,
+    /// `get_transactions`, offered by the ICRC-1 reference ledger
+    GetTransactions,
+}
+
+/// Blocks read from a ledger, starting at the requested index
+#[derive(Debug, Clone)]
+pub struct LedgerBlocks {
+    /// Number of blocks in the ledger, including archived ones
+    pub log_length: u64,
+    /// Index after the last block read, every block before it has been read
+    pub next_block: u64,
+    /// Transfers, mints, burns and approvals among the blocks read
+    pub transactions: Vec<LedgerTransaction>,
+}
+
+/// Get ICRC-1 details from a ledger canister
+pub async fn get_ledger_info(agent: &Agent, canister_id: &str) -> Result<DbLedgerInfo> {
+    info!("Getting ledger info for canister: {}", canister_id);
+
+    // Parse the canister ID
+    let principal = Principal::from_text(canister_id)
+        .context(format!("Invalid canister ID: {}", canister_id))?;
+
+    // Prepare the arguments - empty tuple for no arguments
+    let no_args = Encode!(&()).context("Failed to encode arguments")?;
+
+    // Every field has its own query, run them together
+    let (metadata, name, symbol, decimals, fee, total_supply, standards) = futures::try_join!(
+        query::<Vec<(String, MetadataValue)>>(agent, &principal, "icrc1_metadata", no_args.clone()),
+        query::<String>(agent, &principal, "icrc1_name", no_args.clone()),
+        query::<String>(agent, &principal, "icrc1_symbol", no_args.clone()),
+        query::<u8>(agent, &principal, "icrc1_decimals", no_args.clone()),
+        query::<Nat>(agent, &principal, "icrc1_fee", no_args.clone()),
+        query::<Nat>(agent, &principal, "icrc1_total_supply", no_args.clone()),
+        query::<Vec<StandardRecord>>(agent, &principal, "icrc1_supported_standards", no_args),
+    )?;
+
+    let logo = metadata.iter().find_map(|(key, value)| match value {
+        MetadataValue::Text(logo) if key == LOGO_METADATA_KEY => Some(logo.clone()),
+        _ => None,
+    });
+
+    // Convert the metadata to a JSON object, with numbers as strings so large values survive
+    let metadata: Map<String, Value> = metadata
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                MetadataValue::Nat(n) => Value::String(n.0.to_string()),
+                MetadataValue::Int(i) => Value::String(i.0.to_string()),
+                MetadataValue::Text(text) => Value::String(text),
+                MetadataValue::Blob(bytes) => Value::String(STANDARD.encode(bytes)),
+            };
+            (key, value)
+        })
+        .collect();
+    let metadata = serde_json::to_string(&metadata)
+        .context("Failed to serialize ledger metadata")?;
+
+    let supported_standards = standards
+        .into_iter()
+        .map(|standard| standard.name)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let db_ledger_info = DbLedgerInfo::new(
+        canister_id.to_string(),
+        name,
+        symbol,
+        decimals,
+        fee.0.to_string(),
+        total_supply.0.to_string(),
+        logo,
+        supported_standards,
+        metadata,
+    );
+
+    info!("Successfully retrieved ledger info for canister: {}", canister_id);
+    Ok(db_ledger_info)
+}
+
+/// Read up to `length` blocks starting at `start`, following archive canisters
+///
+/// Ledgers may return fewer blocks than requested, `next_block` tells where to continue.
+pub async fn get_ledger_blocks(
+    agent: &Agent,
+    canister_id: &str,
+    method: BlockMethod,
+    start: u64,
+    length: u64,
+) -> Result<LedgerBlocks> {
+    info!("Getting blocks {}..{} of ledger {}", start, start + length, canister_id);
+
+    // Parse the canister ID
+    let principal = Principal::from_text(canister_id)
+        .context(format!("Invalid canister ID: {}", canister_id))?;
+
+    let (log_length, blocks) = match method {
+        BlockMethod::Icrc3GetBlocks => get_icrc3_blocks(agent, &principal, canister_id, start, length).await?,
+        BlockMethod::GetTransactions => get_transactions(agent, &principal, canister_id, start, length).await?,
+    };
+
+    // Keep the blocks that follow on from `start` without a gap
+    let mut blocks = blocks;
+    blocks.sort_by_key(|(index, _)| *index);
+    let mut next_block = start;
+    let mut transactions = Vec::new();
+    for (index, transaction) in blocks {
+        if index < next_block {
+            continue;
+        }
+        if index > next_block {
+            break;
+        }
+        next_block += 1;
+        transactions.extend(transaction);
+    }
+
+    Ok(LedgerBlocks { log_length, next_block, transactions })
+}
+
+// Read blocks with icrc3_get_blocks, returning each block index with its transaction, if indexed
+async fn get_icrc3_blocks(
+    agent: &Agent,
+    principal: &Principal,
+    canister_id: &str,
+    start: u64,
+    length: u64,
+) -> Result<(u64, Vec<(u64, Option<LedgerTransaction>)>)> {
+    let args = vec![GetBlocksArgs { start: Nat::from(start), length: Nat::from(length) }];
+    let arg_bytes = Encode!(&args).context("Failed to encode arguments")?;
+    let result: GetBlocksResult = query(agent, principal, "icrc3_get_blocks", arg_bytes).await?;
+
+    let mut blocks = result.blocks;
+    for archived in result.archived_blocks {
+        let arg_bytes = Encode!(&archived.args).context("Failed to encode arguments")?;
+        let archive: GetBlocksResult =
+            query(agent, &archived.callback.0.principal, &archived.callback.0.method, arg_bytes).await?;
+        blocks.extend(archive.blocks);
+    }
+
+    let blocks = blocks
+        .into_iter()
+        .map(|block| {
+            let index = nat_to_u64(&block.id)?;
+            let transaction = icrc3_transaction(canister_id, index, &block.block)
+                .with_context(|| format!("Failed to read block {} of ledger {}", index, canister_id))?;
+            Ok((index, transaction))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((nat_to_u64(&result.log_length)?, blocks))
+}
+
+// Read blocks with get_transactions, returning each block index with its transaction, if indexed
+async fn get_transactions(
+    agent: &Agent,
+    principal: &Principal,
+    canister_id: &str,
+    start: u64,
+    length: u64,
+) -> Result<(u64, Vec<(u64, Option<LedgerTransaction>)>)> {
+    let request = GetTransactionsRequest { start: Nat::from(start), length: Nat::from(length) };
+    let arg_bytes = Encode!(&request).context("Failed to encode arguments")?;
+    let response: GetTransactionsResponse = query(agent, principal, "get_transactions", arg_bytes).await?;
+
+    let first_index = nat_to_u64(&response.first_index)?;
+    let mut ranges = vec![(first_index, response.transactions)];
+    for archived in response.archived_transactions {
+        let archive_start = nat_to_u64(&archived.start)?;
+        let request = GetTransactionsRequest { start: archived.start, length: archived.length };
+        let arg_bytes = Encode!(&request).context("Failed to encode arguments")?;
+        let range: TransactionRange =
+            query(agent, &archived.callback.0.principal, &archived.callback.0.method, arg_bytes).await?;
+        ranges.push((archive_start, range.transactions));
+    }
+
+    let blocks = ranges
+        .into_iter()
+        .flat_map(|(range_start, transactions)| {
+            transactions.into_iter().enumerate().map(move |(i, transaction)| (range_start + i as u64, transaction))
+        })
+        .map(|(index, transaction)| (index, reference_transaction(canister_id, index, transaction)))
+        .collect();
+    Ok((nat_to_u64(&response.log_length)?, blocks))
+}
+
+// Convert a get_transactions entry, unknown kinds are not indexed
+fn reference_transaction(canister_id: &str, block_index: u64, transaction: Transaction) -> Option<LedgerTransaction> {
+    let mut indexed = LedgerTransaction {
+        canister_id: canister_id.to_string(),
+        block_index,
+        kind: TransactionKind::Transfer,
+        from_owner: None,
+        from_subaccount: None,
+        to_owner: None,
+        to_subaccount: None,
+        spender: None,
+        amount: String::new(),
+        fee: None,
+        memo: None,
+        timestamp: transaction.timestamp,
+    };
+
+    match transaction.kind.as_str() {
+        "transfer" => {
+            let transfer = transaction.transfer?;
+            (indexed.from_owner, indexed.from_subaccount) = account_parts(&transfer.from);
+            (indexed.to_owner, indexed.to_subaccount) = account_parts(&transfer.to);
+            indexed.spender = transfer.spender.map(|spender| spender.owner.to_text());
+            indexed.amount = transfer.amount.0.to_string();
+            indexed.fee = transfer.fee.map(|fee| fee.0.to_string());
+            indexed.memo = transfer.memo.as_deref().map(to_hex);
+        }
+        "mint" => {
+            let mint = transaction.mint?;
+            indexed.kind = TransactionKind::Mint;
+            (indexed.to_owner, indexed.to_subaccount) = account_parts(&mint.to);
+            indexed.amount = mint.amount.0.to_string();
+            indexed.memo = mint.memo.as_deref().map(to_hex);
+        }
+        "burn" => {
+            let burn = transaction.burn?;
+            indexed.kind = TransactionKind::Burn;
+            (indexed.from_owner, indexed.from_subaccount) = account_parts(&burn.from);
+            indexed.spender = burn.spender.map(|spender| spender.owner.to_text());
+            indexed.amount = burn.amount.0.to_string();
+            indexed.memo = burn.memo.as_deref().map(to_hex);
+        }
+        "approve" => {
+            let approve = transaction.approve?;
+            indexed.kind = TransactionKind::Approve;
+            (indexed.from_owner, indexed.from_subaccount) = account_parts(&approve.from);
+            indexed.spender = Some(approve.spender.owner.to_text());
+            indexed.amount = approve.amount.0.to_string();
+            indexed.fee = approve.fee.map(|fee| fee.0.to_string());
+            indexed.memo = approve.memo.as_deref().map(to_hex);
+        }
+        _ => return None,
+    }
+    Some(indexed)
+}
+
+// Convert an ICRC-3 block, unknown block types are not indexed
+fn icrc3_transaction(canister_id: &str, block_index: u64, block: &Icrc3Value) -> Result<Option<LedgerTransaction>> {
+    let block = as_map(block).ok_or_else(|| anyhow!("Block is not a map"))?;
+    let tx = field(block, "tx").and_then(as_map).ok_or_else(|| anyhow!("Block has no tx"))?;
+
+    // Blocks name their type in btype, older ones only in tx.op
+    let btype = field(block, "btype").and_then(as_text);
+    let kind = match btype.or_else(|| field(tx, "op").and_then(as_text)) {
+        Some("1xfer") | Some("2xfer") | Some("xfer") => TransactionKind::Transfer,
+        Some("1mint") | Some("mint") => TransactionKind::Mint,
+        Some("1burn") | Some("burn") => TransactionKind::Burn,
+        Some("2approve") | Some("approve") => TransactionKind::Approve,
+        _ => return Ok(None),
+    };
+
+    let (from_owner, from_subaccount) = field(tx, "from").map(icrc3_account).transpose()?.unwrap_or_default();
+    let (to_owner, to_subaccount) = field(tx, "to").map(icrc3_account).transpose()?.unwrap_or_default();
+    let spender = field(tx, "spender").map(icrc3_account).transpose()?.and_then(|(owner, _)| owner);
+    let amount = field(tx, "amt").and_then(as_nat).ok_or_else(|| anyhow!("Block has no amount"))?;
+    // The fee paid is in tx when the caller set it, otherwise in the block
+    let fee = field(tx, "fee").or_else(|| field(block, "fee")).and_then(as_nat);
+    let memo = field(tx, "memo").and_then(as_blob).map(to_hex);
+    let timestamp = field(block, "ts").and_then(as_nat).ok_or_else(|| anyhow!("Block has no timestamp"))?;
+
+    Ok(Some(LedgerTransaction {
+        canister_id: canister_id.to_string(),
+        block_index,
+        kind,
+        from_owner,
+        from_subaccount,
+        to_owner,
+        to_subaccount,
+        spender,
+        amount,
+        fee,
+        memo,
+        timestamp: timestamp.parse().context("Block timestamp is out of range")?,
+    }))
+}
+
+fn field<'a>(map: &'a [(String, Icrc3Value)], key: &str) -> Option<&'a Icrc3Value> {
+    map.iter().find(|(k, _)| k == key).map(|(_, value)| value)
+}
+
+fn as_map(value: &Icrc3Value) -> Option<&[(String, Icrc3Value)]> {
+    match value {
+        Icrc3Value::Map(map) => Some(map),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Icrc3Value) -> Option<&str> {
+    match value {
+        Icrc3Value::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+fn as_blob(value: &Icrc3Value) -> Option<&[u8]> {
+    match value {
+        Icrc3Value::Blob(bytes) => Some(bytes),
+        _ => None,
+    }
+}
+
+fn as_nat(value: &Icrc3Value) -> Option<String> {
+    match value {
+        Icrc3Value::Nat(n) => Some(n.0.to_string()),
+        _ => None,
+    }
+}
+
+// An ICRC-3 account is an array of the owner and an optional subaccount, both blobs
+fn icrc3_account(value: &Icrc3Value) -> Result<(Option<String>, Option<String>)> {
+    let parts = match value {
+        Icrc3Value::Array(parts) => parts,
+        _ => return Err(anyhow!("Account is not an array")),
+    };
+    let owner = parts.first().and_then(as_blob).ok_or_else(|| anyhow!("Account has no owner"))?;
+    let owner = Principal::try_from_slice(owner).map_err(|_| anyhow!("Invalid account owner"))?;
+    Ok((Some(owner.to_text()), subaccount_hex(parts.get(1).and_then(as_blob))))
+}
+
+fn account_parts(account: &Account) -> (Option<String>, Option<String>) {
+    (Some(account.owner.to_text()), subaccount_hex(account.subaccount.as_deref()))
+}
+
+/// Query `icrc1_balance_of` for an account, the balance is returned as a decimal string
+pub async fn get_balance(agent: &Agent, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<String> {
+    info!("Getting balance of {} on ledger {}", owner, canister_id);
+
+    // Parse the canister ID and account
+    let principal = Principal::from_text(canister_id)
+        .context(format!("Invalid canister ID: {}", canister_id))?;
+    let account = Account {
+        owner: Principal::from_text(owner).context(format!("Invalid account owner: {}", owner))?,
+        subaccount: subaccount.map(from_hex).transpose()?,
+    };
+
+    let arg_bytes = Encode!(&account).context("Failed to encode arguments")?;
+    let balance: Nat = query(agent, &principal, "icrc1_balance_of", arg_bytes).await?;
+    Ok(balance.0.to_string())
+}
+
+/// Hex form of a subaccount as stored by the indexer, `None` for the default subaccount
+pub fn subaccount_hex(subaccount: Option<&[u8]>) -> Option<String> {
+    subaccount.filter(|bytes| bytes.iter().any(|b| *b != 0)).map(to_hex)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair).ok()
+                .filter(|pair| pair.len() == 2 && pair.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid hex: {}", hex))
+        })
+        .collect()
+}
+
+fn nat_to_u64(n: &Nat) -> Result<u64> {
+    n.0.to_string().parse().context("Block index is out of range")
+}
+
+// Call a query method and decode its single return value
+async fn query<T: CandidType + DeserializeOwned>(
+    agent: &Agent,
+    canister: &Principal,
+    method: &str,
+    arg_bytes: Vec<u8>,
+) -> Result<T> {
+    let started = Instant::now();
+    let response = agent.query(canister, method)
+        .with_arg(arg_bytes)
+        .call()
+        .await;
+    metrics::observe_ic_call(method, started, response.is_ok());
+    let response = response.context(format!("Failed to call {}", method))?;
+
+    Decode!(response.as_slice(), T).context(format!("Failed to decode {} response", method))
+}
@@ -2,10 +2,12 @@ use ic_agent::Agent;
 use candid::{Decode, Encode, Principal};
 use anyhow::{Result, Context, anyhow};
 use log::info;
+use std::time::Instant;
 
 use crate::ic::candid::miner::{MiningStats as CandidMiningStats, Result as CandidResult};
 use crate::db::models::miner_info::{MinerInfo as DbMinerInfo, MinerType as DbMinerType};
 use crate::db::models::mining_stats::MiningStats as DbMiningStats;
+use crate::metrics;
 
 /// Get miner info from a miner canister
 pub async fn get_miner_info(agent: &Agent, canister_id: &str) -> Result<(DbMinerInfo, Option<DbMiningStats>)> {
@@ -19,11 +21,13 @@ pub async fn get_miner_info(agent: &Agent, canister_id: &str) -> Result<(DbMiner
     let arg_bytes = Encode!(&()).context("Failed to encode arguments")?;
     
     // Call the canister to get miner info
+    let started = Instant::now();
     let info_response = agent.query(&principal, "get_info")
         .with_arg(arg_bytes)
         .call()
-        .await
-        .context("Failed to call get_info")?;
+        .await;
+    metrics::observe_ic_call("get_info", started, info_response.is_ok());
+    let info_response = info_response.context("Failed to call get_info")?;
     
     // Decode the response
     let result = Decode!(info_response.as_slice(), CandidResult)
@@ -79,11 +83,13 @@ async fn get_mining_stats(agent: &Agent, canister_id: &str) -> Result<Option<DbM
     let arg_bytes = Encode!(&()).context("Failed to encode arguments")?;
     
     // Call the canister to get mining stats
+    let started = Instant::now();
     let stats_response = agent.query(&principal, "get_mining_stats")
         .with_arg(arg_bytes)
         .call()
-        .await
-        .context("Failed to call get_mining_stats")?;
+        .await;
+    metrics::observe_ic_call("get_mining_stats", started, stats_response.is_ok());
+    let stats_response = stats_response.context("Failed to call get_mining_stats")?;
     
     // Decode the response
     let stats_opt = Decode!(stats_response.as_slice(), Option<CandidMiningStats>)
@@ -1,3 +1,5 @@
 pub mod token;
 pub mod miner;
-pub mod module_hash;
\ No newline at end of file
+pub mod module_hash;
+pub mod ledger;
+pub mod call;
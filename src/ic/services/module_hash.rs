@@ -3,6 +3,9 @@ use candid::Principal;
 use anyhow::{Result, Context, anyhow};
 use log::{info, warn, debug};
 use serde_cbor::Value;
+use std::time::Instant;
+
+use crate::metrics;
 
 /// Get module hash from a canister using read_state_canister_info API (doesn't require controller privileges)
 pub async fn get_module_hash(agent: &Agent, canister_id: &str) -> Result<String> {
@@ -17,9 +20,11 @@ pub async fn get_module_hash(agent: &Agent, canister_id: &str) -> Result<String>
     debug!("Using read_state_canister_info with path: {}", path);
     
     // Call read_state_canister_info API
+    let started = Instant::now();
     let blob = agent.read_state_canister_info(principal, path)
-        .await
-        .context("Failed to call read_state_canister_info for module_hash")?;
+        .await;
+    metrics::observe_ic_call("read_state_canister_info", started, blob.is_ok());
+    let blob = blob.context("Failed to call read_state_canister_info for module_hash")?;
     
     if blob.is_empty() {
         return Err(anyhow!("No module hash found for canister {}", canister_id));
@@ -47,9 +52,11 @@ pub async fn get_controllers(agent: &Agent, canister_id: &str) -> Result<Vec<Str
     debug!("Using read_state_canister_info with path: {}", path);
     
     // Call read_state_canister_info API
+    let started = Instant::now();
     let blob = agent.read_state_canister_info(principal, path)
-        .await
-        .context("Failed to call read_state_canister_info for controllers")?;
+        .await;
+    metrics::observe_ic_call("read_state_canister_info", started, blob.is_ok());
+    let blob = blob.context("Failed to call read_state_canister_info for controllers")?;
     
     if blob.is_empty() {
         return Err(anyhow!("No controllers found for canister {}", canister_id));
@@ -85,6 +92,31 @@ pub async fn get_controllers(agent: &Agent, canister_id: &str) -> Result<Vec<Str
     Ok(controllers)
 }
 
+/// Get the Candid service definition a canister publishes in its `candid:service` metadata
+///
+/// Fails if the section is missing or private, private sections can only be read by controllers.
+pub async fn get_candid_service(agent: &Agent, canister_id: &str) -> Result<String> {
+    info!("Getting candid:service metadata for canister: {}", canister_id);
+    
+    let principal = Principal::from_text(canister_id)
+        .context(format!("Invalid canister ID: {}", canister_id))?;
+    
+    let started = Instant::now();
+    let blob = agent.read_state_canister_metadata(principal, "candid:service")
+        .await;
+    metrics::observe_ic_call("read_state_canister_metadata", started, blob.is_ok());
+    let blob = blob.context("Failed to read candid:service metadata")?;
+    
+    let service = String::from_utf8(blob)
+        .context("candid:service metadata is not valid UTF-8")?;
+    if service.trim().is_empty() {
+        return Err(anyhow!("Empty candid:service metadata for canister {}", canister_id));
+    }
+    
+    debug!("Read {} bytes of candid:service metadata for canister {}", service.len(), canister_id);
+    Ok(service)
+}
+
 /// Get both module hash and controllers in a single call
 pub async fn get_canister_info(agent: &Agent, canister_id: &str) -> Result<(String, Vec<String>)> {
     let module_hash = get_module_hash(agent, canister_id).await?;
@@ -2,9 +2,11 @@ use ic_agent::Agent;
 use candid::{Decode, Encode, Principal};
 use anyhow::{Result, Context, anyhow};
 use log::info;
+use std::time::Instant;
 
 use crate::ic::candid::token::{AllInfoResult, TokenAllInfo};
 use crate::db::models::token_info::TokenInfo as DbTokenInfo;
+use crate::metrics;
 
 /// Get token all info from a token canister
 pub async fn get_token_all_info(agent: &Agent, canister_id: &str) -> Result<DbTokenInfo> {
@@ -18,11 +20,13 @@ pub async fn get_token_all_info(agent: &Agent, canister_id: &str) -> Result<DbTo
     let arg_bytes = Encode!(&()).context("Failed to encode arguments")?;
     
     // Call the canister
+    let started = Instant::now();
     let response = agent.query(&principal, "get_all_info")
         .with_arg(arg_bytes)
         .call()
-        .await
-        .context("Failed to call get_all_info")?;
+        .await;
+    metrics::observe_ic_call("get_all_info", started, response.is_ok());
+    let response = response.context("Failed to call get_all_info")?;
     
     // Decode the response
     let result = Decode!(response.as_slice(), AllInfoResult)
@@ -1 +0,0 @@
-pub mod interface_util; 
\ No newline at end of file
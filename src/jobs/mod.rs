@@ -1,4 +1,4 @@
 pub mod scheduler;
 pub mod tasks;
 
-pub use scheduler::start_scheduler; 
\ No newline at end of file
+pub use scheduler::{start_scheduler, last_success, JOB_NAMES}; 
\ No newline at end of file
@@ -1,37 +1,127 @@
 use tokio::time::{self, Duration};
 use log::{info, error};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use crate::db::DbPool;
-use crate::jobs::tasks::{update_tokens, update_miners};
+use crate::db::backup::BackupConfig;
+use crate::db::repository::Repository;
+use crate::ic::client::IcClient;
+use crate::jobs::tasks::{update_tokens, update_ledgers, index_ledgers, update_miners, backup};
+use crate::metrics;
+
+/// Names of the periodic jobs started by the scheduler
+pub const JOB_NAMES: &[&str] = &["update_tokens", "update_ledgers", "index_ledgers", "update_miners"];
+
+// Timestamp of the last successful run of each job
+lazy_static::lazy_static! {
+    static ref LAST_SUCCESS: Mutex<HashMap<&'static str, i64>> = Mutex::new(HashMap::new());
+}
+
+/// Get the unix timestamp of the last successful run of a job
+pub fn last_success(job: &str) -> Option<i64> {
+    LAST_SUCCESS.lock().unwrap().get(job).copied()
+}
+
+// Record the outcome of a job run
+fn record_run(job: &'static str, started: Instant, success: bool) {
+    metrics::observe_job(job, started, success);
+    if success {
+        LAST_SUCCESS.lock().unwrap().insert(job, chrono::Utc::now().timestamp());
+    }
+}
 
 /// Start the background job scheduler
-pub async fn start_scheduler(db_pool: Arc<DbPool>) {
+pub async fn start_scheduler(repo: Arc<dyn Repository>, ic: Arc<dyn IcClient>) {
     info!("Starting background job scheduler");
     
     // Spawn a task for updating token info
-    let token_db_pool = db_pool.clone();
+    let token_repo = repo.clone();
+    let token_ic = ic.clone();
     tokio::spawn(async move {
         let mut interval = time::interval(Duration::from_secs(60)); // Every minute
         loop {
             interval.tick().await;
-            if let Err(e) = update_tokens::run(token_db_pool.clone()).await {
+            let started = Instant::now();
+            let result = update_tokens::run(token_repo.clone(), token_ic.clone()).await;
+            record_run("update_tokens", started, result.is_ok());
+            if let Err(e) = result {
                 error!("Error updating tokens: {}", e);
             }
         }
     });
     
+    // Spawn a task for updating ledger info
+    let ledger_repo = repo.clone();
+    let ledger_ic = ic.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60)); // Every minute
+        loop {
+            interval.tick().await;
+            let started = Instant::now();
+            let result = update_ledgers::run(ledger_repo.clone(), ledger_ic.clone()).await;
+            record_run("update_ledgers", started, result.is_ok());
+            if let Err(e) = result {
+                error!("Error updating ledgers: {}", e);
+            }
+        }
+    });
+    
+    // Spawn a task for indexing ledger blocks
+    let index_repo = repo.clone();
+    let index_ic = ic.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60)); // Every minute
+        loop {
+            interval.tick().await;
+            let started = Instant::now();
+            let result = index_ledgers::run(index_repo.clone(), index_ic.clone()).await;
+            record_run("index_ledgers", started, result.is_ok());
+            if let Err(e) = result {
+                error!("Error indexing ledgers: {}", e);
+            }
+        }
+    });
+    
     // Spawn a task for updating miner info
-    let miner_db_pool = db_pool.clone();
+    let miner_repo = repo.clone();
+    let miner_ic = ic.clone();
     tokio::spawn(async move {
         let mut interval = time::interval(Duration::from_secs(60)); // Every minute
         loop {
             interval.tick().await;
-            if let Err(e) = update_miners::run(miner_db_pool.clone()).await {
+            let started = Instant::now();
+            let result = update_miners::run(miner_repo.clone(), miner_ic.clone()).await;
+            record_run("update_miners", started, result.is_ok());
+            if let Err(e) = result {
                 error!("Error updating miners: {}", e);
             }
         }
     });
     
+    // Spawn a task for database backups, unless disabled or the database is not a local file
+    let backup_interval = BackupConfig::get().interval_secs;
+    if let Some(db_path) = repo.file_path().filter(|_| backup_interval > 0) {
+        let db_path = db_path.to_path_buf();
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(backup_interval));
+            // The first tick completes immediately, wait a full interval after startup
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let started = Instant::now();
+                let result = backup::run(db_path.clone()).await;
+                record_run("backup", started, result.is_ok());
+                if let Err(e) = result {
+                    error!("Error backing up database: {}", e);
+                }
+            }
+        });
+    } else if repo.file_path().is_none() {
+        info!("Scheduled database backups are not available with the {} backend", repo.backend());
+    } else {
+        info!("Scheduled database backups are disabled");
+    }
+    
     info!("Background job scheduler started");
 } 
\ No newline at end of file
@@ -0,0 +1,24 @@
+use anyhow::{Result, Context};
+use log::info;
+use std::path::PathBuf;
+
+use crate::db::backup::{self, BackupConfig, BackupInfo};
+
+/// Run the backup task: write a snapshot and apply the retention rules
+pub async fn run(db_path: PathBuf) -> Result<BackupInfo> {
+    info!("Running backup task");
+    
+    let config = BackupConfig::get();
+    
+    // The backup API blocks, keep it off the async workers
+    tokio::task::spawn_blocking(move || {
+        let info = backup::create_backup(&db_path, &config.dir)?;
+        let deleted = backup::apply_retention(&config.dir, config.keep_last, config.max_age_days)?;
+        if deleted > 0 {
+            info!("Deleted {} old backups", deleted);
+        }
+        Ok(info)
+    })
+    .await
+    .context("Backup task panicked")?
+}
@@ -0,0 +1,163 @@
+use anyhow::{Result, Context};
+use chrono::Utc;
+use log::{info, warn, error};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::db::models::canister::CanisterType;
+use crate::db::models::ledger_transaction::{AccountBalance, LedgerIndexState, LedgerTransaction, TransactionKind};
+use crate::db::repository::Repository;
+use crate::ic::client::IcClient;
+use crate::ic::services::ledger::BlockMethod;
+
+/// Number of blocks requested per call
+pub const PAGE_SIZE: u64 = 1000;
+
+// Pages read per ledger and run, so one long ledger does not hold up the others
+const MAX_PAGES_PER_RUN: usize = 50;
+
+/// Run the index ledgers task
+pub async fn run(repo: Arc<dyn Repository>, ic: Arc<dyn IcClient>) -> Result<()> {
+    info!("Running index ledgers task");
+
+    // Get all ledger canisters
+    let ledger_canisters = repo.canisters().find_by_type(&CanisterType::Ledger).await
+        .context("Failed to get ledger canisters")?;
+
+    info!("Found {} ledger canisters to index", ledger_canisters.len());
+
+    // Index each ledger
+    for canister in ledger_canisters {
+        if let Err(e) = index_ledger(repo.as_ref(), ic.as_ref(), &canister.canister_id).await {
+            error!("Failed to index ledger canister {}: {}", canister.canister_id, e);
+        }
+    }
+
+    info!("Index ledgers task completed");
+    Ok(())
+}
+
+// Account key of the balances kept while indexing, owner and hex subaccount
+type AccountKey = (String, Option<String>);
+
+// Read the ledger's new blocks from its checkpoint, saving each page with the new checkpoint
+async fn index_ledger(repo: &dyn Repository, ic: &dyn IcClient, canister_id: &str) -> Result<()> {
+    // The block method depends on the supported standards, wait for the ledger info
+    let ledger = match repo.ledgers().find_by_canister_id(canister_id).await? {
+        Some(ledger) => ledger,
+        None => {
+            info!("Ledger canister {} has no ledger info yet, skipping", canister_id);
+            return Ok(());
+        }
+    };
+    let method = if ledger.supports("ICRC-3") {
+        BlockMethod::Icrc3GetBlocks
+    } else {
+        BlockMethod::GetTransactions
+    };
+
+    let mut next_block = repo.ledger_transactions().index_state(canister_id).await?
+        .map(|state| state.next_block)
+        .unwrap_or(0);
+
+    // Balances of the accounts seen in this run, loaded on first use
+    let mut balances = HashMap::new();
+
+    for _ in 0..MAX_PAGES_PER_RUN {
+        let mut blocks = ic.get_ledger_blocks(canister_id, method, next_block, PAGE_SIZE).await?;
+
+        // get_transactions leaves out fees the caller did not set, the ledger charged its fee
+        for transaction in &mut blocks.transactions {
+            if matches!(transaction.kind, TransactionKind::Transfer | TransactionKind::Approve) && transaction.fee.is_none() {
+                transaction.fee = Some(ledger.fee.clone());
+            }
+        }
+        let changed = apply_page(repo, canister_id, &blocks.transactions, &mut balances).await?;
+
+        let state = LedgerIndexState {
+            canister_id: canister_id.to_string(),
+            next_block: blocks.next_block,
+            log_length: blocks.log_length,
+            last_updated: Utc::now().timestamp(),
+        };
+        repo.ledger_transactions().save_page(&blocks.transactions, &changed, &state).await
+            .context("Failed to save ledger transactions")?;
+
+        // Stop when caught up, or when the ledger returned nothing new
+        if blocks.next_block <= next_block || blocks.next_block >= blocks.log_length {
+            next_block = blocks.next_block.max(next_block);
+            break;
+        }
+        next_block = blocks.next_block;
+    }
+
+    info!("Indexed ledger canister {} up to block {}", canister_id, next_block);
+    Ok(())
+}
+
+// Apply a page of transactions to the account balances, returning the balances it changed
+//
+// Fees are treated as burned, blocks paying a fee collector are not credited to it.
+async fn apply_page(
+    repo: &dyn Repository,
+    canister_id: &str,
+    transactions: &[LedgerTransaction],
+    balances: &mut HashMap<AccountKey, u128>,
+) -> Result<Vec<AccountBalance>> {
+    let mut changed = Vec::new();
+    for transaction in transactions {
+        let amount = parse_amount(&transaction.amount)?;
+        let fee = transaction.fee.as_deref().map(parse_amount).transpose()?.unwrap_or(0);
+        let (debit, credit) = match transaction.kind {
+            TransactionKind::Transfer => (amount + fee, amount),
+            TransactionKind::Mint => (0, amount),
+            TransactionKind::Burn => (amount + fee, 0),
+            TransactionKind::Approve => (fee, 0),
+        };
+
+        if let (Some(owner), true) = (&transaction.from_owner, debit > 0) {
+            let key = (owner.clone(), transaction.from_subaccount.clone());
+            let balance = load_balance(repo, canister_id, balances, &key).await?;
+            if *balance < debit {
+                warn!("Block {} of ledger {} overdraws {}", transaction.block_index, canister_id, owner);
+            }
+            *balance = balance.saturating_sub(debit);
+            changed.push(key);
+        }
+        if let (Some(owner), true) = (&transaction.to_owner, credit > 0) {
+            let key = (owner.clone(), transaction.to_subaccount.clone());
+            let balance = load_balance(repo, canister_id, balances, &key).await?;
+            *balance = balance.checked_add(credit).context("Balance is too large")?;
+            changed.push(key);
+        }
+    }
+
+    changed.sort();
+    changed.dedup();
+    Ok(changed
+        .into_iter()
+        .map(|key| {
+            let balance = balances[&key].to_string();
+            AccountBalance { canister_id: canister_id.to_string(), owner: key.0, subaccount: key.1, balance }
+        })
+        .collect())
+}
+
+// Balance of an account, read from the database the first time it is needed
+async fn load_balance<'a>(
+    repo: &dyn Repository,
+    canister_id: &str,
+    balances: &'a mut HashMap<AccountKey, u128>,
+    key: &AccountKey,
+) -> Result<&'a mut u128> {
+    if !balances.contains_key(key) {
+        let stored = repo.ledger_transactions().find_balance(canister_id, &key.0, key.1.as_deref()).await?;
+        let balance = stored.map(|stored| parse_amount(&stored.balance)).transpose()?.unwrap_or(0);
+        balances.insert(key.clone(), balance);
+    }
+    Ok(balances.get_mut(key).expect("balance was just loaded"))
+}
+
+fn parse_amount(amount: &str) -> Result<u128> {
+    amount.parse().with_context(|| format!("Amount is too large: {}", amount))
+}
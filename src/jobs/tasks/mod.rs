@@ -1,2 +1,5 @@
 pub mod update_tokens;
-pub mod update_miners; 
\ No newline at end of file
+pub mod update_ledgers;
+pub mod index_ledgers;
+pub mod update_miners;
+pub mod backup; 
\ No newline at end of file
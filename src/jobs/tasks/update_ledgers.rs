@@ -0,0 +1,37 @@
+use anyhow::{Result, Context};
+use log::{info, error};
+use std::sync::Arc;
+
+use crate::db::models::canister::CanisterType;
+use crate::db::repository::Repository;
+use crate::ic::client::IcClient;
+
+/// Run the update ledgers task
+pub async fn run(repo: Arc<dyn Repository>, ic: Arc<dyn IcClient>) -> Result<()> {
+    info!("Running update ledgers task");
+    
+    // Get all ledger canisters
+    let ledger_canisters = repo.canisters().find_by_type(&CanisterType::Ledger).await
+        .context("Failed to get ledger canisters")?;
+    
+    info!("Found {} ledger canisters to update", ledger_canisters.len());
+    
+    // Update each ledger
+    for canister in ledger_canisters {
+        info!("Updating ledger canister: {}", canister.canister_id);
+        
+        match ic.get_ledger_info(&canister.canister_id).await {
+            Ok(ledger_info) => {
+                // Save the ledger info
+                repo.ledgers().save(&ledger_info).await.context("Failed to save ledger info")?;
+                info!("Successfully updated ledger canister: {}", canister.canister_id);
+            }
+            Err(e) => {
+                error!("Failed to update ledger canister {}: {}", canister.canister_id, e);
+            }
+        }
+    }
+    
+    info!("Update ledgers task completed");
+    Ok(())
+}
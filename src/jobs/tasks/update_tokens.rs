@@ -2,36 +2,28 @@ use anyhow::{Result, Context};
 use log::{info, error};
 use std::sync::Arc;
 
-use crate::db::DbPool;
-use crate::db::models::canister::{Canister, CanisterType};
-use crate::ic::agent::create_agent;
-use crate::ic::services::token::get_token_all_info;
+use crate::db::models::canister::CanisterType;
+use crate::db::repository::Repository;
+use crate::ic::client::IcClient;
 
 /// Run the update tokens task
-pub async fn run(db_pool: Arc<DbPool>) -> Result<()> {
+pub async fn run(repo: Arc<dyn Repository>, ic: Arc<dyn IcClient>) -> Result<()> {
     info!("Running update tokens task");
     
-    // Get a database connection
-    let conn = db_pool.get().context("Failed to get database connection")?;
-    
     // Get all token canisters
-    let token_canisters = Canister::find_by_type(&conn, &CanisterType::Token)
+    let token_canisters = repo.canisters().find_by_type(&CanisterType::Token).await
         .context("Failed to get token canisters")?;
     
     info!("Found {} token canisters to update", token_canisters.len());
     
-    // Create an IC agent
-    let agent = create_agent("https://ic0.app").await
-        .context("Failed to create IC agent")?;
-    
     // Update each token
     for canister in token_canisters {
         info!("Updating token canister: {}", canister.canister_id);
         
-        match get_token_all_info(&agent, &canister.canister_id).await {
+        match ic.get_token_all_info(&canister.canister_id).await {
             Ok(token_info) => {
                 // Save the token info
-                token_info.save(&conn).context("Failed to save token info")?;
+                repo.tokens().save(&token_info).await.context("Failed to save token info")?;
                 info!("Successfully updated token canister: {}", canister.canister_id);
             }
             Err(e) => {
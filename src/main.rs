@@ -1,7 +1,7 @@
 use actix_web::{web, App, HttpServer, middleware, HttpRequest};
+use actix_web::dev::Service;
 use actix_files as fs;
 use log::{info, error, warn};
-use std::path::Path;
 use env_logger::Env;
 use actix_cors::Cors;
 use dotenv::dotenv;
@@ -18,8 +18,15 @@ mod jobs;
 mod websocket;
 mod websocket_handler;
 mod canister_notifications;
+mod metrics;
+mod cli;
+#[cfg(test)]
+mod tests;
 
 use db::models::admin::Admin;
+use db::repository::DatabaseConfig;
+use ic::client::{AgentIcClient, IcClient, IC_URL};
+use std::sync::Arc;
 
 fn load_rustls_config() -> Result<ServerConfig, std::io::Error> {
     // This function is now simplified since DigitalOcean handles SSL
@@ -72,12 +79,17 @@ async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(Env::default().default_filter_or("info"));
     info!("Starting ICP Canister Registry");
     
-    // Initialize database
-    let db_path = Path::new("data/registry.db");
-    let db_pool = match db::init_pool(db_path) {
-        Ok(pool) => {
+    // Record start time for uptime reporting
+    api::handlers::health::mark_started();
+    
+    // Register Prometheus metrics
+    metrics::init();
+    
+    // Initialize database, SQLite or PostgreSQL depending on DATABASE_URL
+    let repo = match db::repository::connect(&DatabaseConfig::from_env()).await {
+        Ok(repo) => {
             info!("Database initialized successfully");
-            pool
+            repo
         },
         Err(e) => {
             error!("Failed to initialize database: {}", e);
@@ -85,28 +97,37 @@ async fn main() -> std::io::Result<()> {
         }
     };
     
+    // Run a command-line subcommand such as `export` or `import` instead of the server
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args, repo.as_ref()).await {
+        return result;
+    }
+    
     // Check if we need to create a default admin account
-    match db_pool.get() {
-        Ok(conn) => {
-            if let Err(e) = Admin::check_or_create_default(&conn) {
-                error!("Failed to create default admin: {}", e);
-            } else {
-                info!("Checked/created default admin account");
-            }
-        },
-        Err(e) => {
-            error!("Failed to get database connection: {}", e);
-        }
+    // Invalid bootstrap credentials are fatal, the server must not start with a weak superadmin
+    if let Err(e) = Admin::check_or_create_default(repo.admins()).await {
+        error!("Failed to create default admin: {:#}", e);
+        return Err(std::io::Error::other(format!("Failed to create default admin: {:#}", e)));
+    } else {
+        info!("Checked/created default admin account");
     }
     
     // Initialize the identity system
     match ic::agent::init_identity() {
         Ok(path) => info!("Identity initialized successfully at: {}", path),
-        Err(e) => warn!("Failed to initialize identity: {}. Will use anonymous identity when needed.", e)
+        // An encrypted identity that cannot be unlocked is fatal, calls must not silently go out anonymously
+        Err(e) if e.downcast_ref::<ic::keystore::LockedIdentityError>().is_some() => {
+            error!("{:#}", e);
+            return Err(std::io::Error::other(format!("{:#}", e)));
+        }
+        Err(e) => warn!("Failed to initialize identity: {:#}. IC calls will use the anonymous identity.", e)
     }
     
+    // Client for canister calls, shared by handlers and jobs
+    let ic_client: Arc<dyn IcClient> = Arc::new(AgentIcClient::new(IC_URL));
+    
     // Start the background job scheduler
-    jobs::start_scheduler(std::sync::Arc::new(db_pool.clone())).await;
+    jobs::start_scheduler(repo.clone(), ic_client.clone()).await;
     info!("Started background job scheduler");
     
     // Initialize WebSocket server
@@ -115,6 +136,7 @@ async fn main() -> std::io::Result<()> {
     // Start cache cleanup tasks
     canister_notifications::start_cache_cleanup_task();
     api::handlers::claude::start_cache_cleanup_task();
+    api::rate_limit::start_cleanup_task();
     
     // Create app factory
     let app_factory = move || {
@@ -130,9 +152,24 @@ async fn main() -> std::io::Result<()> {
             .wrap(cors)
             // Enable logger middleware
             .wrap(middleware::Logger::default())
+            // Record request counts and latencies per route
+            .wrap_fn(|req, srv| {
+                let started = std::time::Instant::now();
+                let method = req.method().to_string();
+                let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                let fut = srv.call(req);
+                async move {
+                    let res = fut.await?;
+                    metrics::observe_http_request(&method, &route, res.status().as_u16(), started);
+                    Ok(res)
+                }
+            })
+            
+            // Database repository, shared by all workers
+            .app_data(web::Data::from(repo.clone()))
             
-            // Database connection pool
-            .app_data(web::Data::new(db_pool.clone()))
+            // IC client for canister calls
+            .app_data(web::Data::from(ic_client.clone()))
             
             // WebSocket server data
             .app_data(web::Data::new(websocket_server.clone()))
@@ -146,16 +183,10 @@ async fn main() -> std::io::Result<()> {
             // WebSocket status endpoint
             .service(web::resource("/ws-status").route(web::get().to(websocket_handler::websocket_status)))
             
-            // Health check endpoint for load balancer
-            .service(web::resource("/health").route(web::get().to(|_req: HttpRequest| async {
-                actix_web::HttpResponse::Ok()
-                    .content_type("application/json")
-                    .body(r#"{"status":"ok"}"#)
-            })))
-            
             // Canister notification endpoint
             .service(
                 web::resource("/miner-notifications")
+                    .wrap(api::rate_limit::RateLimit::new())
                     .route(web::post().to(canister_notifications::handle_canister_notification))
             )
             
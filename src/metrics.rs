@@ -0,0 +1,232 @@
+use lazy_static::lazy_static;
+use log::error;
+use prometheus::{
+    register_gauge, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, Gauge, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+use std::time::Instant;
+
+use crate::db::repository::{PoolStatus, RegistryStats};
+
+// Latency buckets for outbound calls (IC replicas, Claude API)
+const UPSTREAM_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+lazy_static! {
+    // HTTP server metrics
+    pub static ref HTTP_REQUESTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "http_requests_total",
+        "Total number of HTTP requests by method, route and status",
+        &["method", "route", "status"]
+    ).unwrap();
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "HTTP request latency by method and route",
+        &["method", "route"]
+    ).unwrap();
+
+    // IC agent metrics
+    pub static ref IC_QUERY_DURATION: HistogramVec = register_histogram_vec!(
+        "ic_query_duration_seconds",
+        "Latency of IC calls by canister method",
+        &["method"],
+        UPSTREAM_BUCKETS.to_vec()
+    ).unwrap();
+    pub static ref IC_QUERY_ERRORS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "ic_query_errors_total",
+        "Number of failed IC calls by canister method",
+        &["method"]
+    ).unwrap();
+
+    // Background job metrics
+    pub static ref JOB_DURATION: HistogramVec = register_histogram_vec!(
+        "job_duration_seconds",
+        "Duration of background job runs",
+        &["job"],
+        UPSTREAM_BUCKETS.to_vec()
+    ).unwrap();
+    pub static ref JOB_RUNS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "job_runs_total",
+        "Number of background job runs by outcome",
+        &["job", "status"]
+    ).unwrap();
+
+    // WebSocket metrics
+    pub static ref WEBSOCKET_SESSIONS: IntGauge = register_int_gauge!(
+        "websocket_sessions",
+        "Number of currently connected WebSocket sessions"
+    ).unwrap();
+
+    // Deduplication cache metrics
+    pub static ref DEDUP_CACHE_LOOKUPS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "dedup_cache_lookups_total",
+        "Deduplication cache lookups by cache and result",
+        &["cache", "result"]
+    ).unwrap();
+
+    // Claude API metrics
+    pub static ref CLAUDE_UPSTREAM_DURATION: HistogramVec = register_histogram_vec!(
+        "claude_upstream_duration_seconds",
+        "Latency of requests to the Claude API by outcome",
+        &["status"],
+        UPSTREAM_BUCKETS.to_vec()
+    ).unwrap();
+    pub static ref CLAUDE_TOKENS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "claude_tokens_total",
+        "Claude API token usage by direction",
+        &["type"]
+    ).unwrap();
+
+    // Rate limiting metrics
+    pub static ref RATE_LIMIT_DECISIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "rate_limit_decisions_total",
+        "Rate limit checks by bucket scope and result",
+        &["scope", "result"]
+    ).unwrap();
+
+    // Database pool metrics
+    pub static ref DB_POOL_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "db_pool_connections",
+        "Database pool connections by state",
+        &["state"]
+    ).unwrap();
+    pub static ref DB_POOL_MAX_SIZE: IntGauge = register_int_gauge!(
+        "db_pool_max_size",
+        "Maximum number of connections in the database pool"
+    ).unwrap();
+
+    // Registry metrics, refreshed from the database on every scrape
+    pub static ref REGISTRY_CANISTERS: IntGaugeVec = register_int_gauge_vec!(
+        "registry_canisters",
+        "Number of registered canisters by type",
+        &["type"]
+    ).unwrap();
+    pub static ref REGISTRY_ACTIVE_MINERS: IntGauge = register_int_gauge!(
+        "registry_active_miners",
+        "Number of miners currently reporting as mining"
+    ).unwrap();
+    pub static ref REGISTRY_NETWORK_HASH_RATE: Gauge = register_gauge!(
+        "registry_network_hash_rate",
+        "Sum of the last reported hash rates of all active miners"
+    ).unwrap();
+    pub static ref REGISTRY_BLOCKS_MINED: IntGauge = register_int_gauge!(
+        "registry_blocks_mined",
+        "Total blocks mined across all registered miners"
+    ).unwrap();
+    pub static ref REGISTRY_TOTAL_REWARDS: IntGauge = register_int_gauge!(
+        "registry_total_rewards",
+        "Total rewards earned across all registered miners"
+    ).unwrap();
+}
+
+/// Register all metrics so they are exported before their first observation
+pub fn init() {
+    lazy_static::initialize(&HTTP_REQUESTS_TOTAL);
+    lazy_static::initialize(&HTTP_REQUEST_DURATION);
+    lazy_static::initialize(&IC_QUERY_DURATION);
+    lazy_static::initialize(&IC_QUERY_ERRORS_TOTAL);
+    lazy_static::initialize(&JOB_DURATION);
+    lazy_static::initialize(&JOB_RUNS_TOTAL);
+    lazy_static::initialize(&WEBSOCKET_SESSIONS);
+    lazy_static::initialize(&DEDUP_CACHE_LOOKUPS_TOTAL);
+    lazy_static::initialize(&CLAUDE_UPSTREAM_DURATION);
+    lazy_static::initialize(&CLAUDE_TOKENS_TOTAL);
+    lazy_static::initialize(&RATE_LIMIT_DECISIONS_TOTAL);
+    lazy_static::initialize(&DB_POOL_CONNECTIONS);
+    lazy_static::initialize(&DB_POOL_MAX_SIZE);
+    lazy_static::initialize(&REGISTRY_CANISTERS);
+    lazy_static::initialize(&REGISTRY_ACTIVE_MINERS);
+    lazy_static::initialize(&REGISTRY_NETWORK_HASH_RATE);
+    lazy_static::initialize(&REGISTRY_BLOCKS_MINED);
+    lazy_static::initialize(&REGISTRY_TOTAL_REWARDS);
+}
+
+/// Record a completed HTTP request
+pub fn observe_http_request(method: &str, route: &str, status: u16, started: Instant) {
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&[method, route, &status.to_string()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[method, route])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// Record a completed IC call
+pub fn observe_ic_call(method: &str, started: Instant, success: bool) {
+    IC_QUERY_DURATION
+        .with_label_values(&[method])
+        .observe(started.elapsed().as_secs_f64());
+    if !success {
+        IC_QUERY_ERRORS_TOTAL.with_label_values(&[method]).inc();
+    }
+}
+
+/// Record a completed background job run
+pub fn observe_job(job: &str, started: Instant, success: bool) {
+    JOB_DURATION
+        .with_label_values(&[job])
+        .observe(started.elapsed().as_secs_f64());
+    JOB_RUNS_TOTAL
+        .with_label_values(&[job, if success { "success" } else { "error" }])
+        .inc();
+}
+
+/// Record a deduplication cache lookup
+pub fn record_dedup_lookup(cache: &str, hit: bool) {
+    DEDUP_CACHE_LOOKUPS_TOTAL
+        .with_label_values(&[cache, if hit { "hit" } else { "miss" }])
+        .inc();
+}
+
+/// Record a completed request to the Claude API
+pub fn observe_claude_request(started: Instant, status: &str) {
+    CLAUDE_UPSTREAM_DURATION
+        .with_label_values(&[status])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// Record Claude API token usage
+pub fn record_claude_usage(input_tokens: u32, output_tokens: u32) {
+    CLAUDE_TOKENS_TOTAL.with_label_values(&["input"]).inc_by(input_tokens as u64);
+    CLAUDE_TOKENS_TOTAL.with_label_values(&["output"]).inc_by(output_tokens as u64);
+}
+
+/// Record a rate limit check
+pub fn record_rate_limit(scope: &str, allowed: bool) {
+    RATE_LIMIT_DECISIONS_TOTAL
+        .with_label_values(&[scope, if allowed { "allowed" } else { "limited" }])
+        .inc();
+}
+
+/// Refresh the database pool gauges from the pool state
+pub fn update_pool_gauges(status: PoolStatus) {
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["idle"])
+        .set(status.idle_connections as i64);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set(status.connections.saturating_sub(status.idle_connections) as i64);
+    DB_POOL_MAX_SIZE.set(status.max_size as i64);
+}
+
+/// Refresh the registry gauges from the registry statistics
+pub fn update_registry_gauges(stats: &RegistryStats) {
+    REGISTRY_CANISTERS.reset();
+    for (canister_type, count) in &stats.canisters_by_type {
+        REGISTRY_CANISTERS.with_label_values(&[canister_type]).set(*count);
+    }
+    REGISTRY_ACTIVE_MINERS.set(stats.active_miners);
+    REGISTRY_NETWORK_HASH_RATE.set(stats.network_hash_rate);
+    REGISTRY_BLOCKS_MINED.set(stats.blocks_mined);
+    REGISTRY_TOTAL_REWARDS.set(stats.total_rewards);
+}
+
+/// Encode all registered metrics in the Prometheus text format
+pub fn render() -> String {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
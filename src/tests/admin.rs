@@ -0,0 +1,234 @@
+use actix_web::{http::StatusCode, test};
+use candid::{IDLArgs, IDLValue};
+use serde_json::{json, Value};
+
+use crate::db::models::admin::{Admin, AdminRole};
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::tests::{unique_canister_id, TestContext, TEST_PASSWORD};
+
+async fn stored_canister(ctx: &TestContext) -> String {
+    let canister_id = unique_canister_id();
+    let canister = Canister::new("2vxsx-fae".to_string(), canister_id.clone(), CanisterType::Token, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    canister_id
+}
+
+#[actix_web::test]
+async fn admin_routes_require_credentials() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::get().uri("/admin/audit-log").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get().uri("/admin/audit-log")
+        .insert_header(("X-API-KEY", "not-a-key"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn api_key_authenticates_and_role_is_enforced() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (_, superadmin_key) = ctx.create_admin(AdminRole::Superadmin).await;
+    let (viewer, viewer_key) = ctx.create_admin(AdminRole::Viewer).await;
+    let canister_id = stored_canister(&ctx).await;
+
+    let req = test::TestRequest::delete().uri(&format!("/admin/canisters/{}", canister_id))
+        .insert_header(("X-API-KEY", viewer_key.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::delete().uri(&format!("/admin/canisters/{}", canister_id))
+        .insert_header(("X-API-KEY", superadmin_key.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert!(ctx.repo.canisters().find_by_canister_id(&canister_id).await.unwrap().is_none());
+
+    // Authenticating records the last use of the key
+    let viewer = ctx.repo.admins().find_by_id(&viewer.id).await.unwrap().unwrap();
+    assert!(viewer.last_used_at.is_some());
+}
+
+#[actix_web::test]
+async fn soft_deleted_canister_can_be_restored() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (_, api_key) = ctx.create_admin(AdminRole::RegistryModerator).await;
+    let canister_id = stored_canister(&ctx).await;
+
+    let req = test::TestRequest::delete().uri(&format!("/admin/canisters/{}", canister_id))
+        .insert_header(("X-API-KEY", api_key.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get().uri("/admin/canisters/trash")
+        .insert_header(("X-API-KEY", api_key.as_str()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"][0]["canister_id"], canister_id);
+
+    let req = test::TestRequest::post().uri(&format!("/admin/canisters/{}/restore", canister_id))
+        .insert_header(("X-API-KEY", api_key.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert!(ctx.repo.canisters().find_by_canister_id(&canister_id).await.unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn import_rejects_canisters_in_the_trash() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (_, api_key) = ctx.create_admin(AdminRole::Superadmin).await;
+    let canister_id = stored_canister(&ctx).await;
+
+    let req = test::TestRequest::get().uri("/admin/export")
+        .insert_header(("X-API-KEY", api_key.as_str()))
+        .to_request();
+    let export = test::read_body(test::call_service(&app, req).await).await;
+    assert!(ctx.repo.canisters().soft_delete(&canister_id).await.unwrap());
+
+    let req = test::TestRequest::post().uri("/admin/import")
+        .insert_header(("X-API-KEY", api_key.as_str()))
+        .set_payload(export)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["applied"], false);
+    assert_eq!(body["data"]["tables"][0]["errors"][0]["key"], canister_id);
+    assert_eq!(body["data"]["tables"][0]["updated"], 0);
+    assert!(ctx.repo.canisters().find_by_canister_id(&canister_id).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn login_issues_a_bearer_token() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (admin, _) = ctx.create_admin(AdminRole::Superadmin).await;
+
+    let req = test::TestRequest::post().uri("/admin/login")
+        .set_json(json!({ "username": admin.username, "password": "wrong password" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::post().uri("/admin/login")
+        .set_json(json!({ "username": admin.username, "password": TEST_PASSWORD }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    let token = body["data"]["access_token"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::get().uri("/admin/audit-log")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get().uri("/admin/audit-log")
+        .insert_header(("Authorization", "Bearer not-a-token"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn login_does_not_reveal_which_usernames_exist() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (admin, _) = ctx.create_admin(AdminRole::Viewer).await;
+
+    let req = test::TestRequest::post().uri("/admin/login")
+        .set_json(json!({ "username": "no-such-admin", "password": TEST_PASSWORD }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // A locked account fails like a wrong password, even with the right one
+    ctx.repo.admins().record_login_failure(&admin.id, 1, 900).await.unwrap();
+    let req = test::TestRequest::post().uri("/admin/login")
+        .set_json(json!({ "username": admin.username, "password": TEST_PASSWORD }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn bootstrap_credentials_are_only_checked_on_an_empty_database() {
+    // No other test reads ADMIN_PASSWORD
+    std::env::set_var("ADMIN_PASSWORD", "admin123");
+
+    let ctx = TestContext::new();
+    assert!(Admin::check_or_create_default(ctx.repo.admins()).await.is_err());
+    assert!(ctx.repo.admins().find_all().await.unwrap().is_empty());
+
+    // A leftover short password must not stop an installation that already has admins
+    ctx.create_admin(AdminRole::Superadmin).await;
+    assert!(Admin::check_or_create_default(ctx.repo.admins()).await.is_ok());
+    assert_eq!(ctx.repo.admins().find_all().await.unwrap().len(), 1);
+
+    std::env::remove_var("ADMIN_PASSWORD");
+}
+
+#[actix_web::test]
+async fn miner_commands_are_sent_and_recorded() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (_, operator_key) = ctx.create_admin(AdminRole::MinerOperator).await;
+    let (_, viewer_key) = ctx.create_admin(AdminRole::Viewer).await;
+    let miner_id = unique_canister_id();
+    let canister = Canister::new("2vxsx-fae".to_string(), miner_id.clone(), CanisterType::Miner, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    ctx.ic.with_candid_service(&miner_id, "service : { start_mining : () -> (text); get_info : () -> (text) query }");
+    let reply = IDLArgs::new(&[IDLValue::Text("started".to_string())]).to_bytes().unwrap();
+    ctx.ic.with_query_reply(&miner_id, "start_mining", reply);
+    let uri = format!("/admin/miners/{}/commands", miner_id);
+
+    let req = test::TestRequest::post().uri(&uri)
+        .insert_header(("X-API-KEY", viewer_key.as_str()))
+        .set_json(json!({ "command": "start_mining" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post().uri(&uri)
+        .insert_header(("X-API-KEY", operator_key.as_str()))
+        .set_json(json!({ "command": "set_speed", "speed_percentage": 150 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post().uri(&uri)
+        .insert_header(("X-API-KEY", operator_key.as_str()))
+        .set_json(json!({ "command": "start_mining" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["status"], "succeeded");
+    assert_eq!(body["data"]["reply"], "started");
+    assert_eq!(ctx.ic.call_count("start_mining", &miner_id), 1);
+
+    let req = test::TestRequest::get().uri(&uri)
+        .insert_header(("X-API-KEY", viewer_key.as_str()))
+        .to_request();
+    let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["command"], "start_mining");
+}
+
+#[actix_web::test]
+async fn identity_rotation_requires_superadmin() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let (_, viewer_key) = ctx.create_admin(AdminRole::Viewer).await;
+
+    let req = test::TestRequest::get().uri("/admin/identity")
+        .insert_header(("X-API-KEY", viewer_key.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["data"]["retired"].is_array());
+
+    let req = test::TestRequest::post().uri("/admin/identity/rotate")
+        .insert_header(("X-API-KEY", viewer_key.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+}
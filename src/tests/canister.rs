@@ -0,0 +1,305 @@
+use actix_web::{http::StatusCode, test};
+use candid::{IDLArgs, IDLValue};
+use serde_json::{json, Value};
+
+use crate::db::models::audit_log::AuditLogFilter;
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::miner_info::{MinerInfo, MinerType};
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::tests::{eventually, unique_canister_id, TestContext};
+
+fn registration(canister_id: &str, canister_type: &str) -> Value {
+    json!({
+        "principal": "2vxsx-fae",
+        "canister_id": canister_id,
+        "canister_type": canister_type,
+        "module_hash": null,
+    })
+}
+
+#[actix_web::test]
+async fn register_canister_stores_it_and_fetches_module_hash() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let canister_id = unique_canister_id();
+    let hash = "ab".repeat(32);
+    ctx.ic.with_module_hash(&canister_id, &hash);
+
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&canister_id, "token"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["canister_id"], canister_id);
+    assert_eq!(body["data"]["canister_type"], "Token");
+
+    // The module hash is fetched in the background
+    let repo = ctx.repo.clone();
+    let stored = eventually(|| {
+        let repo = repo.clone();
+        let canister_id = canister_id.clone();
+        let hash = hash.clone();
+        async move {
+            matches!(repo.canisters().find_by_canister_id(&canister_id).await,
+                Ok(Some(canister)) if canister.module_hash == Some(hash))
+        }
+    }).await;
+    assert!(stored, "module hash was not stored");
+    assert_eq!(ctx.ic.call_count("get_module_hash", &canister_id), 1);
+
+    let filter = AuditLogFilter { action: Some("canister.register".to_string()), ..Default::default() };
+    assert_eq!(ctx.repo.audit_log().count(&filter).await.unwrap(), 1);
+}
+
+#[actix_web::test]
+async fn register_canister_with_module_hash_skips_ic_call() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let canister_id = unique_canister_id();
+    let mut request = registration(&canister_id, "miner");
+    request["module_hash"] = json!("cd".repeat(32));
+
+    let req = test::TestRequest::post().uri("/canisters").set_json(request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    assert_eq!(ctx.ic.call_count("get_module_hash", &canister_id), 0);
+}
+
+#[actix_web::test]
+async fn register_canister_rejects_duplicates_and_invalid_types() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let canister_id = unique_canister_id();
+
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&canister_id, "token"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&canister_id, "token"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&unique_canister_id(), "spaceship"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    // Single and batch registration share the same validation
+    let mut request = registration(&unique_canister_id(), "token");
+    request["module_hash"] = json!("not a hash");
+    let req = test::TestRequest::post().uri("/canisters").set_json(request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let canisters = ctx.repo.canisters().find_all().await.unwrap();
+    assert_eq!(canisters.len(), 1);
+}
+
+#[actix_web::test]
+async fn register_canister_detects_type_from_candid_service() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let miner_id = unique_canister_id();
+    let service = "service : { get_info : () -> (text) query; get_mining_stats : () -> (opt text) query }";
+    ctx.ic.with_candid_service(&miner_id, service);
+
+    // Registered as a token, but it is a miner
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&miner_id, "token"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let mut request = registration(&miner_id, "token");
+    request["canister_type"] = Value::Null;
+    let req = test::TestRequest::post().uri("/canisters").set_json(request).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"]["canister_type"], "Miner");
+
+    let req = test::TestRequest::get().uri(&format!("/canisters/{}/interface", miner_id)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"]["candid_service"], service);
+
+    // Without metadata the type cannot be detected
+    let mut request = registration(&unique_canister_id(), "token");
+    request["canister_type"] = Value::Null;
+    let req = test::TestRequest::post().uri("/canisters").set_json(request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // An interface that does not type-check is stored, but the requested type is trusted
+    let unreadable_id = unique_canister_id();
+    ctx.ic.with_candid_service(&unreadable_id, "service : Miner");
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&unreadable_id, "miner"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["canister_type"], "Miner");
+
+    let undetectable_id = unique_canister_id();
+    ctx.ic.with_candid_service(&undetectable_id, "service : Miner");
+    let mut request = registration(&undetectable_id, "miner");
+    request["canister_type"] = Value::Null;
+    let req = test::TestRequest::post().uri("/canisters").set_json(request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn query_proxy_calls_allowed_methods_of_registered_canisters() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let token_id = unique_canister_id();
+    ctx.ic.with_candid_service(&token_id, "service : { get_all_info : () -> (text) query; set_name : (text) -> () }");
+    let reply = IDLArgs::new(&[IDLValue::Text("hello".to_string())]).to_bytes().unwrap();
+    ctx.ic.with_query_reply(&token_id, "get_all_info", reply);
+    let req = test::TestRequest::post().uri("/canisters")
+        .set_json(registration(&token_id, "token"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    // The second query is answered from the cache
+    for _ in 0..2 {
+        let req = test::TestRequest::post().uri(&format!("/canisters/{}/query/get_all_info", token_id))
+            .set_json(json!({ "args": [] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"], "hello");
+    }
+    assert_eq!(ctx.ic.call_count("get_all_info", &token_id), 1);
+
+    let req = test::TestRequest::post().uri(&format!("/canisters/{}/query/set_name", token_id))
+        .set_json(json!({ "args": ["new name"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post().uri(&format!("/canisters/{}/query/get_all_info", unique_canister_id()))
+        .set_json(json!({ "args": [] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn batch_registration_reports_each_entry() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let token_id = unique_canister_id();
+    let miner_id = unique_canister_id();
+
+    let req = test::TestRequest::post().uri("/canisters/batch")
+        .set_json(json!({
+            "canisters": [
+                registration(&token_id, "token"),
+                registration(&miner_id, "miner"),
+                registration(&miner_id, "miner"),
+            ],
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    let statuses: Vec<&str> = body["data"].as_array().unwrap().iter()
+        .map(|item| item["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["registered", "registered", "failed"]);
+    assert_eq!(ctx.repo.canisters().find_all().await.unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn all_or_nothing_batch_registers_nothing_on_error() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::post().uri("/canisters/batch")
+        .set_json(json!({
+            "canisters": [
+                registration(&unique_canister_id(), "token"),
+                registration(&unique_canister_id(), "spaceship"),
+            ],
+            "all_or_nothing": true,
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"][0]["status"], "skipped");
+    assert!(ctx.repo.canisters().find_all().await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn canisters_are_listed_by_type() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    for canister_type in ["token", "miner", "miner"] {
+        let req = test::TestRequest::post().uri("/canisters")
+            .set_json(registration(&unique_canister_id(), canister_type))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    let req = test::TestRequest::get().uri("/canisters/type/miner").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    let req = test::TestRequest::get().uri("/system/status").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"]["canisters_count"], 3);
+}
+
+#[actix_web::test]
+async fn principal_portfolio_groups_canisters_and_totals_miners() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let owner = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+    let token_id = unique_canister_id();
+    let miner_ids = [unique_canister_id(), unique_canister_id()];
+    ctx.repo.canisters().save(&Canister::new(owner.to_string(), token_id.clone(), CanisterType::Token, None)).await.unwrap();
+    ctx.repo.tokens().save(&TokenInfo::new(
+        token_id.clone(), "Test".to_string(), "TST".to_string(), 2, 1_000, 10, None, "{}".to_string(),
+    )).await.unwrap();
+    for (i, miner_id) in miner_ids.iter().enumerate() {
+        ctx.repo.canisters().save(&Canister::new(owner.to_string(), miner_id.clone(), CanisterType::Miner, None)).await.unwrap();
+        let miner = MinerInfo::new(miner_id.clone(), MinerType::Normal, i == 0, Some(token_id.clone()), 100, 10, "{}".to_string());
+        ctx.repo.miners().save(&miner).await.unwrap();
+        let stats = MiningStats::new(miner_id.clone(), 5_000, 1, 0, 150, 20.0, 0);
+        ctx.repo.mining_stats().save(&stats).await.unwrap();
+    }
+    // Someone else's canister is left out
+    ctx.repo.canisters().save(&Canister::new("2vxsx-fae".to_string(), unique_canister_id(), CanisterType::Wallet, None)).await.unwrap();
+
+    let req = test::TestRequest::get().uri(&format!("/principals/{}", owner)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    let data = &body["data"];
+    assert_eq!(data["canister_count"], 3);
+    assert_eq!(data["canisters"]["token"][0]["token_info"]["ticker"], "TST");
+    assert_eq!(data["canisters"]["miner"].as_array().unwrap().len(), 2);
+    assert!(data["canisters"]["wallet"].is_null());
+    assert_eq!(data["mining"]["active_miners"], 1);
+    assert_eq!(data["mining"]["total_hash_rate"], 20.0);
+    assert_eq!(data["mining"]["blocks_mined"], 2);
+    assert_eq!(data["mining"]["rewards"][0]["total_rewards"], 300);
+    assert_eq!(data["mining"]["rewards"][0]["formatted"], "3");
+}
+
+#[actix_web::test]
+async fn interface_files_are_served_by_name_only() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::get().uri("/interfaces").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let interfaces = body["data"].as_array().unwrap();
+    assert_eq!(interfaces.len(), 4);
+    let ledger = interfaces.iter().find(|i| i["name"] == "ledger").unwrap();
+    assert_eq!(ledger["files"], json!(["ledger.did", "ledger.did.d.ts", "ledger.mo"]));
+
+    for uri in ["/interfaces/foo.did", "/interfaces/ledger.txt", "/interfaces/..%2Fledger.did"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND, "{}", uri);
+    }
+}
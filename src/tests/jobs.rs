@@ -0,0 +1,67 @@
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::miner_info::{MinerInfo, MinerType};
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::jobs::tasks::{update_ledgers, update_miners, update_tokens};
+use crate::tests::{unique_canister_id, TestContext};
+
+async fn register(ctx: &TestContext, canister_type: CanisterType) -> String {
+    let canister_id = unique_canister_id();
+    let canister = Canister::new("2vxsx-fae".to_string(), canister_id.clone(), canister_type, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    canister_id
+}
+
+#[actix_web::test]
+async fn update_tokens_saves_token_info_and_skips_failures() {
+    let ctx = TestContext::new();
+    let token_id = register(&ctx, CanisterType::Token).await;
+    let broken_id = register(&ctx, CanisterType::Token).await;
+    ctx.ic.with_token(TokenInfo::new(
+        token_id.clone(), "Test".to_string(), "TST".to_string(), 8, 1_000, 10, None, "{}".to_string(),
+    ));
+
+    update_tokens::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let token = ctx.repo.tokens().find_by_canister_id(&token_id).await.unwrap().unwrap();
+    assert_eq!(token.ticker, "TST");
+    assert!(ctx.repo.tokens().find_by_canister_id(&broken_id).await.unwrap().is_none());
+    assert_eq!(ctx.ic.call_count("get_token_all_info", &broken_id), 1);
+}
+
+#[actix_web::test]
+async fn update_ledgers_only_queries_ledgers() {
+    let ctx = TestContext::new();
+    let ledger_id = register(&ctx, CanisterType::Ledger).await;
+    let token_id = register(&ctx, CanisterType::Token).await;
+    ctx.ic.with_ledger(LedgerInfo::new(
+        ledger_id.clone(), "Test".to_string(), "TST".to_string(), 8, "10000".to_string(),
+        "340282366920938463463374607431768211455".to_string(), None, "ICRC-1,ICRC-2".to_string(), "{}".to_string(),
+    ));
+
+    update_ledgers::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let ledger = ctx.repo.ledgers().find_by_canister_id(&ledger_id).await.unwrap().unwrap();
+    assert_eq!(ledger.total_supply, "340282366920938463463374607431768211455");
+    assert_eq!(ctx.ic.call_count("get_ledger_info", &token_id), 0);
+    assert_eq!(ctx.repo.registry().stats().await.unwrap().ledgers, 1);
+}
+
+#[actix_web::test]
+async fn update_miners_saves_miner_info_and_stats() {
+    let ctx = TestContext::new();
+    let miner_id = register(&ctx, CanisterType::Miner).await;
+    let token_id = register(&ctx, CanisterType::Token).await;
+    let miner = MinerInfo::new(miner_id.clone(), MinerType::Normal, true, Some(token_id.clone()), 100, 10, "{}".to_string());
+    let stats = MiningStats::new(miner_id.clone(), 5_000, 2, 1, 300, 42.5, 0);
+    ctx.ic.with_miner(miner, Some(stats));
+
+    update_miners::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let miners = ctx.repo.miners().find_by_token(&token_id).await.unwrap();
+    assert_eq!(miners.len(), 1);
+    let stats = ctx.repo.mining_stats().find_by_canister_id(&miner_id).await.unwrap().unwrap();
+    assert_eq!(stats.blocks_mined, 2);
+    assert_eq!(ctx.repo.mining_stats().total_hash_rate_for_token(&token_id).await.unwrap(), 42.5);
+}
@@ -0,0 +1,172 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::Value;
+
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::ledger_transaction::{LedgerTransaction, TransactionKind};
+use crate::db::models::token_info::TokenInfo;
+use crate::jobs::tasks::index_ledgers;
+use crate::tests::{unique_canister_id, TestContext};
+
+const ALICE: &str = "2vxsx-fae";
+const BOB: &str = "aaaaa-aa";
+
+// Register a ledger with its info, as the update ledgers job would have left it
+async fn register_ledger(ctx: &TestContext) -> String {
+    let canister_id = unique_canister_id();
+    let canister = Canister::new(ALICE.to_string(), canister_id.clone(), CanisterType::Ledger, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    ctx.repo.ledgers().save(&LedgerInfo::new(
+        canister_id.clone(), "Test".to_string(), "TST".to_string(), 8, "10".to_string(),
+        "1000".to_string(), None, "ICRC-1,ICRC-3".to_string(), "{}".to_string(),
+    )).await.unwrap();
+    canister_id
+}
+
+fn transaction(
+    ledger_id: &str,
+    block_index: u64,
+    kind: TransactionKind,
+    from: Option<&str>,
+    to: Option<&str>,
+    amount: &str,
+    fee: Option<&str>,
+) -> LedgerTransaction {
+    LedgerTransaction {
+        canister_id: ledger_id.to_string(),
+        block_index,
+        kind,
+        from_owner: from.map(str::to_string),
+        from_subaccount: None,
+        to_owner: to.map(str::to_string),
+        to_subaccount: None,
+        spender: None,
+        amount: amount.to_string(),
+        fee: fee.map(str::to_string),
+        memo: None,
+        timestamp: 1_700_000_000_000_000_000 + block_index,
+    }
+}
+
+// A mint to Alice followed by transfers alternating between Alice and Bob
+fn blocks(ledger_id: &str, count: u64) -> Vec<LedgerTransaction> {
+    (0..count).map(|block_index| match block_index {
+        0 => transaction(ledger_id, 0, TransactionKind::Mint, None, Some(ALICE), "1000000", None),
+        i if i % 2 == 1 => transaction(ledger_id, i, TransactionKind::Transfer, Some(ALICE), Some(BOB), "100", Some("10")),
+        i => transaction(ledger_id, i, TransactionKind::Transfer, Some(BOB), Some(ALICE), "50", Some("10")),
+    }).collect()
+}
+
+#[actix_web::test]
+async fn index_ledgers_resumes_from_checkpoint() {
+    let ctx = TestContext::new();
+    let ledger_id = register_ledger(&ctx).await;
+    ctx.ic.with_ledger_blocks(&ledger_id, blocks(&ledger_id, index_ledgers::PAGE_SIZE + 5));
+
+    index_ledgers::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let state = ctx.repo.ledger_transactions().index_state(&ledger_id).await.unwrap().unwrap();
+    assert_eq!(state.next_block, index_ledgers::PAGE_SIZE + 5);
+    assert_eq!(ctx.ic.call_count("get_ledger_blocks", &ledger_id), 2);
+
+    // New blocks are read from the checkpoint, earlier ones are not stored twice
+    ctx.ic.with_ledger_blocks(&ledger_id, blocks(&ledger_id, index_ledgers::PAGE_SIZE + 8));
+    index_ledgers::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let state = ctx.repo.ledger_transactions().index_state(&ledger_id).await.unwrap().unwrap();
+    assert_eq!(state.next_block, index_ledgers::PAGE_SIZE + 8);
+    assert_eq!(state.log_length, index_ledgers::PAGE_SIZE + 8);
+    assert_eq!(ctx.ic.call_count("get_ledger_blocks", &ledger_id), 3);
+    let total = ctx.repo.ledger_transactions().count_by_account(&ledger_id, ALICE, None).await.unwrap();
+    assert_eq!(total, (index_ledgers::PAGE_SIZE + 8) as i64);
+}
+
+#[actix_web::test]
+async fn account_transactions_are_paged_newest_first() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let ledger_id = register_ledger(&ctx).await;
+    ctx.ic.with_ledger_blocks(&ledger_id, blocks(&ledger_id, 5));
+    index_ledgers::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let uri = format!("/ledgers/{}/accounts/{}/transactions?limit=2&offset=1", ledger_id, BOB);
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["total"], 4);
+    let indices: Vec<u64> = body["data"]["transactions"].as_array().unwrap().iter()
+        .map(|t| t["block_index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(indices, vec![3, 2]);
+
+    // The default subaccount may be given explicitly, other subaccounts have no history
+    let uri = format!("/ledgers/{}/accounts/{}/transactions?subaccount={}", ledger_id, BOB, "00".repeat(32));
+    let body: Value = test::read_body_json(
+        test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await
+    ).await;
+    assert_eq!(body["data"]["total"], 4);
+    let uri = format!("/ledgers/{}/accounts/{}/transactions?subaccount={}", ledger_id, BOB, "01".repeat(32));
+    let body: Value = test::read_body_json(
+        test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await
+    ).await;
+    assert_eq!(body["data"]["total"], 0);
+
+    let uri = format!("/ledgers/{}/accounts/{}/transactions?subaccount=xyz", ledger_id, BOB);
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn holders_follow_indexed_balances() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let ledger_id = register_ledger(&ctx).await;
+    ctx.ic.with_ledger_blocks(&ledger_id, vec![
+        transaction(&ledger_id, 0, TransactionKind::Mint, None, Some(ALICE), "1000", None),
+        transaction(&ledger_id, 1, TransactionKind::Transfer, Some(ALICE), Some(BOB), "300", Some("10")),
+        // Fees left out by get_transactions are the ledger's fee
+        transaction(&ledger_id, 2, TransactionKind::Approve, Some(BOB), None, "5000", None),
+        transaction(&ledger_id, 3, TransactionKind::Burn, Some(ALICE), None, "690", None),
+    ]);
+    index_ledgers::run(ctx.repo.clone(), ctx.ic.clone()).await.unwrap();
+
+    let resp = test::call_service(
+        &app, test::TestRequest::get().uri(&format!("/tokens/{}/holders", ledger_id)).to_request()
+    ).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["holder_count"], 1);
+    assert_eq!(body["data"]["top_holders"][0]["owner"], BOB);
+    assert_eq!(body["data"]["top_holders"][0]["balance"], "290");
+    assert_eq!(body["data"]["top_holders_share"], 29.0);
+    assert_eq!(body["data"]["indexed_block"], 4);
+}
+
+#[actix_web::test]
+async fn token_balances_are_read_from_the_tokens_ledger_and_cached() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let ledger_id = register_ledger(&ctx).await;
+    let token_id = unique_canister_id();
+    let canister = Canister::new(ALICE.to_string(), token_id.clone(), CanisterType::Token, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    ctx.repo.tokens().save(&TokenInfo::new(
+        token_id.clone(), "Test".to_string(), "TST".to_string(), 8, 1_000, 10, None,
+        serde_json::json!({ "ledger_id": ledger_id }).to_string(),
+    )).await.unwrap();
+    ctx.ic.with_balance(&ledger_id, BOB, None, "340282366920938463463374607431768211455");
+
+    for _ in 0..2 {
+        let uri = format!("/tokens/{}/balances/{}", token_id, BOB);
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["ledger_id"], ledger_id);
+        assert_eq!(body["data"]["balance"], "340282366920938463463374607431768211455");
+    }
+    assert_eq!(ctx.ic.call_count("get_balance", &ledger_id), 1);
+
+    let uri = format!("/tokens/{}/balances/{}", unique_canister_id(), BOB);
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
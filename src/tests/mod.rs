@@ -0,0 +1,219 @@
+//! Handler and job tests
+//!
+//! Run against an in-memory database and a mock IC client, so they need no network.
+
+mod admin;
+mod canister;
+mod jobs;
+mod ledgers;
+mod notifications;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, App};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::api;
+use crate::canister_notifications;
+use crate::db::models::admin::{Admin, AdminRole};
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::ledger_transaction::LedgerTransaction;
+use crate::db::models::miner_info::MinerInfo;
+use crate::db::models::mining_stats::MiningStats;
+use crate::db::models::token_info::TokenInfo;
+use crate::db::repository::{Repository, SqliteRepository};
+use crate::ic::client::IcClient;
+use crate::ic::services::ledger::{BlockMethod, LedgerBlocks};
+
+/// IC client answering from canned responses and counting the calls it gets
+#[derive(Default)]
+pub struct MockIcClient {
+    tokens: Mutex<HashMap<String, TokenInfo>>,
+    ledgers: Mutex<HashMap<String, LedgerInfo>>,
+    ledger_blocks: Mutex<HashMap<String, Vec<LedgerTransaction>>>,
+    balances: Mutex<HashMap<String, String>>,
+    miners: Mutex<HashMap<String, (MinerInfo, Option<MiningStats>)>>,
+    module_hashes: Mutex<HashMap<String, String>>,
+    candid_services: Mutex<HashMap<String, String>>,
+    query_replies: Mutex<HashMap<String, Vec<u8>>>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl MockIcClient {
+    pub fn with_token(&self, token: TokenInfo) {
+        self.tokens.lock().unwrap().insert(token.canister_id.clone(), token);
+    }
+
+    pub fn with_ledger(&self, ledger: LedgerInfo) {
+        self.ledgers.lock().unwrap().insert(ledger.canister_id.clone(), ledger);
+    }
+
+    /// Blocks of a ledger, the block at index `i` being `blocks[i]`
+    pub fn with_ledger_blocks(&self, canister_id: &str, blocks: Vec<LedgerTransaction>) {
+        self.ledger_blocks.lock().unwrap().insert(canister_id.to_string(), blocks);
+    }
+
+    pub fn with_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>, balance: &str) {
+        let account = format!("{}:{}:{}", canister_id, owner, subaccount.unwrap_or(""));
+        self.balances.lock().unwrap().insert(account, balance.to_string());
+    }
+
+    pub fn with_miner(&self, miner: MinerInfo, stats: Option<MiningStats>) {
+        self.miners.lock().unwrap().insert(miner.canister_id.clone(), (miner, stats));
+    }
+
+    pub fn with_module_hash(&self, canister_id: &str, hash: &str) {
+        self.module_hashes.lock().unwrap().insert(canister_id.to_string(), hash.to_string());
+    }
+
+    pub fn with_candid_service(&self, canister_id: &str, service: &str) {
+        self.candid_services.lock().unwrap().insert(canister_id.to_string(), service.to_string());
+    }
+
+    /// Encoded reply to queries and update calls of `method` on a canister
+    pub fn with_query_reply(&self, canister_id: &str, method: &str, reply: Vec<u8>) {
+        self.query_replies.lock().unwrap().insert(format!("{}:{}", canister_id, method), reply);
+    }
+
+    /// Number of calls of `method` made for `canister_id`
+    pub fn call_count(&self, method: &str, canister_id: &str) -> usize {
+        let call = format!("{}:{}", method, canister_id);
+        self.calls.lock().unwrap().iter().filter(|c| **c == call).count()
+    }
+
+    fn record(&self, method: &str, canister_id: &str) {
+        self.calls.lock().unwrap().push(format!("{}:{}", method, canister_id));
+    }
+}
+
+#[async_trait]
+impl IcClient for MockIcClient {
+    async fn get_token_all_info(&self, canister_id: &str) -> Result<TokenInfo> {
+        self.record("get_token_all_info", canister_id);
+        self.tokens.lock().unwrap().get(canister_id).cloned()
+            .ok_or_else(|| anyhow!("Canister {} is not a token", canister_id))
+    }
+
+    async fn get_ledger_info(&self, canister_id: &str) -> Result<LedgerInfo> {
+        self.record("get_ledger_info", canister_id);
+        self.ledgers.lock().unwrap().get(canister_id).cloned()
+            .ok_or_else(|| anyhow!("Canister {} is not a ledger", canister_id))
+    }
+
+    async fn get_ledger_blocks(&self, canister_id: &str, _method: BlockMethod, start: u64, length: u64) -> Result<LedgerBlocks> {
+        self.record("get_ledger_blocks", canister_id);
+        let ledger_blocks = self.ledger_blocks.lock().unwrap();
+        let blocks = ledger_blocks.get(canister_id)
+            .ok_or_else(|| anyhow!("Canister {} has no blocks", canister_id))?;
+        let log_length = blocks.len() as u64;
+        let next_block = (start + length).min(log_length).max(start);
+        let transactions = blocks[start.min(log_length) as usize..next_block as usize].to_vec();
+        Ok(LedgerBlocks { log_length, next_block, transactions })
+    }
+
+    async fn get_balance(&self, canister_id: &str, owner: &str, subaccount: Option<&str>) -> Result<String> {
+        self.record("get_balance", canister_id);
+        let account = format!("{}:{}:{}", canister_id, owner, subaccount.unwrap_or(""));
+        Ok(self.balances.lock().unwrap().get(&account).cloned().unwrap_or_else(|| "0".to_string()))
+    }
+
+    async fn get_miner_info(&self, canister_id: &str) -> Result<(MinerInfo, Option<MiningStats>)> {
+        self.record("get_miner_info", canister_id);
+        self.miners.lock().unwrap().get(canister_id).cloned()
+            .ok_or_else(|| anyhow!("Canister {} is not a miner", canister_id))
+    }
+
+    async fn get_module_hash(&self, canister_id: &str) -> Result<String> {
+        self.record("get_module_hash", canister_id);
+        self.module_hashes.lock().unwrap().get(canister_id).cloned()
+            .ok_or_else(|| anyhow!("No module hash found for canister {}", canister_id))
+    }
+
+    async fn get_candid_service(&self, canister_id: &str) -> Result<String> {
+        self.record("get_candid_service", canister_id);
+        self.candid_services.lock().unwrap().get(canister_id).cloned()
+            .ok_or_else(|| anyhow!("No candid:service metadata found for canister {}", canister_id))
+    }
+
+    async fn query_raw(&self, canister_id: &str, method: &str, _arg: Vec<u8>) -> Result<Vec<u8>> {
+        self.record(method, canister_id);
+        self.query_replies.lock().unwrap().get(&format!("{}:{}", canister_id, method)).cloned()
+            .ok_or_else(|| anyhow!("Canister {} has no method {}", canister_id, method))
+    }
+
+    async fn update_raw(&self, canister_id: &str, method: &str, arg: Vec<u8>) -> Result<Vec<u8>> {
+        self.query_raw(canister_id, method, arg).await
+    }
+
+    async fn reset_agent(&self) {}
+}
+
+/// A fresh in-memory registry and mock IC client
+pub struct TestContext {
+    pub repo: Arc<dyn Repository>,
+    pub ic: Arc<MockIcClient>,
+}
+
+impl TestContext {
+    pub fn new() -> Self {
+        let repo = SqliteRepository::open_in_memory().expect("in-memory database");
+        Self { repo: Arc::new(repo), ic: Arc::new(MockIcClient::default()) }
+    }
+
+    /// The API as configured in `main`, wired to this context
+    pub fn app(&self) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        let ic: Arc<dyn IcClient> = self.ic.clone();
+        App::new()
+            .app_data(web::Data::from(self.repo.clone()))
+            .app_data(web::Data::from(ic))
+            .configure(api::configure_routes)
+            .service(
+                web::resource("/miner-notifications")
+                    .route(web::post().to(canister_notifications::handle_canister_notification))
+            )
+    }
+
+    /// Store an admin and return it with its API key
+    pub async fn create_admin(&self, role: AdminRole) -> (Admin, String) {
+        let username = format!("admin-{}", Uuid::new_v4());
+        let (admin, api_key) = Admin::new(username, TEST_PASSWORD, role).expect("admin");
+        self.repo.admins().save(&admin).await.expect("save admin");
+        (admin, api_key)
+    }
+}
+
+/// Password of the admins created by `TestContext::create_admin`
+pub const TEST_PASSWORD: &str = "correct horse battery staple";
+
+/// A canister ID no other test uses, rate limit buckets are shared by all tests
+pub fn unique_canister_id() -> String {
+    format!("test-{}", Uuid::new_v4())
+}
+
+/// Wait for background work started by a handler, up to two seconds
+pub async fn eventually<F, Fut>(mut check: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for _ in 0..40 {
+        if check().await {
+            return true;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+    }
+    false
+}
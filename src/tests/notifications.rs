@@ -0,0 +1,112 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+
+use crate::db::models::canister::{Canister, CanisterType};
+use crate::db::models::ledger_info::LedgerInfo;
+use crate::db::models::token_info::TokenInfo;
+use crate::tests::{eventually, unique_canister_id, TestContext};
+
+fn notification(miner_id: &str, event: &str, token_id: &str) -> Value {
+    json!({
+        "event": event,
+        "miner_id": miner_id,
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+        "data": { "token_id": token_id },
+    })
+}
+
+#[actix_web::test]
+async fn duplicate_notifications_are_answered_from_the_cache() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let miner_id = unique_canister_id();
+    let token_id = unique_canister_id();
+    let canister = Canister::new("2vxsx-fae".to_string(), token_id.clone(), CanisterType::Token, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    ctx.ic.with_token(TokenInfo::new(
+        token_id.clone(), "Test".to_string(), "TST".to_string(), 8, 1_000, 10, None, "{}".to_string(),
+    ));
+
+    let payload = notification(&miner_id, "solution_found", &token_id);
+    let req = test::TestRequest::post().uri("/miner-notifications").set_json(&payload).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let first: Value = test::read_body_json(resp).await;
+    assert_eq!(first["status"], "processed");
+
+    // The token is refreshed in the background
+    let repo = ctx.repo.clone();
+    let refreshed = eventually(|| {
+        let repo = repo.clone();
+        let token_id = token_id.clone();
+        async move { matches!(repo.tokens().find_by_canister_id(&token_id).await, Ok(Some(_))) }
+    }).await;
+    assert!(refreshed, "token info was not saved");
+
+    let req = test::TestRequest::post().uri("/miner-notifications").set_json(&payload).to_request();
+    let second: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(second, first);
+    assert_eq!(ctx.ic.call_count("get_token_all_info", &token_id), 1);
+}
+
+#[actix_web::test]
+async fn notifications_for_ledgers_refresh_ledger_info() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let miner_id = unique_canister_id();
+    let ledger_id = unique_canister_id();
+    let canister = Canister::new("2vxsx-fae".to_string(), ledger_id.clone(), CanisterType::Ledger, None);
+    ctx.repo.canisters().save(&canister).await.unwrap();
+    ctx.ic.with_ledger(LedgerInfo::new(
+        ledger_id.clone(), "Test".to_string(), "TST".to_string(), 8, "10000".to_string(), "1000000".to_string(),
+        None, "ICRC-1".to_string(), "{}".to_string(),
+    ));
+
+    let req = test::TestRequest::post().uri("/miner-notifications")
+        .set_json(notification(&miner_id, "token_connected", &ledger_id))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let repo = ctx.repo.clone();
+    let refreshed = eventually(|| {
+        let repo = repo.clone();
+        let ledger_id = ledger_id.clone();
+        async move { matches!(repo.ledgers().find_by_canister_id(&ledger_id).await, Ok(Some(_))) }
+    }).await;
+    assert!(refreshed, "ledger info was not saved");
+
+    let req = test::TestRequest::get().uri(&format!("/ledgers/{}", ledger_id)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"]["symbol"], "TST");
+    assert_eq!(ctx.ic.call_count("get_token_all_info", &ledger_id), 0);
+}
+
+#[actix_web::test]
+async fn unregistered_tokens_are_not_fetched() {
+    let ctx = TestContext::new();
+    let app = test::init_service(ctx.app()).await;
+    let miner_id = unique_canister_id();
+    let token_id = unique_canister_id();
+
+    let req = test::TestRequest::post().uri("/miner-notifications")
+        .set_json(notification(&miner_id, "token_connected", &token_id))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // Give the background task a chance to run
+    actix_web::rt::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(ctx.ic.call_count("get_token_all_info", &token_id), 0);
+}
+
+#[actix_web::test]
+async fn per_canister_rate_limits_are_kept_per_endpoint() {
+    use crate::api::rate_limit::{self, CanisterRoute};
+
+    // Use up the Claude bucket of one canister
+    let canister_id = unique_canister_id();
+    let limited = (0..100).any(|_| rate_limit::check_canister(CanisterRoute::Claude, &canister_id).is_err());
+    assert!(limited);
+
+    assert!(rate_limit::check_canister(CanisterRoute::MinerNotification, &canister_id).is_ok());
+    assert!(rate_limit::check_canister(CanisterRoute::Registration, &canister_id).is_ok());
+}
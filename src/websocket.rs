@@ -5,6 +5,8 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use crate::metrics;
+
 // How often heartbeat pings are sent
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 // How long before lack of client response causes a timeout
@@ -140,6 +142,10 @@ pub struct Disconnect {
 #[rtype(result = "()")]
 pub struct BroadcastMessage(pub String);
 
+#[derive(Message)]
+#[rtype(result = "usize")]
+pub struct SessionCount;
+
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct BroadcastNotification {
@@ -153,7 +159,9 @@ impl Handler<Connect> for WebSocketServer {
 
     fn handle(&mut self, msg: Connect, _: &mut actix::Context<Self>) {
         log::info!("WebSocket client connected: {}", msg.id);
-        self.sessions.insert(msg.id, msg.addr);
+        if self.sessions.insert(msg.id, msg.addr).is_none() {
+            metrics::WEBSOCKET_SESSIONS.inc();
+        }
     }
 }
 
@@ -163,7 +171,18 @@ impl Handler<Disconnect> for WebSocketServer {
 
     fn handle(&mut self, msg: Disconnect, _: &mut actix::Context<Self>) {
         log::info!("WebSocket client disconnected: {}", msg.id);
-        self.sessions.remove(&msg.id);
+        if self.sessions.remove(&msg.id).is_some() {
+            metrics::WEBSOCKET_SESSIONS.dec();
+        }
+    }
+}
+
+// Handler for SessionCount message
+impl Handler<SessionCount> for WebSocketServer {
+    type Result = usize;
+
+    fn handle(&mut self, _: SessionCount, _: &mut actix::Context<Self>) -> usize {
+        self.sessions.len()
     }
 }
 